@@ -1,11 +1,38 @@
 use std::env::current_dir;
 use std::fs::create_dir_all;
 
-use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
+use cosmwasm_schema::{export_schema, export_schema_with_title, remove_schemas, schema_for};
+use schemars::schema::RootSchema;
 
-use invoice::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use invoice::msg::{
+    ContractInfoResponse, ContractName, CreditResponse, Denylist, DepositResponse, ExecuteMsg,
+    InstantiateMsg, InvoiceHistory, InvoiceList, InvoiceResponse, MigrateMsg, PayerStatement,
+    PaymentResponse, QueryMsg, ReceiptList, ReceiptResponse, ReceivableOwnership, StatsResponse,
+    VersionInfoResponse,
+};
 use invoice::state::State;
 
+/// The response schema of every `QueryMsg` variant, keyed by the variant's JSON name.
+fn query_response_schemas() -> Vec<(&'static str, RootSchema)> {
+    vec![
+        ("get_invoice", schema_for!(InvoiceResponse)),
+        ("get_contract_info", schema_for!(ContractInfoResponse)),
+        ("get_contract_name", schema_for!(ContractName)),
+        ("get_version_info", schema_for!(VersionInfoResponse)),
+        ("get_stats", schema_for!(StatsResponse)),
+        ("get_payer_statement", schema_for!(PayerStatement)),
+        ("get_credit", schema_for!(CreditResponse)),
+        ("get_deposit", schema_for!(DepositResponse)),
+        ("get_receivable", schema_for!(ReceivableOwnership)),
+        ("get_invoice_history", schema_for!(InvoiceHistory)),
+        ("get_payment", schema_for!(PaymentResponse)),
+        ("get_receipt", schema_for!(ReceiptResponse)),
+        ("get_denylist", schema_for!(Denylist)),
+        ("list_receipts", schema_for!(ReceiptList)),
+        ("list_invoices", schema_for!(InvoiceList)),
+    ]
+}
+
 fn main() {
     let mut out_dir = current_dir().unwrap();
     out_dir.push("schema");
@@ -14,6 +41,75 @@ fn main() {
 
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
+
+    for (query, schema) in query_response_schemas() {
+        export_schema_with_title(&schema, &out_dir, &format!("{}_response", query));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schemars::schema::{Schema, SchemaObject};
+    use std::fs::File;
+    use std::path::Path;
+
+    #[test]
+    fn every_query_has_a_response_schema() {
+        let query_schema = schema_for!(QueryMsg);
+        let variants: Vec<String> = query_schema
+            .schema
+            .subschemas
+            .and_then(|subschemas| subschemas.one_of)
+            .expect("QueryMsg schema has no variants")
+            .into_iter()
+            .map(|variant| match variant {
+                Schema::Object(SchemaObject {
+                    object: Some(object),
+                    ..
+                }) => object.required.into_iter().next().unwrap(),
+                variant => panic!("unexpected variant schema: {:?}", variant),
+            })
+            .collect();
+
+        let responses: Vec<&str> = query_response_schemas()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+
+        for variant in &variants {
+            assert!(
+                responses.contains(&variant.as_str()),
+                "query {} has no response schema",
+                variant
+            );
+        }
+        assert_eq!(variants.len(), responses.len());
+    }
+
+    #[test]
+    fn exported_response_schemas_are_current() {
+        let out_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("schema");
+
+        for (query, mut schema) in query_response_schemas() {
+            let title = format!("{}_response", query);
+            if let Some(metadata) = &mut schema.schema.metadata {
+                metadata.title = Some(title.to_owned());
+            }
+
+            let path = out_dir.join(format!("{}.json", title));
+            let file = File::open(&path)
+                .unwrap_or_else(|_| panic!("{} is missing, run `make schema`", path.display()));
+            let exported: serde_json::Value = serde_json::from_reader(file).unwrap();
+            assert_eq!(
+                serde_json::to_value(&schema).unwrap(),
+                exported,
+                "{} is out of date, run `make schema`",
+                path.display()
+            );
+        }
+    }
 }
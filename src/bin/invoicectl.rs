@@ -0,0 +1,26 @@
+use invoice::cli::{Invocation, ProvenancedExecutor};
+use std::process;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = Invocation::parse(&args).and_then(|invocation| {
+        let executor = ProvenancedExecutor {
+            binary: invocation
+                .provenanced
+                .clone()
+                .unwrap_or_else(|| String::from("provenanced")),
+            from: invocation.from.clone(),
+            args: invocation.executor_args.clone(),
+        };
+        invocation.run(Some(executor))
+    });
+
+    match result {
+        Ok(output) => println!("{}", output),
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,634 @@
+//! Command-line interface for merchants, used by the `invoicectl` binary.
+//!
+//! Each command is turned into an `ExecuteMsg` or `QueryMsg` and checked with `Validate`. By
+//! default the JSON message is printed, ready to pass to `provenanced tx wasm execute`. With
+//! `--send` it goes through a [`Transport`] instead: [`ProvenancedExecutor`] shells out to
+//! `provenanced`, and tests plug in a mock.
+//!
+//! Only available with the `cli` feature.
+use cosmwasm_std::{to_vec, Addr, Binary, Coin, Event, Timestamp, Uint128};
+use serde_json::{json, Value};
+use std::process::Command;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::client::{AddInvoice, InvoiceClient, Transport, TxResult};
+use crate::msg::{
+    ContractInfoResponse, ExecuteMsg, InvoiceList, InvoiceResponse, PayerStatement, QueryMsg,
+    Validate,
+};
+use crate::ContractError;
+
+pub const USAGE: &str = "\
+usage: invoicectl [--contract <address>] [--send [--from <key>] [--provenanced <path>]] <command> [-- <provenanced args>]
+
+commands:
+  invoice add (--amount <base units> | --display-amount <decimal>) [--id <uuid>]
+              [--description <text>] [--payer <address>] [--due-date <nanos>] [--expires-at <nanos>]
+  invoice pay --id <uuid> --funds <amount><denom> [--payment-ref <ref>]
+  invoice cancel --id <uuid>
+  invoice show --id <uuid>
+  invoice list [--start-after <uuid>] [--limit <count>]
+  payer statement --payer <address> [--start-after <sequence>] [--limit <count>]
+  config show
+
+Without --send the JSON message is printed. An id is generated for `invoice add` when omitted.";
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("{0}\n\n{}", USAGE)]
+    Usage(String),
+
+    #[error("{0}")]
+    Invalid(#[from] ContractError),
+
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("{0}")]
+    Executor(String),
+}
+
+/// What a command asks the contract for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    Execute { msg: ExecuteMsg, funds: Vec<Coin> },
+    Query(QueryMsg),
+}
+
+/// A parsed command line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Invocation {
+    pub request: Request,
+    pub contract: Option<String>,
+    pub send: bool,
+    pub from: Option<String>,
+    pub provenanced: Option<String>,
+    // Passed through to provenanced after `--`
+    pub executor_args: Vec<String>,
+}
+
+impl Invocation {
+    pub fn parse<S: AsRef<str>>(args: &[S]) -> Result<Self, CliError> {
+        let mut flags = Flags::new(args)?;
+
+        let command: Vec<String> = flags.words.drain(..flags.words.len().min(2)).collect();
+        let command: Vec<&str> = command.iter().map(String::as_str).collect();
+
+        let request = match command.as_slice() {
+            ["invoice", "add"] => {
+                let id = match flags.take("id") {
+                    Some(id) => id,
+                    None => Uuid::new_v4().to_string(),
+                };
+                let mut msg = match (flags.parse("amount")?, flags.take("display-amount")) {
+                    (Some(amount), None) => AddInvoice::new(id, amount),
+                    (None, Some(display_amount)) => {
+                        AddInvoice::new(id, Uint128::zero()).display_amount(display_amount)
+                    }
+                    _ => {
+                        return Err(CliError::Usage(String::from(
+                            "one of --amount or --display-amount is required",
+                        )))
+                    }
+                };
+                if let Some(description) = flags.take("description") {
+                    msg = msg.description(description);
+                }
+                if let Some(payer) = flags.take("payer") {
+                    msg = msg.payer(payer);
+                }
+                if let Some(due_date) = flags.parse("due-date")? {
+                    msg = msg.due_date(Timestamp::from_nanos(due_date));
+                }
+                if let Some(expires_at) = flags.parse("expires-at")? {
+                    msg = msg.expires_at(Timestamp::from_nanos(expires_at));
+                }
+                Request::Execute {
+                    msg: msg.into(),
+                    funds: vec![],
+                }
+            }
+            ["invoice", "pay"] => Request::Execute {
+                msg: ExecuteMsg::PayInvoice {
+                    id: flags.required("id")?,
+                    payment_ref: flags.take("payment-ref"),
+                },
+                funds: vec![parse_coin(&flags.required("funds")?)?],
+            },
+            ["invoice", "cancel"] => Request::Execute {
+                msg: ExecuteMsg::CancelInvoice {
+                    id: flags.required("id")?,
+                },
+                funds: vec![],
+            },
+            ["invoice", "show"] => Request::Query(QueryMsg::GetInvoice {
+                id: flags.required("id")?,
+            }),
+            ["invoice", "list"] => Request::Query(QueryMsg::ListInvoices {
+                start_after: flags.take("start-after"),
+                limit: flags.parse("limit")?,
+            }),
+            ["payer", "statement"] => Request::Query(QueryMsg::GetPayerStatement {
+                payer: flags.required("payer")?,
+                start_after: flags.parse("start-after")?,
+                limit: flags.parse("limit")?,
+            }),
+            ["config", "show"] => Request::Query(QueryMsg::GetContractInfo {}),
+            _ => return Err(CliError::Usage(String::from("unknown command"))),
+        };
+
+        let invocation = Invocation {
+            request,
+            contract: flags.take("contract"),
+            send: flags.take_switch("send"),
+            from: flags.take("from"),
+            provenanced: flags.take("provenanced"),
+            executor_args: std::mem::take(&mut flags.rest),
+        };
+        flags.ensure_consumed()?;
+        Ok(invocation)
+    }
+
+    /// Validate the request, then print its message or send it with `transport`.
+    pub fn run<T: Transport>(&self, transport: Option<T>) -> Result<String, CliError> {
+        match &self.request {
+            Request::Execute { msg, .. } => msg.validate()?,
+            Request::Query(msg) => msg.validate()?,
+        }
+
+        let transport = match transport {
+            Some(transport) if self.send => transport,
+            _ => return self.print(),
+        };
+        let contract = self
+            .contract
+            .as_ref()
+            .ok_or_else(|| CliError::Usage(String::from("--contract is required with --send")))?;
+        let mut client = InvoiceClient::new(Addr::unchecked(contract), transport);
+
+        let output = match &self.request {
+            Request::Execute { msg, funds } => {
+                let tx = client.execute(msg, funds).map_err(executor_error)?;
+                json!({ "tx_hash": tx.tx_hash, "height": tx.height })
+            }
+            Request::Query(msg @ QueryMsg::GetInvoice { .. }) => serde_json::to_value(
+                client
+                    .query::<InvoiceResponse>(msg)
+                    .map_err(executor_error)?,
+            )?,
+            Request::Query(msg @ QueryMsg::ListInvoices { .. }) => {
+                serde_json::to_value(client.query::<InvoiceList>(msg).map_err(executor_error)?)?
+            }
+            Request::Query(msg @ QueryMsg::GetPayerStatement { .. }) => serde_json::to_value(
+                client
+                    .query::<PayerStatement>(msg)
+                    .map_err(executor_error)?,
+            )?,
+            Request::Query(msg) => serde_json::to_value(
+                client
+                    .query::<ContractInfoResponse>(msg)
+                    .map_err(executor_error)?,
+            )?,
+        };
+        Ok(serde_json::to_string_pretty(&output)?)
+    }
+
+    fn print(&self) -> Result<String, CliError> {
+        let mut output = match &self.request {
+            Request::Execute { msg, .. } => serde_json::to_string(msg)?,
+            Request::Query(msg) => serde_json::to_string(msg)?,
+        };
+        if let Request::Execute { funds, .. } = &self.request {
+            for coin in funds {
+                output.push_str(&format!("\n# --amount {}", coin));
+            }
+        }
+        Ok(output)
+    }
+}
+
+fn executor_error<E: std::fmt::Display>(error: E) -> CliError {
+    CliError::Executor(error.to_string())
+}
+
+/// Parse an amount with its denom, such as `100usdx.c`.
+fn parse_coin(value: &str) -> Result<Coin, CliError> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .filter(|split| *split > 0)
+        .ok_or_else(|| CliError::Usage(format!("invalid funds {}", value)))?;
+    let amount: Uint128 = value[..split]
+        .parse()
+        .map_err(|_| CliError::Usage(format!("invalid funds {}", value)))?;
+    Ok(Coin::new(amount.u128(), &value[split..]))
+}
+
+/// Positional words and `--name value` flags, in any order.
+struct Flags {
+    words: Vec<String>,
+    values: Vec<(String, Option<String>)>,
+    rest: Vec<String>,
+}
+
+const SWITCHES: &[&str] = &["send"];
+
+impl Flags {
+    fn new<S: AsRef<str>>(args: &[S]) -> Result<Self, CliError> {
+        let mut flags = Flags {
+            words: vec![],
+            values: vec![],
+            rest: vec![],
+        };
+
+        let mut args = args.iter().map(|arg| arg.as_ref().to_owned());
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                flags.rest = args.collect();
+                break;
+            }
+            match arg.strip_prefix("--") {
+                Some(name) if SWITCHES.contains(&name) => flags.values.push((name.into(), None)),
+                Some(name) => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| CliError::Usage(format!("--{} needs a value", name)))?;
+                    flags.values.push((name.into(), Some(value)));
+                }
+                None => flags.words.push(arg),
+            }
+        }
+        Ok(flags)
+    }
+
+    fn take(&mut self, name: &str) -> Option<String> {
+        let index = self.values.iter().position(|(key, _)| key == name)?;
+        self.values.remove(index).1
+    }
+
+    fn take_switch(&mut self, name: &str) -> bool {
+        match self.values.iter().position(|(key, _)| key == name) {
+            Some(index) => {
+                self.values.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn required(&mut self, name: &str) -> Result<String, CliError> {
+        self.take(name)
+            .ok_or_else(|| CliError::Usage(format!("--{} is required", name)))
+    }
+
+    fn parse<T: std::str::FromStr>(&mut self, name: &str) -> Result<Option<T>, CliError> {
+        self.take(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| CliError::Usage(format!("invalid --{} {}", name, value)))
+            })
+            .transpose()
+    }
+
+    fn ensure_consumed(&self) -> Result<(), CliError> {
+        if let Some(word) = self.words.first() {
+            return Err(CliError::Usage(format!("unexpected argument {}", word)));
+        }
+        if let Some((name, _)) = self.values.first() {
+            return Err(CliError::Usage(format!("unknown option --{}", name)));
+        }
+        Ok(())
+    }
+}
+
+/// Sends messages with the `provenanced` command.
+pub struct ProvenancedExecutor {
+    pub binary: String,
+    pub from: Option<String>,
+    pub args: Vec<String>,
+}
+
+impl ProvenancedExecutor {
+    fn run(&self, args: &[String]) -> Result<Value, CliError> {
+        let output = Command::new(&self.binary)
+            .args(args)
+            .args(&self.args)
+            .output()
+            .map_err(|error| CliError::Executor(format!("{}: {}", self.binary, error)))?;
+        if !output.status.success() {
+            return Err(CliError::Executor(
+                String::from_utf8_lossy(&output.stderr).trim().into(),
+            ));
+        }
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+}
+
+impl Transport for ProvenancedExecutor {
+    type Error = CliError;
+
+    fn execute(
+        &mut self,
+        contract: &Addr,
+        msg: &Binary,
+        funds: &[Coin],
+    ) -> Result<TxResult, CliError> {
+        let mut args = vec![
+            "tx".into(),
+            "wasm".into(),
+            "execute".into(),
+            contract.to_string(),
+            String::from_utf8_lossy(msg.as_slice()).into(),
+        ];
+        if !funds.is_empty() {
+            let amount: Vec<String> = funds.iter().map(Coin::to_string).collect();
+            args.extend(vec!["--amount".into(), amount.join(",")]);
+        }
+        if let Some(from) = &self.from {
+            args.extend(vec!["--from".into(), from.to_owned()]);
+        }
+        // wait for the block so the result carries the transaction's events
+        args.extend(vec![
+            "--broadcast-mode".into(),
+            "block".into(),
+            "--yes".into(),
+            "-o".into(),
+            "json".into(),
+        ]);
+
+        parse_tx_output(&self.run(&args)?)
+    }
+
+    fn query(&self, contract: &Addr, msg: &Binary) -> Result<Binary, CliError> {
+        let args = vec![
+            "query".into(),
+            "wasm".into(),
+            "contract-state".into(),
+            "smart".into(),
+            contract.to_string(),
+            String::from_utf8_lossy(msg.as_slice()).into(),
+            "-o".into(),
+            "json".into(),
+        ];
+
+        let output = self.run(&args)?;
+        Ok(Binary::from(
+            to_vec(&output["data"]).map_err(executor_error)?,
+        ))
+    }
+}
+
+/// Read the result of `provenanced tx ... -o json`.
+fn parse_tx_output(output: &Value) -> Result<TxResult, CliError> {
+    if output["code"].as_u64().unwrap_or_default() != 0 {
+        return Err(CliError::Executor(
+            output["raw_log"].as_str().unwrap_or_default().into(),
+        ));
+    }
+
+    let events = output["logs"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|log| log["events"].as_array().into_iter().flatten())
+        .map(|event| {
+            let attributes =
+                event["attributes"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|attribute| {
+                        (
+                            attribute["key"].as_str().unwrap_or_default(),
+                            attribute["value"].as_str().unwrap_or_default(),
+                        )
+                    });
+            Event::new(event["type"].as_str().unwrap_or_default()).add_attributes(attributes)
+        })
+        .collect();
+
+    Ok(TxResult {
+        tx_hash: output["txhash"].as_str().unwrap_or_default().into(),
+        height: output["height"]
+            .as_str()
+            .and_then(|height| height.parse().ok())
+            .unwrap_or_default(),
+        events,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::tests::test_state;
+    use crate::state::State;
+    use cosmwasm_std::{coin, from_binary, to_binary, StdError};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const INVOICE_ID: &str = "63069195-bc51-41bd-80d7-0ab84b98e283";
+
+    type Sent = (ExecuteMsg, Vec<Coin>);
+
+    /// Records what is sent and answers queries with the contract info.
+    #[derive(Clone, Default)]
+    struct MockExecutor {
+        sent: Rc<RefCell<Vec<Sent>>>,
+    }
+
+    impl Transport for MockExecutor {
+        type Error = StdError;
+
+        fn execute(
+            &mut self,
+            _contract: &Addr,
+            msg: &Binary,
+            funds: &[Coin],
+        ) -> Result<TxResult, StdError> {
+            self.sent
+                .borrow_mut()
+                .push((from_binary(msg)?, funds.to_vec()));
+            Ok(TxResult {
+                tx_hash: String::from("ABCD"),
+                height: 12345,
+                events: vec![],
+            })
+        }
+
+        fn query(&self, _contract: &Addr, msg: &Binary) -> Result<Binary, StdError> {
+            match from_binary(msg)? {
+                QueryMsg::GetContractInfo {} => to_binary(&State {
+                    admin: Addr::unchecked("admin"),
+                    recipient: Addr::unchecked("recipient"),
+                    denom: "usdx.c".into(),
+                    business_name: "Shoe Co, LLC".into(),
+                    ..test_state()
+                }),
+                _ => Err(StdError::not_found("invoice::state::Invoice")),
+            }
+        }
+    }
+
+    fn parse_args(args: &str) -> Invocation {
+        Invocation::parse(&args.split(' ').collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn add_generates_id_and_prints_message() {
+        let invocation = parse_args("invoice add --amount 100 --payer payer --description shoes");
+
+        let output = invocation.run::<MockExecutor>(None).unwrap();
+        let msg: ExecuteMsg = serde_json::from_str(&output).unwrap();
+        match msg {
+            ExecuteMsg::AddInvoice {
+                id,
+                amount,
+                description,
+                payer,
+                ..
+            } => {
+                assert!(Uuid::parse_str(&id).is_ok());
+                assert_eq!(Uint128::new(100), amount);
+                assert_eq!(Some("shoes".into()), description);
+                assert_eq!(Some("payer".into()), payer);
+            }
+            msg => panic!("unexpected message: {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn pay_sends_funds_through_executor() {
+        let executor = MockExecutor::default();
+        let invocation = parse_args(&format!(
+            "--contract tp1contract --send invoice pay --id {} --funds 100usdx.c",
+            INVOICE_ID
+        ));
+
+        let output = invocation.run(Some(executor.clone())).unwrap();
+        assert!(output.contains("\"tx_hash\": \"ABCD\""));
+        assert_eq!(
+            vec![(
+                ExecuteMsg::PayInvoice {
+                    id: INVOICE_ID.into(),
+                    payment_ref: None,
+                },
+                vec![coin(100, "usdx.c")]
+            )],
+            *executor.sent.borrow()
+        );
+    }
+
+    #[test]
+    fn validates_before_sending() {
+        let executor = MockExecutor::default();
+        let invocation = parse_args("--contract tp1contract --send invoice cancel --id 12345");
+
+        match invocation.run(Some(executor.clone())) {
+            Err(CliError::Invalid(ContractError::InvalidFields { fields })) => {
+                assert_eq!(vec!["id"], fields)
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert!(executor.sent.borrow().is_empty());
+    }
+
+    #[test]
+    fn config_show_decodes_state() {
+        let invocation = parse_args("--contract tp1contract --send config show");
+
+        let output = invocation.run(Some(MockExecutor::default())).unwrap();
+        let state: State = serde_json::from_str(&output).unwrap();
+        assert_eq!("usdx.c", state.denom);
+
+        let error = parse_args(
+            "--contract tp1contract --send invoice show --id 63069195-bc51-41bd-80d7-0ab84b98e283",
+        )
+        .run(Some(MockExecutor::default()))
+        .unwrap_err();
+        assert!(matches!(error, CliError::Executor(..)));
+    }
+
+    #[test]
+    fn lists_invoices_and_payer_statements() {
+        assert_eq!(
+            Request::Query(QueryMsg::ListInvoices {
+                start_after: Some(INVOICE_ID.into()),
+                limit: Some(5),
+            }),
+            parse_args(&format!(
+                "invoice list --start-after {} --limit 5",
+                INVOICE_ID
+            ))
+            .request
+        );
+        assert_eq!(
+            Request::Query(QueryMsg::GetPayerStatement {
+                payer: "payer".into(),
+                start_after: None,
+                limit: None,
+            }),
+            parse_args("payer statement --payer payer").request
+        );
+    }
+
+    #[test]
+    fn rejects_bad_usage() {
+        for args in &[
+            "invoice",
+            "invoice add",
+            "invoice add --amount ten",
+            "invoice add --amount 10 --display-amount 1.5",
+            "invoice pay --id 1 --funds usdx.c",
+            "invoice show --id 1 --unknown 2",
+            "config show extra",
+        ] {
+            match Invocation::parse(&args.split(' ').collect::<Vec<_>>()) {
+                Err(CliError::Usage(..)) => {}
+                result => panic!("expected usage error for {}: {:?}", args, result),
+            }
+        }
+    }
+
+    #[test]
+    fn passes_executor_args_through() {
+        let invocation = parse_args("config show -- --testnet --node tcp://localhost:26657");
+        assert_eq!(
+            vec!["--testnet", "--node", "tcp://localhost:26657"],
+            invocation.executor_args
+        );
+    }
+
+    #[test]
+    fn parses_provenanced_tx_output() {
+        let output = json!({
+            "height": "42",
+            "txhash": "F00D",
+            "code": 0,
+            "logs": [{
+                "events": [{
+                    "type": "wasm-invoice_cancelled",
+                    "attributes": [
+                        { "key": "event_version", "value": "2" },
+                        { "key": "id", "value": INVOICE_ID },
+                        { "key": "amount", "value": "5" },
+                        { "key": "denom", "value": "usdx.c" }
+                    ]
+                }]
+            }]
+        });
+
+        let tx = parse_tx_output(&output).unwrap();
+        assert_eq!(42, tx.height);
+        assert_eq!(
+            INVOICE_ID,
+            tx.events::<crate::events::InvoiceCancelled>().unwrap()[0].id
+        );
+
+        let failed = json!({ "code": 5, "raw_log": "insufficient funds" });
+        assert!(matches!(
+            parse_tx_output(&failed),
+            Err(CliError::Executor(..))
+        ));
+    }
+}
@@ -0,0 +1,725 @@
+//! Typed client for the invoice contract.
+//!
+//! [`InvoiceClient`] builds every `ExecuteMsg` and `QueryMsg`, checks it with [`Validate`] before
+//! anything is sent, and decodes query responses and transaction events into the contract's own
+//! types. Sending is left to a [`Transport`], so a real chain client or a mock can be plugged in.
+//!
+//! Only available with the `client` feature.
+use cosmwasm_std::{
+    from_binary, to_binary, Addr, Binary, Coin, Event, StdError, Timestamp, Uint128,
+};
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use crate::events::{parse_events, ContractEvent};
+use crate::msg::{
+    ContractInfoResponse, ContractName, ContractNameUpdate, CreditResponse, Denylist,
+    DepositResponse, ExecuteMsg, InvoiceHistory, InvoiceList, InvoiceResponse, PayerStatement,
+    PaymentResponse, QueryMsg, ReceiptList, ReceiptResponse, ReceivableOwnership, SignedInvoice,
+    StatsResponse, Validate, VersionInfoResponse,
+};
+use crate::state::FinancingTerms;
+use crate::ContractError;
+
+/// Sends messages to a deployed contract.
+pub trait Transport {
+    type Error: std::error::Error + 'static;
+
+    /// Broadcast an execute message and wait for the transaction result.
+    fn execute(
+        &mut self,
+        contract: &Addr,
+        msg: &Binary,
+        funds: &[Coin],
+    ) -> Result<TxResult, Self::Error>;
+
+    /// Run a smart query.
+    fn query(&self, contract: &Addr, msg: &Binary) -> Result<Binary, Self::Error>;
+}
+
+/// The outcome of a broadcast execute message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TxResult {
+    pub tx_hash: String,
+    pub height: u64,
+    pub events: Vec<Event>,
+}
+
+impl TxResult {
+    /// Every event of type `T` emitted by the transaction.
+    pub fn events<T: ContractEvent>(&self) -> Result<Vec<T>, StdError> {
+        parse_events(&self.events)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ClientError<E: std::error::Error + 'static> {
+    #[error("{0}")]
+    Invalid(#[from] ContractError),
+
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Transport failed: {0}")]
+    Transport(#[source] E),
+}
+
+/// An `add_invoice` message with its optional fields.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AddInvoice {
+    id: String,
+    amount: Uint128,
+    description: Option<String>,
+    due_date: Option<Timestamp>,
+    expires_at: Option<Timestamp>,
+    payer: Option<String>,
+    display_amount: Option<String>,
+}
+
+impl AddInvoice {
+    pub fn new<S: Into<String>>(id: S, amount: Uint128) -> Self {
+        AddInvoice {
+            id: id.into(),
+            amount,
+            description: None,
+            due_date: None,
+            expires_at: None,
+            payer: None,
+            display_amount: None,
+        }
+    }
+
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn due_date(mut self, due_date: Timestamp) -> Self {
+        self.due_date = Some(due_date);
+        self
+    }
+
+    pub fn expires_at(mut self, expires_at: Timestamp) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn payer<S: Into<String>>(mut self, payer: S) -> Self {
+        self.payer = Some(payer.into());
+        self
+    }
+
+    /// Bill a decimal amount in the denom's display unit instead of base units.
+    pub fn display_amount<S: Into<String>>(mut self, display_amount: S) -> Self {
+        self.amount = Uint128::zero();
+        self.display_amount = Some(display_amount.into());
+        self
+    }
+}
+
+impl From<AddInvoice> for ExecuteMsg {
+    fn from(msg: AddInvoice) -> Self {
+        ExecuteMsg::AddInvoice {
+            id: msg.id,
+            amount: msg.amount,
+            description: msg.description,
+            due_date: msg.due_date,
+            expires_at: msg.expires_at,
+            payer: msg.payer,
+            display_amount: msg.display_amount,
+        }
+    }
+}
+
+/// An `amend_invoice` message with its optional fields.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AmendInvoice {
+    id: String,
+    amount: Uint128,
+    description: Option<String>,
+    due_date: Option<Timestamp>,
+}
+
+impl AmendInvoice {
+    pub fn new<S: Into<String>>(id: S, amount: Uint128) -> Self {
+        AmendInvoice {
+            id: id.into(),
+            amount,
+            description: None,
+            due_date: None,
+        }
+    }
+
+    pub fn description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn due_date(mut self, due_date: Timestamp) -> Self {
+        self.due_date = Some(due_date);
+        self
+    }
+}
+
+impl From<AmendInvoice> for ExecuteMsg {
+    fn from(msg: AmendInvoice) -> Self {
+        ExecuteMsg::AmendInvoice {
+            id: msg.id,
+            amount: msg.amount,
+            description: msg.description,
+            due_date: msg.due_date,
+        }
+    }
+}
+
+/// An `update_config` message. Fields left unset are unchanged.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UpdateConfig {
+    recipient: Option<String>,
+    business_name: Option<String>,
+    contract_name: Option<ContractNameUpdate>,
+    attribute_name: Option<String>,
+}
+
+impl UpdateConfig {
+    pub fn new() -> Self {
+        UpdateConfig::default()
+    }
+
+    pub fn recipient<S: Into<String>>(mut self, recipient: S) -> Self {
+        self.recipient = Some(recipient.into());
+        self
+    }
+
+    pub fn business_name<S: Into<String>>(mut self, business_name: S) -> Self {
+        self.business_name = Some(business_name.into());
+        self
+    }
+
+    pub fn bind_contract_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.contract_name = Some(ContractNameUpdate::Bind { name: name.into() });
+        self
+    }
+
+    pub fn unbind_contract_name(mut self) -> Self {
+        self.contract_name = Some(ContractNameUpdate::Unbind {});
+        self
+    }
+
+    pub fn bind_attribute_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.attribute_name = Some(name.into());
+        self
+    }
+}
+
+impl From<UpdateConfig> for ExecuteMsg {
+    fn from(msg: UpdateConfig) -> Self {
+        ExecuteMsg::UpdateConfig {
+            recipient: msg.recipient,
+            business_name: msg.business_name,
+            contract_name: msg.contract_name,
+            attribute_name: msg.attribute_name,
+        }
+    }
+}
+
+/// Which receipts `list_receipts` returns.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReceiptFilter {
+    Invoice(String),
+    Payer(String),
+}
+
+/// A client for one deployed invoice contract.
+pub struct InvoiceClient<T: Transport> {
+    contract: Addr,
+    transport: T,
+}
+
+type ClientResult<T, E> = Result<T, ClientError<E>>;
+
+impl<T: Transport> InvoiceClient<T> {
+    pub fn new(contract: Addr, transport: T) -> Self {
+        InvoiceClient {
+            contract,
+            transport,
+        }
+    }
+
+    pub fn contract(&self) -> &Addr {
+        &self.contract
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Validate and broadcast any execute message.
+    pub fn execute(
+        &mut self,
+        msg: &ExecuteMsg,
+        funds: &[Coin],
+    ) -> ClientResult<TxResult, T::Error> {
+        msg.validate()?;
+        self.transport
+            .execute(&self.contract, &to_binary(msg)?, funds)
+            .map_err(ClientError::Transport)
+    }
+
+    /// Validate any query message and decode its response.
+    pub fn query<R: DeserializeOwned>(&self, msg: &QueryMsg) -> ClientResult<R, T::Error> {
+        msg.validate()?;
+        let response = self
+            .transport
+            .query(&self.contract, &to_binary(msg)?)
+            .map_err(ClientError::Transport)?;
+        Ok(from_binary(&response)?)
+    }
+
+    pub fn add_invoice(&mut self, msg: AddInvoice) -> ClientResult<TxResult, T::Error> {
+        self.execute(&msg.into(), &[])
+    }
+
+    pub fn pay_invoice<S: Into<String>>(
+        &mut self,
+        id: S,
+        payment_ref: Option<String>,
+        funds: &[Coin],
+    ) -> ClientResult<TxResult, T::Error> {
+        let msg = ExecuteMsg::PayInvoice {
+            id: id.into(),
+            payment_ref,
+        };
+        self.execute(&msg, funds)
+    }
+
+    pub fn pay_with_signed_invoice(
+        &mut self,
+        invoice: SignedInvoice,
+        signature: Binary,
+        funds: &[Coin],
+    ) -> ClientResult<TxResult, T::Error> {
+        let msg = ExecuteMsg::PayWithSignedInvoice { invoice, signature };
+        self.execute(&msg, funds)
+    }
+
+    pub fn cancel_invoice<S: Into<String>>(&mut self, id: S) -> ClientResult<TxResult, T::Error> {
+        self.execute(&ExecuteMsg::CancelInvoice { id: id.into() }, &[])
+    }
+
+    pub fn amend_invoice(&mut self, msg: AmendInvoice) -> ClientResult<TxResult, T::Error> {
+        self.execute(&msg.into(), &[])
+    }
+
+    pub fn issue_credit_note<S: Into<String>, R: Into<String>>(
+        &mut self,
+        invoice_id: S,
+        amount: Uint128,
+        reason: R,
+    ) -> ClientResult<TxResult, T::Error> {
+        let msg = ExecuteMsg::IssueCreditNote {
+            invoice_id: invoice_id.into(),
+            amount,
+            reason: reason.into(),
+        };
+        self.execute(&msg, &[])
+    }
+
+    pub fn accept_invoice<S: Into<String>>(&mut self, id: S) -> ClientResult<TxResult, T::Error> {
+        self.execute(&ExecuteMsg::AcceptInvoice { id: id.into() }, &[])
+    }
+
+    pub fn reject_invoice<S: Into<String>, R: Into<String>>(
+        &mut self,
+        id: S,
+        reason: R,
+    ) -> ClientResult<TxResult, T::Error> {
+        let msg = ExecuteMsg::Reject {
+            id: id.into(),
+            reason: reason.into(),
+        };
+        self.execute(&msg, &[])
+    }
+
+    pub fn prune_expired(&mut self, limit: Option<u32>) -> ClientResult<TxResult, T::Error> {
+        self.execute(&ExecuteMsg::PruneExpired { limit }, &[])
+    }
+
+    pub fn withdraw_credit(&mut self) -> ClientResult<TxResult, T::Error> {
+        self.execute(&ExecuteMsg::WithdrawCredit {}, &[])
+    }
+
+    pub fn deposit(&mut self, funds: &[Coin]) -> ClientResult<TxResult, T::Error> {
+        self.execute(&ExecuteMsg::Deposit {}, funds)
+    }
+
+    pub fn withdraw(&mut self, amount: Coin) -> ClientResult<TxResult, T::Error> {
+        self.execute(&ExecuteMsg::Withdraw { amount }, &[])
+    }
+
+    pub fn settle_from_deposit<S: Into<String>>(
+        &mut self,
+        id: S,
+    ) -> ClientResult<TxResult, T::Error> {
+        self.execute(&ExecuteMsg::SettleFromDeposit { id: id.into() }, &[])
+    }
+
+    pub fn transfer_receivable<S: Into<String>, P: Into<String>>(
+        &mut self,
+        id: S,
+        new_payee: P,
+    ) -> ClientResult<TxResult, T::Error> {
+        let msg = ExecuteMsg::TransferReceivable {
+            id: id.into(),
+            new_payee: new_payee.into(),
+        };
+        self.execute(&msg, &[])
+    }
+
+    pub fn fund_invoice<S: Into<String>>(
+        &mut self,
+        id: S,
+        funds: &[Coin],
+    ) -> ClientResult<TxResult, T::Error> {
+        self.execute(&ExecuteMsg::FundInvoice { id: id.into() }, funds)
+    }
+
+    pub fn update_config(&mut self, msg: UpdateConfig) -> ClientResult<TxResult, T::Error> {
+        self.execute(&msg.into(), &[])
+    }
+
+    pub fn update_financing(
+        &mut self,
+        terms: Option<FinancingTerms>,
+        financiers: Vec<String>,
+    ) -> ClientResult<TxResult, T::Error> {
+        self.execute(&ExecuteMsg::UpdateFinancing { terms, financiers }, &[])
+    }
+
+    pub fn update_signing_key(
+        &mut self,
+        public_key: Option<Binary>,
+    ) -> ClientResult<TxResult, T::Error> {
+        self.execute(&ExecuteMsg::UpdateSigningKey { public_key }, &[])
+    }
+
+    pub fn update_payment_attributes(&mut self, enabled: bool) -> ClientResult<TxResult, T::Error> {
+        self.execute(&ExecuteMsg::UpdatePaymentAttributes { enabled }, &[])
+    }
+
+    pub fn update_required_attributes(
+        &mut self,
+        attributes: Vec<String>,
+    ) -> ClientResult<TxResult, T::Error> {
+        self.execute(&ExecuteMsg::UpdateRequiredAttributes { attributes }, &[])
+    }
+
+    pub fn update_scope_specification(
+        &mut self,
+        specification_id: Option<String>,
+    ) -> ClientResult<TxResult, T::Error> {
+        let msg = ExecuteMsg::UpdateScopeSpecification { specification_id };
+        self.execute(&msg, &[])
+    }
+
+    pub fn add_to_denylist<S: Into<String>>(
+        &mut self,
+        address: S,
+    ) -> ClientResult<TxResult, T::Error> {
+        let msg = ExecuteMsg::AddToDenylist {
+            address: address.into(),
+        };
+        self.execute(&msg, &[])
+    }
+
+    pub fn remove_from_denylist<S: Into<String>>(
+        &mut self,
+        address: S,
+    ) -> ClientResult<TxResult, T::Error> {
+        let msg = ExecuteMsg::RemoveFromDenylist {
+            address: address.into(),
+        };
+        self.execute(&msg, &[])
+    }
+
+    pub fn get_invoice<S: Into<String>>(&self, id: S) -> ClientResult<InvoiceResponse, T::Error> {
+        self.query(&QueryMsg::GetInvoice { id: id.into() })
+    }
+
+    pub fn get_contract_info(&self) -> ClientResult<ContractInfoResponse, T::Error> {
+        self.query(&QueryMsg::GetContractInfo {})
+    }
+
+    pub fn get_contract_name(&self) -> ClientResult<ContractName, T::Error> {
+        self.query(&QueryMsg::GetContractName {})
+    }
+
+    pub fn get_version_info(&self) -> ClientResult<VersionInfoResponse, T::Error> {
+        self.query(&QueryMsg::GetVersionInfo {})
+    }
+
+    pub fn get_stats(&self) -> ClientResult<StatsResponse, T::Error> {
+        self.query(&QueryMsg::GetStats {})
+    }
+
+    pub fn get_payer_statement<S: Into<String>>(
+        &self,
+        payer: S,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> ClientResult<PayerStatement, T::Error> {
+        self.query(&QueryMsg::GetPayerStatement {
+            payer: payer.into(),
+            start_after,
+            limit,
+        })
+    }
+
+    pub fn get_credit<S: Into<String>>(
+        &self,
+        address: S,
+    ) -> ClientResult<CreditResponse, T::Error> {
+        self.query(&QueryMsg::GetCredit {
+            address: address.into(),
+        })
+    }
+
+    pub fn get_deposit<S: Into<String>>(
+        &self,
+        address: S,
+    ) -> ClientResult<DepositResponse, T::Error> {
+        self.query(&QueryMsg::GetDeposit {
+            address: address.into(),
+        })
+    }
+
+    pub fn get_receivable<S: Into<String>>(
+        &self,
+        id: S,
+    ) -> ClientResult<ReceivableOwnership, T::Error> {
+        self.query(&QueryMsg::GetReceivable { id: id.into() })
+    }
+
+    pub fn get_invoice_history<S: Into<String>>(
+        &self,
+        id: S,
+    ) -> ClientResult<InvoiceHistory, T::Error> {
+        self.query(&QueryMsg::GetInvoiceHistory { id: id.into() })
+    }
+
+    pub fn get_payment<S: Into<String>, R: Into<String>>(
+        &self,
+        invoice_id: S,
+        payment_ref: R,
+    ) -> ClientResult<PaymentResponse, T::Error> {
+        self.query(&QueryMsg::GetPayment {
+            invoice_id: invoice_id.into(),
+            payment_ref: payment_ref.into(),
+        })
+    }
+
+    pub fn get_receipt<S: Into<String>>(
+        &self,
+        invoice_id: S,
+        sequence: u64,
+    ) -> ClientResult<ReceiptResponse, T::Error> {
+        self.query(&QueryMsg::GetReceipt {
+            invoice_id: invoice_id.into(),
+            sequence,
+        })
+    }
+
+    pub fn list_receipts(
+        &self,
+        filter: ReceiptFilter,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> ClientResult<ReceiptList, T::Error> {
+        let (invoice_id, payer) = match filter {
+            ReceiptFilter::Invoice(invoice_id) => (Some(invoice_id), None),
+            ReceiptFilter::Payer(payer) => (None, Some(payer)),
+        };
+        self.query(&QueryMsg::ListReceipts {
+            invoice_id,
+            payer,
+            start_after,
+            limit,
+        })
+    }
+
+    pub fn get_denylist(
+        &self,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> ClientResult<Denylist, T::Error> {
+        self.query(&QueryMsg::GetDenylist { start_after, limit })
+    }
+
+    pub fn list_invoices(
+        &self,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> ClientResult<InvoiceList, T::Error> {
+        self.query(&QueryMsg::ListInvoices { start_after, limit })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::tests::test_state;
+    use crate::contract::{execute, query};
+    use crate::events::{InvoiceAdded, InvoicePaid};
+    use crate::state::{config, State};
+    use cosmwasm_std::testing::{mock_env, mock_info, MockApi};
+    use cosmwasm_std::{coin, MemoryStorage, OwnedDeps};
+    use provwasm_mocks::{mock_dependencies, ProvenanceMockQuerier};
+    use provwasm_std::ProvenanceQuery;
+    use std::cell::Cell;
+
+    const ADMIN: &str = "contract_admin";
+    const DENOM: &str = "usdx.c";
+    const INVOICE_ID: &str = "63069195-bc51-41bd-80d7-0ab84b98e283";
+
+    /// Runs messages straight against the contract, as `sender`.
+    struct MockTransport {
+        deps: OwnedDeps<MemoryStorage, MockApi, ProvenanceMockQuerier, ProvenanceQuery>,
+        sender: String,
+        sent: Cell<usize>,
+    }
+
+    impl Transport for MockTransport {
+        type Error = StdError;
+
+        fn execute(
+            &mut self,
+            _contract: &Addr,
+            msg: &Binary,
+            funds: &[Coin],
+        ) -> Result<TxResult, StdError> {
+            self.sent.set(self.sent.get() + 1);
+            let env = mock_env();
+            let response = execute(
+                self.deps.as_mut(),
+                env.clone(),
+                mock_info(&self.sender, funds),
+                from_binary(msg)?,
+            )
+            .map_err(|error| StdError::generic_err(error.to_string()))?;
+
+            Ok(TxResult {
+                tx_hash: format!("{:064X}", self.sent.get()),
+                height: env.block.height,
+                events: response.events,
+            })
+        }
+
+        fn query(&self, _contract: &Addr, msg: &Binary) -> Result<Binary, StdError> {
+            self.sent.set(self.sent.get() + 1);
+            query(self.deps.as_ref(), mock_env(), from_binary(msg)?)
+        }
+    }
+
+    fn client() -> InvoiceClient<MockTransport> {
+        let mut deps = mock_dependencies(&[]);
+        config(&mut deps.storage)
+            .save(&State {
+                admin: Addr::unchecked(ADMIN),
+                recipient: Addr::unchecked("recipient"),
+                denom: DENOM.into(),
+                business_name: "Shoe Co, LLC".into(),
+                ..test_state()
+            })
+            .unwrap();
+
+        InvoiceClient::new(
+            mock_env().contract.address,
+            MockTransport {
+                deps,
+                sender: ADMIN.into(),
+                sent: Cell::new(0),
+            },
+        )
+    }
+
+    #[test]
+    fn executes_and_decodes() {
+        let mut client = client();
+
+        let tx = client
+            .add_invoice(
+                AddInvoice::new(INVOICE_ID, Uint128::new(5))
+                    .description("Air Jordan")
+                    .payer("payer"),
+            )
+            .unwrap();
+        let added = tx.events::<InvoiceAdded>().unwrap();
+        assert_eq!(INVOICE_ID, added[0].id);
+
+        let invoice = client.get_invoice(INVOICE_ID).unwrap().invoice;
+        assert_eq!(Some("Air Jordan".into()), invoice.description);
+        assert_eq!(Some(Addr::unchecked("payer")), invoice.payer);
+
+        client.transport_mut().sender = "payer".into();
+        let tx = client
+            .pay_invoice(INVOICE_ID, None, &[coin(5, DENOM)])
+            .unwrap();
+        let paid = tx.events::<InvoicePaid>().unwrap();
+        assert_eq!(Uint128::new(5), paid[0].amount);
+
+        let receipts = client
+            .list_receipts(ReceiptFilter::Payer("payer".into()), None, None)
+            .unwrap();
+        assert_eq!(1, receipts.receipts.len());
+        assert_eq!(DENOM, client.get_contract_info().unwrap().denom);
+    }
+
+    #[test]
+    fn validates_before_sending() {
+        let mut client = client();
+
+        let response = client.add_invoice(AddInvoice::new("not-a-uuid", Uint128::new(5)));
+        match response {
+            Err(ClientError::Invalid(ContractError::InvalidFields { fields })) => {
+                assert_eq!(vec!["id"], fields)
+            }
+            response => panic!("unexpected response: {:?}", response),
+        }
+
+        let response = client.get_denylist(None, Some(0));
+        assert!(matches!(response, Err(ClientError::Invalid(..))));
+        assert_eq!(0, client.transport().sent.get());
+    }
+
+    #[test]
+    fn surfaces_transport_errors() {
+        let mut client = client();
+
+        let response = client.cancel_invoice(INVOICE_ID);
+        assert!(matches!(response, Err(ClientError::Transport(..))));
+    }
+
+    #[test]
+    fn builds_update_config() {
+        let msg: ExecuteMsg = UpdateConfig::new()
+            .business_name("Shoe Co, Inc")
+            .bind_contract_name("shoeco.invoice.pb")
+            .into();
+
+        assert_eq!(
+            ExecuteMsg::UpdateConfig {
+                recipient: None,
+                business_name: Some("Shoe Co, Inc".into()),
+                contract_name: Some(ContractNameUpdate::Bind {
+                    name: "shoeco.invoice.pb".into()
+                }),
+                attribute_name: None,
+            },
+            msg
+        );
+    }
+}
@@ -1,14 +1,41 @@
 use std::fmt;
 
 use cosmwasm_std::{
-    attr, coins, entry_point, to_binary, BankMsg, Binary, Deps, DepsMut, Env, MessageInfo,
-    Response, StdResult, Uint128,
+    attr, coin, coins, entry_point, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps,
+    DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult, Storage, Timestamp, Uint128,
+};
+use provwasm_std::{
+    add_json_attribute, bind_name, unbind_name, NameBinding, ProvenanceMsg, ProvenanceQuerier,
+    ProvenanceQuery,
 };
-use provwasm_std::{ProvenanceMsg, ProvenanceQuery};
 
+use crate::denom::{to_base_units, to_display_amount};
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, QueryMsg, Validate};
-use crate::state::{config_read, get_invoice_storage, get_invoice_storage_read, Invoice};
+use crate::events::{
+    ConfigUpdated, ContractEvent, DenylistUpdated, InvoiceAdded, InvoiceAmended, InvoiceCancelled,
+    InvoiceCredited, InvoiceExpired, InvoiceFinanced, InvoicePaid, ReceivableTransferred,
+};
+use crate::metadata::{create_invoice_scope, scope_id, update_invoice_record, RecordStatus};
+use crate::msg::{
+    ContractInfoResponse, ContractName, ContractNameUpdate, CreditResponse, Denylist,
+    DepositResponse, DisplayAmounts, ExecuteMsg, InvoiceHistory, InvoiceList, InvoiceResponse,
+    PayerStatement, PaymentResponse, QueryMsg, ReceiptList, ReceiptResponse, ReceivableOwnership,
+    SignedInvoice, StatsResponse, Validate, VersionInfoResponse,
+};
+use crate::state::{
+    append_ledger_entry, config, config_read, expiry_key, get_credit_storage,
+    get_credit_storage_read, get_denylist_storage, get_denylist_storage_read, get_deposit_storage,
+    get_deposit_storage_read, get_expired_invoice_storage, get_expired_invoice_storage_read,
+    get_expiry_storage, get_expiry_storage_read, get_invoice_storage, get_invoice_storage_read,
+    get_ledger_account_storage_read, get_ledger_storage_read, get_paid_invoice_storage,
+    get_paid_invoice_storage_read, get_payer_receipt_storage_read, get_payment_storage,
+    get_payment_storage_read, get_receipt_storage_read, get_receivable_storage,
+    get_receivable_storage_read, get_revision_storage, get_revision_storage_read,
+    get_signed_invoice_storage, get_signed_invoice_storage_read, get_stats_storage_read,
+    issue_receipt, update_stats, Acceptance, CreditNote, DenylistEntry, FinancingTerms, Invoice,
+    InvoiceRevision, InvoiceStats, LedgerEntry, LedgerEntryKind, OverpaymentMode, Payment,
+    PaymentReceipt, ReceivableTransfer, State,
+};
 
 pub const CRATE_NAME: &str = env!("CARGO_CRATE_NAME");
 pub const PACKAGE_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -17,7 +44,7 @@ pub const PACKAGE_VERSION: &str = env!("CARGO_PKG_VERSION");
 #[entry_point]
 pub fn execute(
     deps: DepsMut<ProvenanceQuery>,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
@@ -28,18 +55,95 @@ pub fn execute(
             id,
             amount,
             description,
-        } => add_invoice(deps, info, id, amount, description),
-        ExecuteMsg::CancelInvoice { id } => cancel_invoice(deps, info, id),
-        ExecuteMsg::PayInvoice { id } => pay_invoice(deps, info, id),
+            due_date,
+            expires_at,
+            payer,
+            display_amount,
+        } => add_invoice(
+            deps,
+            env,
+            info,
+            id,
+            amount,
+            description,
+            due_date,
+            expires_at,
+            payer,
+            display_amount,
+        ),
+        ExecuteMsg::AmendInvoice {
+            id,
+            amount,
+            description,
+            due_date,
+        } => amend_invoice(deps, env, info, id, amount, description, due_date),
+        ExecuteMsg::CancelInvoice { id } => cancel_invoice(deps, env, info, id),
+        ExecuteMsg::PayInvoice { id, payment_ref } => pay_invoice(deps, env, info, id, payment_ref),
+        ExecuteMsg::PayWithSignedInvoice { invoice, signature } => {
+            pay_with_signed_invoice(deps, env, info, invoice, signature)
+        }
+        ExecuteMsg::UpdateSigningKey { public_key } => update_signing_key(deps, info, public_key),
+        ExecuteMsg::UpdatePaymentAttributes { enabled } => {
+            update_payment_attributes(deps, info, enabled)
+        }
+        ExecuteMsg::UpdateRequiredAttributes { attributes } => {
+            update_required_attributes(deps, info, attributes)
+        }
+        ExecuteMsg::UpdateScopeSpecification { specification_id } => {
+            update_scope_specification(deps, info, specification_id)
+        }
+        ExecuteMsg::UpdateConfig {
+            recipient,
+            business_name,
+            contract_name,
+            attribute_name,
+        } => update_config(
+            deps,
+            env,
+            info,
+            recipient,
+            business_name,
+            contract_name,
+            attribute_name,
+        ),
+        ExecuteMsg::AddToDenylist { address } => update_denylist(deps, env, info, address, true),
+        ExecuteMsg::RemoveFromDenylist { address } => {
+            update_denylist(deps, env, info, address, false)
+        }
+        ExecuteMsg::IssueCreditNote {
+            invoice_id,
+            amount,
+            reason,
+        } => issue_credit_note(deps, env, info, invoice_id, amount, reason),
+        ExecuteMsg::AcceptInvoice { id } => accept_invoice(deps, env, info, id),
+        ExecuteMsg::Reject { id, reason } => reject_invoice(deps, env, info, id, reason),
+        ExecuteMsg::PruneExpired { limit } => prune_expired(deps, env, info, limit),
+        ExecuteMsg::WithdrawCredit {} => withdraw_credit(deps, info),
+        ExecuteMsg::FundInvoice { id } => fund_invoice(deps, env, info, id),
+        ExecuteMsg::UpdateFinancing { terms, financiers } => {
+            update_financing(deps, info, terms, financiers)
+        }
+        ExecuteMsg::TransferReceivable { id, new_payee } => {
+            transfer_receivable(deps, env, info, id, new_payee)
+        }
+        ExecuteMsg::Deposit {} => deposit(deps, info),
+        ExecuteMsg::Withdraw { amount } => withdraw(deps, info, amount),
+        ExecuteMsg::SettleFromDeposit { id } => settle_from_deposit(deps, env, info, id),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn add_invoice(
     deps: DepsMut<ProvenanceQuery>,
+    env: Env,
     info: MessageInfo,
     id: String,
     amount: Uint128,
     description: Option<String>,
+    due_date: Option<Timestamp>,
+    expires_at: Option<Timestamp>,
+    payer: Option<String>,
+    display_amount: Option<String>,
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
     // get state for auth and attrs
     let state = &config_read(deps.storage).load()?;
@@ -56,39 +160,139 @@ fn add_invoice(
         return Err(ContractError::SentFundsUnsupported);
     }
 
+    // an invoice must be payable when added
+    if matches!(expires_at, Some(expires_at) if expires_at <= env.block.time) {
+        return Err(ContractError::InvalidFields {
+            fields: vec![String::from("expires_at")],
+        });
+    }
+
+    // only a designated payer can accept an invoice
+    if state.require_acceptance && payer.is_none() {
+        return Err(ContractError::InvalidFields {
+            fields: vec![String::from("payer")],
+        });
+    }
+
+    let payer = payer
+        .map(|payer| deps.api.addr_validate(&payer))
+        .transpose()?;
+
+    // ensure the payer is not blocked by compliance
+    if let Some(payer) = &payer {
+        if is_denylisted(deps.storage, payer)? {
+            return Err(ContractError::PayerDenylisted {
+                address: payer.to_string(),
+            });
+        }
+    }
+
+    // convert an amount in the display unit, which must be a whole number of base units
+    let amount = match display_amount {
+        Some(display_amount) => {
+            let metadata = state
+                .denom_metadata
+                .as_ref()
+                .ok_or(ContractError::DenomMetadataUnavailable)?;
+            to_base_units(&display_amount, metadata)?
+        }
+        None => amount,
+    };
+
     // invoice model
-    let invoice = Invoice {
+    let mut invoice = Invoice {
         id,
         amount,
+        amount_due: amount,
         description,
+        due_date,
+        expires_at,
+        payer,
+        payee: None,
+        financing: None,
+        credit_notes: vec![],
+        acceptance: None,
+        scope_id: None,
     };
 
     // ensure id is unique
-    let mut invoice_storage = get_invoice_storage(deps.storage);
-    if invoice_storage.may_load(invoice.id.as_bytes())?.is_some() {
-        return Err(ContractError::InvalidFields {
-            fields: vec![String::from("id")],
-        });
+    ensure_unused_id(deps.storage, &invoice.id)?;
+
+    // record the invoice in the metadata module too
+    if state.scope_specification_id.is_some() {
+        invoice.scope_id = Some(scope_id(&invoice.id)?);
     }
+    let mut invoice_storage = get_invoice_storage(deps.storage);
 
-    let response = Response::new().add_attributes(vec![
+    let mut response = Response::new().add_attributes(vec![
         attr("action", Action::Add.to_string()),
         attr("id", &invoice.id),
         attr("denom", &state.denom),
-        attr("amount", &invoice.amount.to_string()),
+        attr("amount", invoice.amount.to_string()),
         attr("recipient", &state.recipient),
     ]);
 
+    response = response.add_event(InvoiceAdded::new(state, &invoice).to_event());
+
+    if let Some(scope_id) = &invoice.scope_id {
+        response = response
+            .add_attribute("scope_id", scope_id)
+            .add_messages(create_invoice_scope(&env, state, &invoice)?);
+    }
+
     // save invoice
     invoice_storage.save(invoice.id.as_bytes(), &invoice)?;
 
+    if let Some(expires_at) = invoice.expires_at {
+        get_expiry_storage(deps.storage).save(&expiry_key(expires_at, &invoice.id), &invoice.id)?;
+    }
+
+    update_stats(deps.storage, &state.denom, |stats| {
+        stats.open.add(invoice.amount)
+    })?;
+
+    // bill the payer's account
+    if let Some(payer) = &invoice.payer {
+        response = response.add_attribute("payer", payer);
+        append_ledger_entry(
+            deps.storage,
+            payer,
+            LedgerEntry::new(
+                &invoice.id,
+                LedgerEntryKind::Invoice,
+                invoice.amount,
+                &state.denom,
+                &env.block,
+            ),
+        )?;
+
+        // settle straight away when the payer has prepaid enough and need not accept first,
+        // leaving the invoice open for a payer who is no longer verified
+        if !state.require_acceptance
+            && missing_attribute(deps.as_ref(), state, payer)?.is_none()
+            && withdraw_deposit(deps.storage, state, payer, invoice.amount)?
+        {
+            let receipt = record_payment(deps.storage, &env, state, &invoice, payer)?;
+            response = response
+                .add_attribute("settled_from_deposit", "true")
+                .add_event(InvoicePaid::new(state, &invoice, &receipt).to_event())
+                .add_messages(settlement_messages(deps.storage, state, &invoice)?)
+                .add_messages(payment_attribute(state, &receipt)?)
+                .add_messages(update_invoice_record(state, &invoice, RecordStatus::Paid)?);
+        }
+    }
+
     Ok(response)
 }
 
-fn cancel_invoice(
+fn amend_invoice(
     deps: DepsMut<ProvenanceQuery>,
+    env: Env,
     info: MessageInfo,
     id: String,
+    amount: Uint128,
+    description: Option<String>,
+    due_date: Option<Timestamp>,
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
     // get state for auth and attrs
     let state = &config_read(deps.storage).load()?;
@@ -96,7 +300,7 @@ fn cancel_invoice(
     // ensure message sender is admin
     if info.sender != state.admin {
         return Err(ContractError::Unauthorized {
-            error: String::from("Only admin can cancel invoice"),
+            error: String::from("Only admin can amend invoice"),
         });
     }
 
@@ -107,734 +311,5689 @@ fn cancel_invoice(
 
     // ensure invoice exists
     let mut invoice_storage = get_invoice_storage(deps.storage);
-    let invoice = invoice_storage
+    let mut invoice = invoice_storage
         .load(id.as_bytes())
         .map_err(|error| ContractError::LoadInvoiceFailed { error })?;
 
-    let response = Response::new().add_attributes(vec![
-        attr("action", Action::Cancel.to_string()),
-        attr("id", &invoice.id),
-        attr("denom", &state.denom),
-        attr("amount", &invoice.amount.to_string()),
-        attr("recipient", &state.recipient),
-    ]);
+    // an expired invoice is closed
+    if invoice.is_expired(&env.block) {
+        return Err(ContractError::InvoiceExpired { id: invoice.id });
+    }
 
-    // remove invoice
-    invoice_storage.remove(invoice.id.as_bytes());
+    // a sold or financed receivable belongs to its holder
+    ensure_receivable_held(&invoice)?;
 
-    Ok(response)
+    // credit notes already issued still apply to the new amount
+    let credited = invoice.amount - invoice.amount_due;
+    if amount <= credited {
+        return Err(ContractError::CreditNoteExceedsAmountDue);
+    }
+    let previous_amount_due = invoice.amount_due;
+
+    let revision = InvoiceRevision {
+        amount: invoice.amount,
+        description: invoice.description.to_owned(),
+        due_date: invoice.due_date,
+        amended_by: info.sender.to_owned(),
+        block_height: env.block.height,
+        block_time: env.block.time,
+    };
+
+    invoice.amount = amount;
+    invoice.amount_due = amount - credited;
+    invoice.description = description;
+    invoice.due_date = due_date;
+    // the payer only agreed to the prior terms
+    invoice.acceptance = None;
+    invoice_storage.save(invoice.id.as_bytes(), &invoice)?;
+
+    get_revision_storage(deps.storage).update(
+        invoice.id.as_bytes(),
+        |revisions| -> StdResult<_> {
+            let mut revisions = revisions.unwrap_or_default();
+            revisions.push(revision);
+            Ok(revisions)
+        },
+    )?;
+
+    update_stats(deps.storage, &state.denom, |stats| {
+        stats.open.sub(previous_amount_due)?;
+        stats.open.add(invoice.amount_due)
+    })?;
+
+    // bill or credit the payer's account for the difference
+    if let Some(payer) = &invoice.payer {
+        let entry = if invoice.amount_due > previous_amount_due {
+            Some((
+                LedgerEntryKind::Invoice,
+                invoice.amount_due - previous_amount_due,
+            ))
+        } else if invoice.amount_due < previous_amount_due {
+            Some((
+                LedgerEntryKind::Credit,
+                previous_amount_due - invoice.amount_due,
+            ))
+        } else {
+            None
+        };
+        if let Some((kind, difference)) = entry {
+            append_ledger_entry(
+                deps.storage,
+                payer,
+                LedgerEntry::new(&invoice.id, kind, difference, &state.denom, &env.block),
+            )?;
+        }
+    }
+
+    Ok(Response::new()
+        .add_event(InvoiceAmended::new(state, &invoice).to_event())
+        .add_attributes(vec![
+            attr("action", Action::Amend.to_string()),
+            attr("id", &invoice.id),
+            attr("denom", &state.denom),
+            attr("amount", invoice.amount.to_string()),
+            attr("amount_due", invoice.amount_due.to_string()),
+        ])
+        // the scope record carries the new terms
+        .add_messages(update_invoice_record(state, &invoice, RecordStatus::Open)?))
 }
 
-fn pay_invoice(
+fn cancel_invoice(
     deps: DepsMut<ProvenanceQuery>,
+    env: Env,
     info: MessageInfo,
     id: String,
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
-    // get state for attrs
+    // get state for auth and attrs
     let state = &config_read(deps.storage).load()?;
 
+    // ensure message sender is admin
+    if info.sender != state.admin {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only admin can cancel invoice"),
+        });
+    }
+
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
     // ensure invoice exists
     let mut invoice_storage = get_invoice_storage(deps.storage);
     let invoice = invoice_storage
         .load(id.as_bytes())
         .map_err(|error| ContractError::LoadInvoiceFailed { error })?;
 
-    // ensure funds match invoice
-    let amount = coins(invoice.amount.into(), state.denom.to_owned());
-    if info.funds.ne(&amount) {
-        return Err(ContractError::SentFundsInvoiceMismatch);
-    }
-
-    let mut response = Response::new().add_attributes(vec![
-        attr("action", Action::Pay.to_string()),
-        attr("id", &invoice.id),
-        attr("denom", &state.denom),
-        attr("amount", &invoice.amount.to_string()),
-        attr("sender", &info.sender.to_owned()),
-        attr("recipient", &state.recipient),
-    ]);
-
-    // transfer coins to recipient
-    response = response.add_message(BankMsg::Send {
-        to_address: state.recipient.to_string(),
-        amount,
-    });
+    // a sold or financed receivable belongs to its holder
+    ensure_receivable_held(&invoice)?;
+
+    let response = Response::new()
+        .add_attributes(vec![
+            attr("action", Action::Cancel.to_string()),
+            attr("id", &invoice.id),
+            attr("denom", &state.denom),
+            attr("amount", invoice.amount_due.to_string()),
+            attr("recipient", &state.recipient),
+        ])
+        .add_event(InvoiceCancelled::new(state, &invoice).to_event())
+        .add_messages(update_invoice_record(
+            state,
+            &invoice,
+            RecordStatus::Cancelled,
+        )?);
 
     // remove invoice
     invoice_storage.remove(invoice.id.as_bytes());
+    remove_expiry(deps.storage, &invoice);
+
+    update_stats(deps.storage, &state.denom, |stats| {
+        stats.open.sub(invoice.amount_due)?;
+        stats.cancelled.add(invoice.amount_due)
+    })?;
+
+    // credit the payer's account
+    if let Some(payer) = &invoice.payer {
+        append_ledger_entry(
+            deps.storage,
+            payer,
+            LedgerEntry::new(
+                &invoice.id,
+                LedgerEntryKind::Credit,
+                invoice.amount_due,
+                &state.denom,
+                &env.block,
+            ),
+        )?;
+    }
 
     Ok(response)
 }
 
-#[entry_point]
-pub fn query(deps: Deps<ProvenanceQuery>, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    msg.validate()?;
-
-    match msg {
-        QueryMsg::GetContractInfo {} => to_binary(&config_read(deps.storage).load()?),
-        QueryMsg::GetVersionInfo {} => to_binary(&cw2::get_contract_version(deps.storage)?),
-        QueryMsg::GetInvoice { id } => {
-            to_binary(&get_invoice_storage_read(deps.storage).load(id.as_bytes())?)
-        }
+// Only a receivable still held by the contract's recipient can be changed by the admin.
+fn ensure_receivable_held(invoice: &Invoice) -> Result<(), ContractError> {
+    if invoice.financing.is_some() {
+        return Err(ContractError::InvoiceFinanced {
+            id: invoice.id.to_owned(),
+        });
+    }
+    if invoice.payee.is_some() {
+        return Err(ContractError::ReceivableTransferred {
+            id: invoice.id.to_owned(),
+        });
     }
+    Ok(())
 }
 
-enum Action {
-    Add,
-    Cancel,
-    Pay,
-}
+fn accept_invoice(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    id: String,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let state = &config_read(deps.storage).load()?;
+    ensure_verified(deps.as_ref(), state, &info.sender)?;
 
-impl fmt::Display for Action {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Action::Add => write!(f, "add_invoice"),
-            Action::Cancel => write!(f, "cancel_invoice"),
-            Action::Pay => write!(f, "pay_invoice"),
-        }
-    }
+    let acceptance = Acceptance::Accepted {
+        block_time: env.block.time,
+    };
+    let invoice = record_acceptance(deps, &env, &info, &id, acceptance)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", Action::Accept.to_string()),
+        attr("id", &invoice.id),
+        attr("payer", info.sender),
+    ]))
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::state::{config, State};
-    use cosmwasm_std::testing::{mock_env, mock_info};
-    use cosmwasm_std::{coin, Addr, CosmosMsg, StdError, Storage};
-    use provwasm_mocks::mock_dependencies;
+fn reject_invoice(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    id: String,
+    reason: String,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    let acceptance = Acceptance::Rejected {
+        block_time: env.block.time,
+        reason: reason.to_owned(),
+    };
+    let invoice = record_acceptance(deps, &env, &info, &id, acceptance)?;
 
-    use crate::state::get_invoice_storage_read;
+    Ok(Response::new().add_attributes(vec![
+        attr("action", Action::Reject.to_string()),
+        attr("id", &invoice.id),
+        attr("payer", info.sender),
+        attr("reason", reason),
+    ]))
+}
 
-    use super::*;
+/// Record the designated payer's answer to the current terms of an open invoice.
+fn record_acceptance(
+    deps: DepsMut<ProvenanceQuery>,
+    env: &Env,
+    info: &MessageInfo,
+    id: &str,
+    acceptance: Acceptance,
+) -> Result<Invoice, ContractError> {
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
 
-    const TEST_DENOM: &str = "testdenom";
-    const INVOICE_ID: &str = "63069195-bc51-41bd-80d7-0ab84b98e283";
-    const BUSINESS_NAME: &str = "company";
-    const ADMIN: &str = "admin";
-    const RECIPIENT: &str = "recipient";
-    const DESCRIPTION: &str = "description";
+    ensure_sender_allowed(deps.storage, &info.sender)?;
 
-    #[test]
-    fn create_invoice_success() {
-        let mut deps = mock_dependencies(&[]);
+    // ensure invoice exists
+    let mut invoice_storage = get_invoice_storage(deps.storage);
+    let mut invoice = invoice_storage
+        .load(id.as_bytes())
+        .map_err(|error| ContractError::LoadInvoiceFailed { error })?;
 
-        setup_test_base(
-            &mut deps.storage,
-            &State {
-                admin: Addr::unchecked(ADMIN),
-                recipient: Addr::unchecked(RECIPIENT),
-                denom: TEST_DENOM.into(),
-                business_name: BUSINESS_NAME.into(),
-            },
-        );
+    // ensure message sender is the designated payer
+    if invoice.payer.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only payer can accept or reject invoice"),
+        });
+    }
 
-        let amount = Uint128::new(100);
-        let add_msg = ExecuteMsg::AddInvoice {
-            id: INVOICE_ID.into(),
-            amount: amount.into(),
-            description: Option::Some(DESCRIPTION.into()),
-        };
+    // an expired invoice is closed
+    if invoice.is_expired(&env.block) {
+        return Err(ContractError::InvoiceExpired { id: invoice.id });
+    }
 
-        let sender_info = mock_info(ADMIN, &[]);
+    // the payer answers each version of the terms once
+    if invoice.acceptance.is_some() {
+        return Err(ContractError::AcceptanceAlreadyRecorded { id: invoice.id });
+    }
 
-        // execute add invoice
-        let add_response = execute(
-            deps.as_mut(),
-            mock_env(),
-            sender_info.clone(),
-            add_msg.clone(),
-        );
+    invoice.acceptance = Some(acceptance);
+    invoice_storage.save(invoice.id.as_bytes(), &invoice)?;
 
-        // verify invoice response
-        match add_response {
-            Ok(response) => {
-                assert_eq!(response.attributes.len(), 5);
-                assert_eq!(
-                    response.attributes[0],
-                    attr("action", Action::Add.to_string())
-                );
-                assert_eq!(response.attributes[1], attr("id", INVOICE_ID));
-                assert_eq!(response.attributes[2], attr("denom", TEST_DENOM));
-                assert_eq!(response.attributes[3], attr("amount", amount.to_string()));
-                assert_eq!(response.attributes[4], attr("recipient", RECIPIENT));
-            }
-            Err(error) => {
-                panic!("failed to create add invoice: {:?}", error)
-            }
-        }
+    Ok(invoice)
+}
 
-        // verify invoice stored
-        let invoice_storage = get_invoice_storage_read(&deps.storage);
+fn prune_expired(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for attrs
+    let state = &config_read(deps.storage).load()?;
 
-        match invoice_storage.load(INVOICE_ID.as_bytes()) {
-            Ok(stored_invoice) => {
-                assert_eq!(
-                    stored_invoice,
-                    Invoice {
-                        id: INVOICE_ID.into(),
-                        amount,
-                        description: Option::Some(DESCRIPTION.into())
-                    }
-                )
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    // oldest expiries first, up to and including the current block time
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let end = (env.block.time.nanos() + 1).to_be_bytes();
+    let entries = get_expiry_storage_read(deps.storage)
+        .range(None, Some(&end), Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<(Vec<u8>, String)>>>()?;
+
+    let mut messages = vec![];
+    let mut expired = vec![];
+    let mut written_off = vec![];
+    let mut events = vec![];
+    for (key, id) in entries {
+        // an index entry left behind by a closed invoice must not hold up the rest
+        let invoice = match get_invoice_storage_read(deps.storage).may_load(id.as_bytes())? {
+            Some(invoice) => invoice,
+            None => {
+                get_expiry_storage(deps.storage).remove(&key);
+                continue;
             }
-            _ => {
-                panic!("invoice was not found in storage")
+        };
+        messages.extend(update_invoice_record(
+            state,
+            &invoice,
+            RecordStatus::Expired,
+        )?);
+
+        // close invoice, keeping it for lookups
+        get_invoice_storage(deps.storage).remove(invoice.id.as_bytes());
+        get_expired_invoice_storage(deps.storage).save(invoice.id.as_bytes(), &invoice)?;
+        remove_expiry(deps.storage, &invoice);
+
+        // a transferred or financed receivable can no longer be collected by its holder
+        let lost = invoice.payee.is_some() || invoice.financing.is_some();
+        update_stats(deps.storage, &state.denom, |stats| {
+            stats.open.sub(invoice.amount_due)?;
+            if lost {
+                stats.written_off.add(invoice.amount_due)?;
             }
+            stats.expired.add(invoice.amount_due)
+        })?;
+        if lost {
+            written_off.push(invoice.id.to_owned());
+        }
+        events.push(InvoiceExpired::new(state, &invoice, lost).to_event());
+
+        // credit the payer's account
+        if let Some(payer) = &invoice.payer {
+            append_ledger_entry(
+                deps.storage,
+                payer,
+                LedgerEntry::new(
+                    &invoice.id,
+                    LedgerEntryKind::Credit,
+                    invoice.amount_due,
+                    &state.denom,
+                    &env.block,
+                ),
+            )?;
         }
+        expired.push(invoice.id);
     }
 
-    #[test]
-    fn create_invoice_with_funds_throws_error() {
-        let mut deps = mock_dependencies(&[]);
-
-        setup_test_base(
-            &mut deps.storage,
-            &State {
-                admin: Addr::unchecked(ADMIN),
-                recipient: Addr::unchecked(RECIPIENT),
-                denom: TEST_DENOM.into(),
-                business_name: BUSINESS_NAME.into(),
-            },
-        );
+    Ok(Response::new()
+        .add_events(events)
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", Action::PruneExpired.to_string()),
+            attr("count", expired.len().to_string()),
+            attr("ids", expired.join(",")),
+            attr("written_off", written_off.join(",")),
+        ]))
+}
 
-        let amount = Uint128::new(100);
-        let add_msg = ExecuteMsg::AddInvoice {
-            id: INVOICE_ID.into(),
-            amount: amount.into(),
-            description: Option::Some(DESCRIPTION.into()),
-        };
+/// Drop a closed invoice from the expiry index.
+fn remove_expiry(storage: &mut dyn Storage, invoice: &Invoice) {
+    if let Some(expires_at) = invoice.expires_at {
+        get_expiry_storage(storage).remove(&expiry_key(expires_at, &invoice.id));
+    }
+}
 
-        let sender_info = mock_info(ADMIN, &[coin(amount.u128(), TEST_DENOM)]);
+fn issue_credit_note(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    invoice_id: String,
+    amount: Uint128,
+    reason: String,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for auth and attrs
+    let state = &config_read(deps.storage).load()?;
 
-        // execute add invoice
-        let add_response = execute(
-            deps.as_mut(),
+    // ensure message sender is admin
+    if info.sender != state.admin {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only admin can issue credit note"),
+        });
+    }
+
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    // ensure invoice exists
+    let mut invoice_storage = get_invoice_storage(deps.storage);
+    let mut invoice = invoice_storage
+        .load(invoice_id.as_bytes())
+        .map_err(|error| ContractError::LoadInvoiceFailed { error })?;
+
+    // an expired invoice is closed
+    if invoice.is_expired(&env.block) {
+        return Err(ContractError::InvoiceExpired { id: invoice.id });
+    }
+
+    // a sold or financed receivable belongs to its holder
+    ensure_receivable_held(&invoice)?;
+
+    // something must be left to pay
+    if amount >= invoice.amount_due {
+        return Err(ContractError::CreditNoteExceedsAmountDue);
+    }
+
+    invoice.amount_due -= amount;
+    let note = CreditNote {
+        amount,
+        reason,
+        block_height: env.block.height,
+        block_time: env.block.time,
+    };
+    let event = InvoiceCredited::new(state, &invoice, &note);
+    invoice.credit_notes.push(note);
+    invoice_storage.save(invoice.id.as_bytes(), &invoice)?;
+
+    update_stats(deps.storage, &state.denom, |stats| {
+        stats.open.reduce(amount)
+    })?;
+
+    // credit the payer's account
+    if let Some(payer) = &invoice.payer {
+        append_ledger_entry(
+            deps.storage,
+            payer,
+            LedgerEntry::new(
+                &invoice.id,
+                LedgerEntryKind::Credit,
+                amount,
+                &state.denom,
+                &env.block,
+            ),
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", Action::IssueCreditNote.to_string()),
+            attr("id", &invoice.id),
+            attr("denom", &state.denom),
+            attr("amount", amount.to_string()),
+            attr("amount_due", invoice.amount_due.to_string()),
+        ])
+        .add_event(event.to_event())
+        // the scope record carries the reduced amount due
+        .add_messages(update_invoice_record(state, &invoice, RecordStatus::Open)?))
+}
+
+fn pay_invoice(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    id: String,
+    payment_ref: Option<String>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for attrs
+    let state = &config_read(deps.storage).load()?;
+
+    ensure_sender_allowed(deps.storage, &info.sender)?;
+    ensure_verified(deps.as_ref(), state, &info.sender)?;
+
+    // a retried payment is reported as such, even once the invoice is closed
+    if let Some(payment_ref) = &payment_ref {
+        if get_payment_storage_read(deps.storage, &id)
+            .may_load(payment_ref.as_bytes())?
+            .is_some()
+        {
+            return Err(ContractError::DuplicatePaymentRef {
+                payment_ref: payment_ref.to_owned(),
+            });
+        }
+    }
+
+    // ensure invoice exists
+    let invoice = get_invoice_storage_read(deps.storage)
+        .load(id.as_bytes())
+        .map_err(|error| ContractError::LoadInvoiceFailed { error })?;
+
+    // an expired invoice is closed
+    if invoice.is_expired(&env.block) {
+        return Err(ContractError::InvoiceExpired { id: invoice.id });
+    }
+
+    // the payer must agree to the invoice first when acceptance is required
+    if state.require_acceptance && !invoice.is_accepted() {
+        return Err(ContractError::InvoiceNotAccepted { id: invoice.id });
+    }
+
+    let mut response = settle_payment(deps.storage, &env, &info, state, &invoice, Action::Pay)?;
+
+    // remember the reference for reconciliation
+    if let Some(payment_ref) = payment_ref {
+        response = response.add_attribute("payment_ref", &payment_ref);
+        get_payment_storage(deps.storage, &invoice.id).save(
+            payment_ref.as_bytes(),
+            &Payment {
+                invoice_id: invoice.id.to_owned(),
+                payment_ref: payment_ref.to_owned(),
+                sender: info.sender,
+                amount: invoice.amount_due,
+                denom: state.denom.to_owned(),
+                block_height: env.block.height,
+                block_time: env.block.time,
+            },
+        )?;
+    }
+
+    Ok(response)
+}
+
+fn pay_with_signed_invoice(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    signed: SignedInvoice,
+    signature: Binary,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for the signing key and attrs
+    let state = &config_read(deps.storage).load()?;
+
+    let signing_key = state
+        .signing_key
+        .as_ref()
+        .ok_or_else(|| ContractError::Unauthorized {
+            error: String::from("Only invoices signed by a registered key can be paid"),
+        })?;
+
+    // ensure the merchant signed exactly this invoice, for this contract
+    let message_hash = signed.message_hash(&env.block.chain_id, &env.contract.address)?;
+    let verified = deps
+        .api
+        .secp256k1_verify(&message_hash, &signature, signing_key)
+        .map_err(|_| ContractError::InvalidSignature)?;
+    if !verified {
+        return Err(ContractError::InvalidSignature);
+    }
+
+    // ensure message sender is the payer the invoice was issued to
+    let payer = deps.api.addr_validate(&signed.payer)?;
+    if info.sender != payer {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only payer can pay signed invoice"),
+        });
+    }
+
+    ensure_sender_allowed(deps.storage, &payer)?;
+    ensure_verified(deps.as_ref(), state, &payer)?;
+
+    if signed.denom != state.denom {
+        return Err(ContractError::UnsupportedDenom {
+            denom: state.denom.to_owned(),
+        });
+    }
+
+    // an expired invoice is closed
+    if env.block.time >= signed.expires_at {
+        return Err(ContractError::InvoiceExpired { id: signed.id });
+    }
+
+    ensure_unused_id(deps.storage, &signed.id)?;
+    get_signed_invoice_storage(deps.storage).save(signed.id.as_bytes(), &env.block.height)?;
+
+    // paying a signed invoice is the payer's acceptance of it
+    let invoice = Invoice {
+        id: signed.id,
+        amount: signed.amount,
+        amount_due: signed.amount,
+        expires_at: Some(signed.expires_at),
+        payer: Some(payer.to_owned()),
+        acceptance: Some(Acceptance::Accepted {
+            block_time: env.block.time,
+        }),
+        ..Invoice::default()
+    };
+
+    // bill the payer's account before settling it
+    update_stats(deps.storage, &state.denom, |stats| {
+        stats.open.add(invoice.amount)
+    })?;
+    append_ledger_entry(
+        deps.storage,
+        &payer,
+        LedgerEntry::new(
+            &invoice.id,
+            LedgerEntryKind::Invoice,
+            invoice.amount,
+            &state.denom,
+            &env.block,
+        ),
+    )?;
+
+    settle_payment(
+        deps.storage,
+        &env,
+        &info,
+        state,
+        &invoice,
+        Action::PayWithSignedInvoice,
+    )
+}
+
+/// Take payment of an open invoice from the sender's credit and sent funds, paying out
+/// whoever is owed and closing the invoice.
+fn settle_payment(
+    storage: &mut dyn Storage,
+    env: &Env,
+    info: &MessageInfo,
+    state: &State,
+    invoice: &Invoice,
+    action: Action,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // apply any credit the sender holds before looking at sent funds
+    let mut credit_storage = get_credit_storage(storage);
+    let credit = credit_storage
+        .may_load(info.sender.as_bytes())?
+        .unwrap_or_default();
+    let credit_applied = credit.min(invoice.amount_due);
+    let amount_required = invoice.amount_due - credit_applied;
+
+    // ensure funds cover the invoice, only holding the excess when overpayment is credited
+    let sent = match info.funds.as_slice() {
+        [] => Uint128::zero(),
+        [coin] if coin.denom == state.denom => coin.amount,
+        _ => return Err(ContractError::SentFundsInvoiceMismatch),
+    };
+    if sent < amount_required {
+        return Err(ContractError::SentFundsInvoiceMismatch);
+    }
+    let credit_added = sent - amount_required;
+    if !credit_added.is_zero() && state.overpayment != OverpaymentMode::Credit {
+        return Err(ContractError::SentFundsInvoiceMismatch);
+    }
+
+    let mut response = Response::new().add_attributes(vec![
+        attr("action", action.to_string()),
+        attr("id", &invoice.id),
+        attr("denom", &state.denom),
+        attr("amount", invoice.amount_due.to_string()),
+        attr("sender", info.sender.to_owned()),
+        attr("recipient", invoice.payee(state)),
+    ]);
+
+    // update the sender's credit balance
+    if !credit_applied.is_zero() || !credit_added.is_zero() {
+        let balance = credit - credit_applied + credit_added;
+        if balance.is_zero() {
+            credit_storage.remove(info.sender.as_bytes());
+        } else {
+            credit_storage.save(info.sender.as_bytes(), &balance)?;
+        }
+        response = response.add_attributes(vec![
+            attr("credit_applied", credit_applied.to_string()),
+            attr("credit_added", credit_added.to_string()),
+        ]);
+    }
+
+    // transfer coins to whoever holds the receivable
+    response = response.add_messages(settlement_messages(storage, state, invoice)?);
+
+    let receipt = record_payment(storage, env, state, invoice, &info.sender)?;
+
+    Ok(response
+        .add_event(InvoicePaid::new(state, invoice, &receipt).to_event())
+        .add_messages(payment_attribute(state, &receipt)?)
+        .add_messages(update_invoice_record(state, invoice, RecordStatus::Paid)?))
+}
+
+fn update_signing_key(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+    public_key: Option<Binary>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for auth
+    let mut state = config_read(deps.storage).load()?;
+
+    // ensure message sender is admin
+    if info.sender != state.admin {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only admin can update signing key"),
+        });
+    }
+
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    state.signing_key = public_key;
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_event(ConfigUpdated::new(&state).to_event())
+        .add_attributes(vec![
+            attr("action", Action::UpdateSigningKey.to_string()),
+            attr(
+                "public_key",
+                state
+                    .signing_key
+                    .map(|key| key.to_base64())
+                    .unwrap_or_default(),
+            ),
+        ]))
+}
+
+fn update_config(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    recipient: Option<String>,
+    business_name: Option<String>,
+    contract_name: Option<ContractNameUpdate>,
+    attribute_name: Option<String>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for auth
+    let mut state = config_read(deps.storage).load()?;
+
+    // ensure message sender is admin
+    if info.sender != state.admin {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only admin can update config"),
+        });
+    }
+
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    if let Some(recipient) = recipient {
+        let recipient = deps.api.addr_validate(&recipient)?;
+        if is_denylisted(deps.storage, &recipient)? {
+            return Err(ContractError::RecipientDenylisted {
+                address: recipient.to_string(),
+            });
+        }
+        state.recipient = recipient;
+    }
+    if let Some(business_name) = business_name {
+        state.business_name = business_name;
+    }
+
+    let mut messages = vec![];
+
+    // existing payment attributes and invoice records live under the attribute name,
+    // so it can be bound once but never replaced
+    if let Some(name) = attribute_name {
+        match &state.attribute_name {
+            Some(current) if current == &name => {
+                // noop
+            }
+            Some(current) => {
+                return Err(ContractError::AttributeNameBound {
+                    name: current.to_owned(),
+                })
+            }
+            None => {
+                messages.push(bind_name(
+                    &name,
+                    env.contract.address.to_owned(),
+                    NameBinding::Restricted,
+                )?);
+                state.attribute_name = Some(name);
+            }
+        }
+    }
+
+    // unbind the current name before binding its replacement
+    match contract_name {
+        Some(ContractNameUpdate::Bind { name }) if state.contract_name.as_ref() != Some(&name) => {
+            if let Some(current) = &state.contract_name {
+                messages.push(unbind_name(current)?);
+            }
+            messages.push(bind_name(
+                &name,
+                env.contract.address,
+                NameBinding::Restricted,
+            )?);
+            state.contract_name = Some(name);
+        }
+        Some(ContractNameUpdate::Unbind {}) => {
+            let current = state
+                .contract_name
+                .take()
+                .ok_or(ContractError::ContractNameUnbound)?;
+            messages.push(unbind_name(current)?);
+        }
+        _ => {
+            // noop
+        }
+    }
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_event(ConfigUpdated::new(&state).to_event())
+        .add_messages(messages)
+        .add_attributes(vec![
+            attr("action", Action::UpdateConfig.to_string()),
+            attr("recipient", &state.recipient),
+            attr("business_name", &state.business_name),
+            attr("contract_name", state.contract_name.unwrap_or_default()),
+            attr("attribute_name", state.attribute_name.unwrap_or_default()),
+        ]))
+}
+
+fn update_denylist(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    denied: bool,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for auth
+    let state = config_read(deps.storage).load()?;
+
+    // ensure message sender is admin
+    if info.sender != state.admin {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only admin can update denylist"),
+        });
+    }
+
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    let address = deps.api.addr_validate(&address)?;
+    let event = DenylistUpdated {
+        address: address.to_owned(),
+        denied,
+    };
+    let mut denylist_storage = get_denylist_storage(deps.storage);
+    let action = if denied {
+        denylist_storage.save(
+            address.as_bytes(),
+            &DenylistEntry {
+                address: address.to_owned(),
+                block_height: env.block.height,
+                block_time: env.block.time,
+            },
+        )?;
+        Action::AddToDenylist
+    } else {
+        denylist_storage.remove(address.as_bytes());
+        Action::RemoveFromDenylist
+    };
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", action.to_string()),
+            attr("address", address),
+        ])
+        .add_event(event.to_event()))
+}
+
+fn is_denylisted(storage: &dyn Storage, address: &Addr) -> StdResult<bool> {
+    Ok(get_denylist_storage_read(storage)
+        .may_load(address.as_bytes())?
+        .is_some())
+}
+
+fn ensure_sender_allowed(storage: &dyn Storage, sender: &Addr) -> Result<(), ContractError> {
+    if is_denylisted(storage, sender)? {
+        return Err(ContractError::SenderDenylisted {
+            address: sender.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Ensure `address` holds every Provenance attribute the contract requires of payers.
+fn ensure_verified(
+    deps: Deps<ProvenanceQuery>,
+    state: &State,
+    address: &Addr,
+) -> Result<(), ContractError> {
+    match missing_attribute(deps, state, address)? {
+        Some(attribute) => Err(ContractError::PayerNotVerified {
+            address: address.to_string(),
+            attribute,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The first attribute required of payers that `address` does not hold.
+fn missing_attribute(
+    deps: Deps<ProvenanceQuery>,
+    state: &State,
+    address: &Addr,
+) -> StdResult<Option<String>> {
+    if state.required_attributes.is_empty() {
+        return Ok(None);
+    }
+
+    let attributes = ProvenanceQuerier::new(&deps.querier)
+        .get_attributes(address.to_owned(), None::<String>)?
+        .attributes;
+
+    Ok(state
+        .required_attributes
+        .iter()
+        .find(|name| !attributes.iter().any(|attribute| &attribute.name == *name))
+        .cloned())
+}
+
+fn update_required_attributes(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+    attributes: Vec<String>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for auth
+    let mut state = config_read(deps.storage).load()?;
+
+    // ensure message sender is admin
+    if info.sender != state.admin {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only admin can update required attributes"),
+        });
+    }
+
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    state.required_attributes = attributes;
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_event(ConfigUpdated::new(&state).to_event())
+        .add_attributes(vec![
+            attr("action", Action::UpdateRequiredAttributes.to_string()),
+            attr("attributes", state.required_attributes.join(",")),
+        ]))
+}
+
+/// Ids are never reused, whether the invoice was added or signed off-chain.
+fn ensure_unused_id(storage: &dyn Storage, id: &str) -> Result<(), ContractError> {
+    if get_signed_invoice_storage_read(storage)
+        .may_load(id.as_bytes())?
+        .is_some()
+        || find_invoice(storage, id)?.is_some()
+    {
+        return Err(ContractError::InvalidFields {
+            fields: vec![String::from("id")],
+        });
+    }
+    Ok(())
+}
+
+/// An invoice that is open, paid or expired.
+fn find_invoice(storage: &dyn Storage, id: &str) -> StdResult<Option<Invoice>> {
+    let buckets = [
+        get_invoice_storage_read(storage),
+        get_paid_invoice_storage_read(storage),
+        get_expired_invoice_storage_read(storage),
+    ];
+    for bucket in &buckets {
+        if let Some(invoice) = bucket.may_load(id.as_bytes())? {
+            return Ok(Some(invoice));
+        }
+    }
+    Ok(None)
+}
+
+/// Close a paid invoice, moving it to the paid totals and recording the payment on the
+/// payer's ledger.
+fn record_payment(
+    storage: &mut dyn Storage,
+    env: &Env,
+    state: &State,
+    invoice: &Invoice,
+    sender: &Addr,
+) -> Result<PaymentReceipt, ContractError> {
+    // close invoice, keeping it for lookups
+    get_invoice_storage(storage).remove(invoice.id.as_bytes());
+    get_paid_invoice_storage(storage).save(invoice.id.as_bytes(), invoice)?;
+    remove_expiry(storage, invoice);
+
+    update_stats(storage, &state.denom, |stats| {
+        stats.open.sub(invoice.amount_due)?;
+        stats.paid.add(invoice.amount_due)
+    })?;
+
+    // record the payment against the billed payer, or bill the sender first when the
+    // invoice was not addressed to anyone
+    let payer = match &invoice.payer {
+        Some(payer) => payer,
+        None => {
+            append_ledger_entry(
+                storage,
+                sender,
+                LedgerEntry::new(
+                    &invoice.id,
+                    LedgerEntryKind::Invoice,
+                    invoice.amount_due,
+                    &state.denom,
+                    &env.block,
+                ),
+            )?;
+            sender
+        }
+    };
+    append_ledger_entry(
+        storage,
+        payer,
+        LedgerEntry::new(
+            &invoice.id,
+            LedgerEntryKind::Payment,
+            invoice.amount_due,
+            &state.denom,
+            &env.block,
+        ),
+    )?;
+
+    let receipt = issue_receipt(
+        storage,
+        env,
+        &invoice.id,
+        payer,
+        invoice.amount_due,
+        &state.denom,
+    )?;
+
+    Ok(receipt)
+}
+
+/// The receipt attribute written to the payer's account when payment attributes are enabled.
+fn payment_attribute(
+    state: &State,
+    receipt: &PaymentReceipt,
+) -> StdResult<Option<CosmosMsg<ProvenanceMsg>>> {
+    match &state.attribute_name {
+        Some(name) if state.payment_attributes => Ok(Some(add_json_attribute(
+            receipt.payer.to_owned(),
+            name,
+            receipt,
+        )?)),
+        _ => Ok(None),
+    }
+}
+
+fn update_payment_attributes(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+    enabled: bool,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for auth
+    let mut state = config_read(deps.storage).load()?;
+
+    // ensure message sender is admin
+    if info.sender != state.admin {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only admin can update payment attributes"),
+        });
+    }
+
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    // attributes can only be written under a name the contract owns
+    if enabled && state.attribute_name.is_none() {
+        return Err(ContractError::AttributeNameUnbound);
+    }
+
+    state.payment_attributes = enabled;
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_event(ConfigUpdated::new(&state).to_event())
+        .add_attributes(vec![
+            attr("action", Action::UpdatePaymentAttributes.to_string()),
+            attr("enabled", enabled.to_string()),
+        ]))
+}
+
+fn update_scope_specification(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+    specification_id: Option<String>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for auth
+    let mut state = config_read(deps.storage).load()?;
+
+    // ensure message sender is admin
+    if info.sender != state.admin {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only admin can update scope specification"),
+        });
+    }
+
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    // invoice records are written as attributes under a name the contract owns
+    if specification_id.is_some() && state.attribute_name.is_none() {
+        return Err(ContractError::AttributeNameUnbound);
+    }
+
+    state.scope_specification_id = specification_id;
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_event(ConfigUpdated::new(&state).to_event())
+        .add_attributes(vec![
+            attr("action", Action::UpdateScopeSpecification.to_string()),
+            attr(
+                "specification_id",
+                state.scope_specification_id.unwrap_or_default(),
+            ),
+        ]))
+}
+
+fn deposit(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for the supported denom
+    let state = &config_read(deps.storage).load()?;
+
+    ensure_sender_allowed(deps.storage, &info.sender)?;
+    ensure_verified(deps.as_ref(), state, &info.sender)?;
+
+    // ensure only the supported denom is deposited
+    let amount = match info.funds.as_slice() {
+        [coin] if coin.denom == state.denom => coin.amount,
+        _ => {
+            return Err(ContractError::UnsupportedDenom {
+                denom: state.denom.to_owned(),
+            })
+        }
+    };
+
+    let balance = get_deposit_storage(deps.storage, &info.sender)
+        .update(state.denom.as_bytes(), |balance| -> StdResult<_> {
+            Ok(balance.unwrap_or_default().checked_add(amount)?)
+        })?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", Action::Deposit.to_string()),
+        attr("denom", &state.denom),
+        attr("amount", amount.to_string()),
+        attr("sender", info.sender),
+        attr("balance", balance.to_string()),
+    ]))
+}
+
+fn withdraw(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+    amount: Coin,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    ensure_sender_allowed(deps.storage, &info.sender)?;
+
+    // ensure the deposit covers the withdrawal
+    let mut deposit_storage = get_deposit_storage(deps.storage, &info.sender);
+    let deposited = deposit_storage
+        .may_load(amount.denom.as_bytes())?
+        .unwrap_or_default();
+    if deposited < amount.amount {
+        return Err(ContractError::InsufficientDeposit);
+    }
+
+    let balance = deposited - amount.amount;
+    if balance.is_zero() {
+        deposit_storage.remove(amount.denom.as_bytes());
+    } else {
+        deposit_storage.save(amount.denom.as_bytes(), &balance)?;
+    }
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            attr("action", Action::Withdraw.to_string()),
+            attr("denom", &amount.denom),
+            attr("amount", amount.amount.to_string()),
+            attr("sender", info.sender.to_owned()),
+            attr("balance", balance.to_string()),
+        ])
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![amount],
+        }))
+}
+
+fn settle_from_deposit(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    id: String,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for auth and attrs
+    let state = &config_read(deps.storage).load()?;
+
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    // ensure invoice exists
+    let invoice = get_invoice_storage_read(deps.storage)
+        .load(id.as_bytes())
+        .map_err(|error| ContractError::LoadInvoiceFailed { error })?;
+
+    // an expired invoice is closed
+    if invoice.is_expired(&env.block) {
+        return Err(ContractError::InvoiceExpired { id: invoice.id });
+    }
+
+    // the payer must agree to the invoice first when acceptance is required
+    if state.require_acceptance && !invoice.is_accepted() {
+        return Err(ContractError::InvoiceNotAccepted { id: invoice.id });
+    }
+
+    // ensure message sender owns the deposit or is admin
+    let depositor = invoice.payer.clone().unwrap_or_else(|| info.sender.clone());
+    if info.sender != depositor && info.sender != state.admin {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only payer or admin can settle from deposit"),
+        });
+    }
+
+    // the admin cannot settle on behalf of a denylisted or unverified payer
+    if is_denylisted(deps.storage, &depositor)? {
+        return Err(ContractError::PayerDenylisted {
+            address: depositor.to_string(),
+        });
+    }
+    ensure_verified(deps.as_ref(), state, &depositor)?;
+
+    let response = Response::new().add_attributes(vec![
+        attr("action", Action::SettleFromDeposit.to_string()),
+        attr("id", &invoice.id),
+        attr("denom", &state.denom),
+        attr("amount", invoice.amount_due.to_string()),
+        attr("sender", info.sender),
+        attr("recipient", invoice.payee(state)),
+    ]);
+
+    if !withdraw_deposit(deps.storage, state, &depositor, invoice.amount_due)? {
+        return Err(ContractError::InsufficientDeposit);
+    }
+
+    let receipt = record_payment(deps.storage, &env, state, &invoice, &depositor)?;
+
+    Ok(response
+        .add_event(InvoicePaid::new(state, &invoice, &receipt).to_event())
+        .add_messages(settlement_messages(deps.storage, state, &invoice)?)
+        .add_messages(payment_attribute(state, &receipt)?)
+        .add_messages(update_invoice_record(state, &invoice, RecordStatus::Paid)?))
+}
+
+/// Take `amount` from the depositor's balance in the contract denom, returning `false` and
+/// leaving the balance untouched when the deposit is insufficient.
+fn withdraw_deposit(
+    storage: &mut dyn Storage,
+    state: &State,
+    depositor: &Addr,
+    amount: Uint128,
+) -> Result<bool, ContractError> {
+    let mut deposit_storage = get_deposit_storage(storage, depositor);
+    let deposited = deposit_storage
+        .may_load(state.denom.as_bytes())?
+        .unwrap_or_default();
+    if deposited < amount {
+        return Ok(false);
+    }
+
+    let balance = deposited - amount;
+    if balance.is_zero() {
+        deposit_storage.remove(state.denom.as_bytes());
+    } else {
+        deposit_storage.save(state.denom.as_bytes(), &balance)?;
+    }
+
+    Ok(true)
+}
+
+/// Transfers that pay out a settled invoice: the financier is repaid their advance plus fee
+/// first, and whoever holds the receivable receives the rest. Nothing is paid out to a
+/// denylisted address, so the invoice cannot be settled until it is removed from the list.
+fn settlement_messages(
+    storage: &dyn Storage,
+    state: &State,
+    invoice: &Invoice,
+) -> Result<Vec<BankMsg>, ContractError> {
+    let mut remaining = invoice.amount_due;
+    let mut messages = vec![];
+
+    if let Some(financing) = &invoice.financing {
+        if is_denylisted(storage, &financing.financier)? {
+            return Err(ContractError::FinancierDenylisted {
+                address: financing.financier.to_string(),
+            });
+        }
+        let repayment = financing
+            .advance
+            .checked_add(financing.fee)
+            .map_err(StdError::from)?;
+        remaining = remaining.checked_sub(repayment).map_err(StdError::from)?;
+        messages.push(BankMsg::Send {
+            to_address: financing.financier.to_string(),
+            amount: coins(repayment.into(), state.denom.to_owned()),
+        });
+    }
+
+    if !remaining.is_zero() {
+        let payee = invoice.payee(state);
+        if is_denylisted(storage, payee)? {
+            return Err(ContractError::PayeeDenylisted {
+                address: payee.to_string(),
+            });
+        }
+        messages.push(BankMsg::Send {
+            to_address: payee.to_string(),
+            amount: coins(remaining.into(), state.denom.to_owned()),
+        });
+    }
+
+    Ok(messages)
+}
+
+fn fund_invoice(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    id: String,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for auth and terms
+    let state = &config_read(deps.storage).load()?;
+
+    // ensure message sender is a whitelisted financier
+    if !state.financiers.contains(&info.sender) {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only whitelisted financier can fund invoice"),
+        });
+    }
+    if is_denylisted(deps.storage, &info.sender)? {
+        return Err(ContractError::FinancierDenylisted {
+            address: info.sender.to_string(),
+        });
+    }
+
+    // the advance is paid to the recipient
+    if is_denylisted(deps.storage, &state.recipient)? {
+        return Err(ContractError::RecipientDenylisted {
+            address: state.recipient.to_string(),
+        });
+    }
+
+    let terms = state
+        .financing_terms
+        .as_ref()
+        .ok_or_else(|| financing_unavailable("financing is not enabled"))?;
+
+    // ensure invoice exists
+    let mut invoice_storage = get_invoice_storage(deps.storage);
+    let mut invoice = invoice_storage
+        .load(id.as_bytes())
+        .map_err(|error| ContractError::LoadInvoiceFailed { error })?;
+
+    // an expired invoice is closed
+    if invoice.is_expired(&env.block) {
+        return Err(ContractError::InvoiceExpired { id: invoice.id });
+    }
+
+    // the payer must agree to the invoice first when acceptance is required
+    if state.require_acceptance && !invoice.is_accepted() {
+        return Err(ContractError::InvoiceNotAccepted { id: invoice.id });
+    }
+
+    // only receivables still held by the merchant can be financed, and only once
+    if invoice.financing.is_some() {
+        return Err(financing_unavailable("invoice is already financed"));
+    }
+    if invoice.payee.is_some() {
+        return Err(financing_unavailable("receivable has been transferred"));
+    }
+
+    // ensure funds match the advance
+    let financing = terms.finance(&invoice, &info.sender, &env.block);
+    let advance = coins(financing.advance.into(), state.denom.to_owned());
+    if info.funds.ne(&advance) {
+        return Err(ContractError::SentFundsInvoiceMismatch);
+    }
+
+    let mut response = Response::new()
+        .add_attributes(vec![
+            attr("action", Action::Fund.to_string()),
+            attr("id", &invoice.id),
+            attr("denom", &state.denom),
+            attr("advance", financing.advance.to_string()),
+            attr("fee", financing.fee.to_string()),
+            attr("financier", &info.sender),
+            attr("recipient", invoice.payee(state)),
+        ])
+        .add_message(BankMsg::Send {
+            to_address: invoice.payee(state).to_string(),
+            amount: advance,
+        });
+
+    response = response.add_event(InvoiceFinanced::new(state, &invoice, &financing).to_event());
+
+    // save financing
+    invoice.financing = Some(financing);
+    invoice_storage.save(invoice.id.as_bytes(), &invoice)?;
+
+    // the record names the financier who is now repaid first
+    response = response.add_messages(update_invoice_record(state, &invoice, RecordStatus::Open)?);
+
+    Ok(response)
+}
+
+fn update_financing(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+    terms: Option<FinancingTerms>,
+    financiers: Vec<String>,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for auth
+    let mut state = config_read(deps.storage).load()?;
+
+    // ensure message sender is admin
+    if info.sender != state.admin {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only admin can update financing"),
+        });
+    }
+
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    state.financiers = financiers
+        .iter()
+        .map(|financier| deps.api.addr_validate(financier))
+        .collect::<StdResult<Vec<Addr>>>()?;
+    state.financing_terms = terms;
+    config(deps.storage).save(&state)?;
+
+    Ok(Response::new()
+        .add_event(ConfigUpdated::new(&state).to_event())
+        .add_attributes(vec![
+            attr("action", Action::UpdateFinancing.to_string()),
+            attr("financiers", financiers.join(",")),
+        ]))
+}
+
+fn financing_unavailable(reason: &str) -> ContractError {
+    ContractError::FinancingUnavailable {
+        reason: reason.into(),
+    }
+}
+
+fn transfer_receivable(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    id: String,
+    new_payee: String,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for auth
+    let state = &config_read(deps.storage).load()?;
+
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    // ensure invoice exists
+    let mut invoice = get_invoice_storage_read(deps.storage)
+        .load(id.as_bytes())
+        .map_err(|error| ContractError::LoadInvoiceFailed { error })?;
+
+    // ensure message sender holds the receivable, the admin acting for the recipient until
+    // it is first transferred
+    let payee = invoice.payee(state).to_owned();
+    if info.sender != payee && !(invoice.payee.is_none() && info.sender == state.admin) {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only payee can transfer receivable"),
+        });
+    }
+
+    // a financed invoice stays with the merchant until the financier is repaid
+    if invoice.financing.is_some() {
+        return Err(ContractError::Unauthorized {
+            error: String::from("Only payee can transfer receivable that is not financed"),
+        });
+    }
+
+    // the payment will be routed to the new payee
+    let new_payee = deps.api.addr_validate(&new_payee)?;
+    if is_denylisted(deps.storage, &new_payee)? {
+        return Err(ContractError::PayeeDenylisted {
+            address: new_payee.to_string(),
+        });
+    }
+    invoice.payee = Some(new_payee.to_owned());
+    get_invoice_storage(deps.storage).save(invoice.id.as_bytes(), &invoice)?;
+
+    // extend the ownership chain
+    let transfer = ReceivableTransfer {
+        from: payee.to_owned(),
+        to: new_payee.to_owned(),
+        block_height: env.block.height,
+        block_time: env.block.time,
+    };
+    let event = ReceivableTransferred::new(&invoice, &transfer);
+    get_receivable_storage(deps.storage).update(
+        invoice.id.as_bytes(),
+        |transfers| -> StdResult<_> {
+            let mut transfers = transfers.unwrap_or_default();
+            transfers.push(transfer);
+            Ok(transfers)
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_event(event.to_event())
+        .add_messages(update_invoice_record(state, &invoice, RecordStatus::Open)?)
+        .add_attributes(vec![
+            attr("action", Action::TransferReceivable.to_string()),
+            attr("id", &invoice.id),
+            attr("from", payee),
+            attr("to", new_payee),
+        ]))
+}
+
+fn withdraw_credit(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // get state for attrs
+    let state = &config_read(deps.storage).load()?;
+
+    // funds should not be sent
+    if !info.funds.is_empty() {
+        return Err(ContractError::SentFundsUnsupported);
+    }
+
+    ensure_sender_allowed(deps.storage, &info.sender)?;
+
+    // ensure there is credit to withdraw
+    let mut credit_storage = get_credit_storage(deps.storage);
+    let credit = credit_storage
+        .may_load(info.sender.as_bytes())?
+        .unwrap_or_default();
+    if credit.is_zero() {
+        return Err(ContractError::NoCreditBalance);
+    }
+
+    let response = Response::new()
+        .add_attributes(vec![
+            attr("action", Action::WithdrawCredit.to_string()),
+            attr("denom", &state.denom),
+            attr("amount", credit.to_string()),
+            attr("sender", info.sender.to_owned()),
+        ])
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: coins(credit.into(), state.denom.to_owned()),
+        });
+
+    // clear credit
+    credit_storage.remove(info.sender.as_bytes());
+
+    Ok(response)
+}
+
+#[entry_point]
+pub fn query(deps: Deps<ProvenanceQuery>, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    msg.validate()?;
+
+    match msg {
+        QueryMsg::GetContractInfo {} => to_binary(&ContractInfoResponse::from(
+            config_read(deps.storage).load()?,
+        )),
+        QueryMsg::GetContractName {} => to_binary(&query_contract_name(deps)?),
+        QueryMsg::GetVersionInfo {} => to_binary(&VersionInfoResponse::from(
+            cw2::get_contract_version(deps.storage)?,
+        )),
+        QueryMsg::GetInvoice { id } => to_binary(&query_invoice(deps, id)?),
+        QueryMsg::GetCredit { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            let credit = get_credit_storage_read(deps.storage)
+                .may_load(address.as_bytes())?
+                .unwrap_or_default();
+            to_binary(&CreditResponse {
+                address,
+                credit: coin(credit.into(), config_read(deps.storage).load()?.denom),
+            })
+        }
+        QueryMsg::GetReceivable { id } => to_binary(&query_receivable(deps, id)?),
+        QueryMsg::GetPayment {
+            invoice_id,
+            payment_ref,
+        } => to_binary(&PaymentResponse {
+            payment: get_payment_storage_read(deps.storage, &invoice_id)
+                .load(payment_ref.as_bytes())?,
+        }),
+        QueryMsg::GetReceipt {
+            invoice_id,
+            sequence,
+        } => to_binary(&ReceiptResponse {
+            receipt: get_receipt_storage_read(deps.storage, &invoice_id)
+                .load(&sequence.to_be_bytes())?,
+        }),
+        QueryMsg::ListReceipts {
+            invoice_id,
+            payer,
+            start_after,
+            limit,
+        } => to_binary(&query_receipts(
+            deps,
+            invoice_id,
+            payer,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::GetDenylist { start_after, limit } => {
+            to_binary(&query_denylist(deps, start_after, limit)?)
+        }
+        QueryMsg::ListInvoices { start_after, limit } => {
+            to_binary(&query_invoices(deps, start_after, limit)?)
+        }
+        QueryMsg::GetInvoiceHistory { id } => to_binary(&query_invoice_history(deps, id)?),
+        QueryMsg::GetDeposit { address } => {
+            let address = deps.api.addr_validate(&address)?;
+            let deposits = get_deposit_storage_read(deps.storage, &address)
+                .range(None, None, Order::Ascending)
+                .map(|item| {
+                    item.and_then(|(denom, amount)| {
+                        Ok(coin(amount.into(), String::from_utf8(denom)?))
+                    })
+                })
+                .collect::<StdResult<Vec<Coin>>>()?;
+            to_binary(&DepositResponse { address, deposits })
+        }
+        QueryMsg::GetPayerStatement {
+            payer,
+            start_after,
+            limit,
+        } => to_binary(&query_payer_statement(deps, payer, start_after, limit)?),
+        QueryMsg::GetStats {} => {
+            let stats = get_stats_storage_read(deps.storage)
+                .range(None, None, Order::Ascending)
+                .map(|item| item.map(|(_, stats)| stats))
+                .collect::<StdResult<Vec<InvoiceStats>>>()?;
+            to_binary(&StatsResponse { stats })
+        }
+    }
+}
+
+fn query_invoice(deps: Deps<ProvenanceQuery>, id: String) -> StdResult<InvoiceResponse> {
+    // pruned invoices are still reported, with their expiry in the past
+    let invoice = match get_invoice_storage_read(deps.storage).may_load(id.as_bytes())? {
+        Some(invoice) => invoice,
+        None => get_expired_invoice_storage_read(deps.storage).load(id.as_bytes())?,
+    };
+
+    let display = config_read(deps.storage)
+        .load()?
+        .denom_metadata
+        .map(|metadata| DisplayAmounts {
+            amount: to_display_amount(invoice.amount, &metadata),
+            amount_due: to_display_amount(invoice.amount_due, &metadata),
+            denom: metadata.display,
+        });
+
+    Ok(InvoiceResponse { invoice, display })
+}
+
+fn query_receivable(deps: Deps<ProvenanceQuery>, id: String) -> StdResult<ReceivableOwnership> {
+    let transfers = get_receivable_storage_read(deps.storage)
+        .may_load(id.as_bytes())?
+        .unwrap_or_default();
+
+    // invoices paid before they were kept for lookups are only known by their transfers
+    let (payee, financing) = match find_invoice(deps.storage, &id)? {
+        Some(invoice) => (
+            invoice.payee(&config_read(deps.storage).load()?).to_owned(),
+            invoice.financing,
+        ),
+        None => match transfers.last() {
+            Some(transfer) => (transfer.to.to_owned(), None),
+            None => return Err(StdError::not_found("invoice::state::Invoice")),
+        },
+    };
+
+    Ok(ReceivableOwnership {
+        id,
+        payee,
+        transfers,
+        financing,
+    })
+}
+
+fn query_invoice_history(deps: Deps<ProvenanceQuery>, id: String) -> StdResult<InvoiceHistory> {
+    let current = find_invoice(deps.storage, &id)?;
+    let revisions = get_revision_storage_read(deps.storage).may_load(id.as_bytes())?;
+
+    // an invoice that was never amended has no prior terms
+    let revisions = match (&current, revisions) {
+        (_, Some(revisions)) => revisions,
+        (Some(_), None) => vec![],
+        (None, None) => return Err(StdError::not_found("invoice::state::Invoice")),
+    };
+
+    Ok(InvoiceHistory {
+        id,
+        current,
+        revisions,
+    })
+}
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+fn query_payer_statement(
+    deps: Deps<ProvenanceQuery>,
+    payer: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PayerStatement> {
+    let payer = deps.api.addr_validate(&payer)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let entries = match start_after.map(|sequence| sequence.checked_add(1)) {
+        // nothing follows the last possible sequence
+        Some(None) => vec![],
+        start => {
+            let start = start
+                .flatten()
+                .map(|sequence| sequence.to_be_bytes().to_vec());
+            get_ledger_storage_read(deps.storage, &payer)
+                .range(start.as_deref(), None, Order::Ascending)
+                .take(limit)
+                .map(|item| item.map(|(_, entry)| entry))
+                .collect::<StdResult<Vec<LedgerEntry>>>()?
+        }
+    };
+
+    let balance = get_ledger_account_storage_read(deps.storage)
+        .may_load(payer.as_bytes())?
+        .unwrap_or_default()
+        .balance;
+
+    Ok(PayerStatement {
+        payer,
+        entries,
+        balance,
+    })
+}
+
+fn query_contract_name(deps: Deps<ProvenanceQuery>) -> StdResult<ContractName> {
+    let name = config_read(deps.storage)
+        .load()?
+        .contract_name
+        .map(|name| ProvenanceQuerier::new(&deps.querier).resolve_name(name))
+        .transpose()?;
+
+    Ok(ContractName { name })
+}
+
+fn query_invoices(
+    deps: Deps<ProvenanceQuery>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<InvoiceList> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    // the first key after `start_after`
+    let start = start_after.map(|id| [id.as_bytes(), &[0]].concat());
+
+    let invoices = get_invoice_storage_read(deps.storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, invoice)| invoice))
+        .collect::<StdResult<Vec<Invoice>>>()?;
+
+    Ok(InvoiceList { invoices })
+}
+
+fn query_denylist(
+    deps: Deps<ProvenanceQuery>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Denylist> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    // the first key after `start_after`
+    let start = start_after.map(|address| [address.as_bytes(), &[0]].concat());
+
+    let entries = get_denylist_storage_read(deps.storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, entry)| entry))
+        .collect::<StdResult<Vec<DenylistEntry>>>()?;
+
+    Ok(Denylist { entries })
+}
+
+fn query_receipts(
+    deps: Deps<ProvenanceQuery>,
+    invoice_id: Option<String>,
+    payer: Option<String>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ReceiptList> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let receipt_storage = match (invoice_id, payer) {
+        (Some(invoice_id), _) => get_receipt_storage_read(deps.storage, &invoice_id),
+        (None, Some(payer)) => {
+            get_payer_receipt_storage_read(deps.storage, &deps.api.addr_validate(&payer)?)
+        }
+        (None, None) => return Err(StdError::generic_err("invoice_id or payer is required")),
+    };
+
+    let start = match start_after.map(|sequence| sequence.checked_add(1)) {
+        // nothing follows the last possible sequence
+        Some(None) => return Ok(ReceiptList { receipts: vec![] }),
+        start => start
+            .flatten()
+            .map(|sequence| sequence.to_be_bytes().to_vec()),
+    };
+
+    let receipts = receipt_storage
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, receipt)| receipt))
+        .collect::<StdResult<Vec<PaymentReceipt>>>()?;
+
+    Ok(ReceiptList { receipts })
+}
+
+enum Action {
+    Add,
+    Accept,
+    Reject,
+    Amend,
+    Cancel,
+    PruneExpired,
+    Pay,
+    PayWithSignedInvoice,
+    UpdateSigningKey,
+    UpdatePaymentAttributes,
+    UpdateRequiredAttributes,
+    UpdateScopeSpecification,
+    UpdateConfig,
+    AddToDenylist,
+    RemoveFromDenylist,
+    IssueCreditNote,
+    WithdrawCredit,
+    Deposit,
+    Withdraw,
+    SettleFromDeposit,
+    TransferReceivable,
+    Fund,
+    UpdateFinancing,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Add => write!(f, "add_invoice"),
+            Action::Accept => write!(f, "accept_invoice"),
+            Action::Reject => write!(f, "reject_invoice"),
+            Action::Amend => write!(f, "amend_invoice"),
+            Action::Cancel => write!(f, "cancel_invoice"),
+            Action::PruneExpired => write!(f, "prune_expired"),
+            Action::Pay => write!(f, "pay_invoice"),
+            Action::PayWithSignedInvoice => write!(f, "pay_signed_invoice"),
+            Action::UpdateSigningKey => write!(f, "update_signing_key"),
+            Action::UpdatePaymentAttributes => write!(f, "update_payment_attributes"),
+            Action::UpdateRequiredAttributes => write!(f, "update_required_attributes"),
+            Action::UpdateScopeSpecification => write!(f, "update_scope_specification"),
+            Action::UpdateConfig => write!(f, "update_config"),
+            Action::AddToDenylist => write!(f, "add_to_denylist"),
+            Action::RemoveFromDenylist => write!(f, "remove_from_denylist"),
+            Action::IssueCreditNote => write!(f, "issue_credit_note"),
+            Action::WithdrawCredit => write!(f, "withdraw_credit"),
+            Action::Deposit => write!(f, "deposit"),
+            Action::Withdraw => write!(f, "withdraw"),
+            Action::SettleFromDeposit => write!(f, "settle_from_deposit"),
+            Action::TransferReceivable => write!(f, "transfer_receivable"),
+            Action::Fund => write!(f, "fund_invoice"),
+            Action::UpdateFinancing => write!(f, "update_financing"),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::events::parse_events;
+    use crate::metadata::InvoiceRecord;
+    use crate::state::LedgerEntryKind;
+    use crate::state::{config, DenomMetadata, Financing, State, Tally};
+    use cosmwasm_std::testing::{mock_env, mock_info};
+    use cosmwasm_std::SubMsg;
+    use cosmwasm_std::{coin, from_binary, Addr, Coin, CosmosMsg, StdError, Storage};
+    use k256::ecdsa::signature::Signer;
+    use k256::ecdsa::{Signature, SigningKey, VerifyingKey};
+    use provwasm_mocks::mock_dependencies;
+    use provwasm_std::{
+        delete_attributes, MetadataMsgParams, Party, PartyType, ProvenanceMsgParams, Scope,
+    };
+
+    use crate::state::get_invoice_storage_read;
+
+    use super::*;
+
+    const TEST_DENOM: &str = "testdenom";
+    const INVOICE_ID: &str = "63069195-bc51-41bd-80d7-0ab84b98e283";
+    const BUSINESS_NAME: &str = "company";
+    const ADMIN: &str = "admin";
+    const RECIPIENT: &str = "recipient";
+    const DESCRIPTION: &str = "description";
+
+    #[test]
+    fn create_invoice_success() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let amount = Uint128::new(100);
+        let add_msg = ExecuteMsg::AddInvoice {
+            id: INVOICE_ID.into(),
+            amount,
+            description: Option::Some(DESCRIPTION.into()),
+            due_date: None,
+            expires_at: None,
+            payer: None,
+            display_amount: None,
+        };
+
+        let sender_info = mock_info(ADMIN, &[]);
+
+        // execute add invoice
+        let add_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            add_msg.clone(),
+        );
+
+        // verify invoice response
+        match add_response {
+            Ok(response) => {
+                assert_eq!(response.attributes.len(), 5);
+                assert_eq!(
+                    response.attributes[0],
+                    attr("action", Action::Add.to_string())
+                );
+                assert_eq!(response.attributes[1], attr("id", INVOICE_ID));
+                assert_eq!(response.attributes[2], attr("denom", TEST_DENOM));
+                assert_eq!(response.attributes[3], attr("amount", amount.to_string()));
+                assert_eq!(response.attributes[4], attr("recipient", RECIPIENT));
+
+                assert_eq!(
+                    vec![InvoiceAdded {
+                        id: INVOICE_ID.into(),
+                        amount,
+                        denom: TEST_DENOM.into(),
+                        recipient: Addr::unchecked(RECIPIENT),
+                        payer: None,
+                        due_date: None,
+                        expires_at: None,
+                        scope_id: None,
+                    }],
+                    parse_events::<InvoiceAdded>(&response.events).unwrap()
+                );
+            }
+            Err(error) => {
+                panic!("failed to create add invoice: {:?}", error)
+            }
+        }
+
+        // verify invoice stored
+        let invoice_storage = get_invoice_storage_read(&deps.storage);
+
+        match invoice_storage.load(INVOICE_ID.as_bytes()) {
+            Ok(stored_invoice) => {
+                assert_eq!(
+                    stored_invoice,
+                    Invoice {
+                        description: Option::Some(DESCRIPTION.into()),
+                        ..test_invoice(amount.u128())
+                    }
+                )
+            }
+            _ => {
+                panic!("invoice was not found in storage")
+            }
+        }
+    }
+
+    #[test]
+    fn create_invoice_with_funds_throws_error() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let amount = Uint128::new(100);
+        let add_msg = ExecuteMsg::AddInvoice {
+            id: INVOICE_ID.into(),
+            amount,
+            description: Option::Some(DESCRIPTION.into()),
+            due_date: None,
+            expires_at: None,
+            payer: None,
+            display_amount: None,
+        };
+
+        let sender_info = mock_info(ADMIN, &[coin(amount.u128(), TEST_DENOM)]);
+
+        // execute add invoice
+        let add_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            add_msg.clone(),
+        );
+
+        assert_sent_funds_unsupported_error(add_response);
+    }
+
+    #[test]
+    fn create_invoice_invalid_data_error() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let amount = Uint128::new(100);
+        let add_msg = ExecuteMsg::AddInvoice {
+            id: "".into(),
+            amount,
+            description: Option::Some(DESCRIPTION.into()),
+            due_date: None,
+            expires_at: None,
+            payer: None,
+            display_amount: None,
+        };
+
+        let sender_info = mock_info(ADMIN, &[]);
+
+        // execute add invoice
+        let add_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            add_msg.clone(),
+        );
+
+        // verify invoice response
+        match add_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvalidFields { fields } => {
+                    assert!(fields.contains(&"id".into()));
+                }
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+    }
+
+    #[test]
+    fn create_invoice_existing_id_error() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        store_test_invoice(&mut deps.storage, &test_invoice(1));
+
+        let amount = Uint128::new(100);
+        let add_msg = ExecuteMsg::AddInvoice {
+            id: INVOICE_ID.into(),
+            amount,
+            description: Option::Some(DESCRIPTION.into()),
+            due_date: None,
+            expires_at: None,
+            payer: None,
+            display_amount: None,
+        };
+
+        let sender_info = mock_info(ADMIN, &[]);
+
+        // execute add invoice
+        let add_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            add_msg.clone(),
+        );
+
+        // verify invoice response
+        match add_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvalidFields { fields } => {
+                    assert!(fields.contains(&"id".into()));
+                }
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+    }
+
+    #[test]
+    fn create_invoice_unauthorized_error() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let amount = Uint128::new(100);
+        let add_msg = ExecuteMsg::AddInvoice {
+            id: INVOICE_ID.into(),
+            amount,
+            description: Option::Some(DESCRIPTION.into()),
+            due_date: None,
+            expires_at: None,
+            payer: None,
+            display_amount: None,
+        };
+
+        let sender_info = mock_info("invalid_sender", &[]);
+
+        // execute add invoice
+        let add_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            add_msg.clone(),
+        );
+
+        assert_not_authorized_error(add_response);
+    }
+
+    #[test]
+    fn cancel_invoice_success() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let amount = Uint128::new(5);
+        store_test_invoice(&mut deps.storage, &test_invoice(amount.u128()));
+
+        let cancel_msg = ExecuteMsg::CancelInvoice {
+            id: INVOICE_ID.into(),
+        };
+
+        let sender_info = mock_info(ADMIN, &[]);
+
+        // execute cancel invoice
+        let cancel_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            cancel_msg.clone(),
+        );
+
+        // verify invoice response
+        match cancel_response {
+            Ok(response) => {
+                assert_eq!(response.attributes.len(), 5);
+                assert_eq!(
+                    response.attributes[0],
+                    attr("action", Action::Cancel.to_string())
+                );
+                assert_eq!(response.attributes[1], attr("id", INVOICE_ID));
+                assert_eq!(response.attributes[2], attr("denom", TEST_DENOM));
+                assert_eq!(response.attributes[3], attr("amount", amount.to_string()));
+                assert_eq!(response.attributes[4], attr("recipient", RECIPIENT));
+
+                assert_eq!(
+                    vec![InvoiceCancelled {
+                        id: INVOICE_ID.into(),
+                        amount,
+                        denom: TEST_DENOM.into(),
+                    }],
+                    parse_events::<InvoiceCancelled>(&response.events).unwrap()
+                );
+            }
+            Err(error) => {
+                panic!("failed to create add invoice: {:?}", error)
+            }
+        }
+
+        // verify invoice stored
+        let invoice_storage = get_invoice_storage_read(&deps.storage);
+
+        match invoice_storage.load(INVOICE_ID.as_bytes()) {
+            Ok(..) => panic!("expected error, but found"),
+            Err(error) => match error {
+                StdError::NotFound { .. } => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+    }
+
+    #[test]
+    fn cancel_invoice_not_found_error() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let cancel_msg = ExecuteMsg::CancelInvoice {
+            id: INVOICE_ID.into(),
+        };
+
+        let sender_info = mock_info(ADMIN, &[]);
+
+        // execute pay invoice
+        let cancel_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            cancel_msg.clone(),
+        );
+
+        // verify invoice response
+        match cancel_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::LoadInvoiceFailed { .. } => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+    }
+
+    #[test]
+    fn cancel_invoice_with_funds_throws_error() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let amount = Uint128::new(5);
+        store_test_invoice(&mut deps.storage, &test_invoice(amount.u128()));
+
+        let cancel_msg = ExecuteMsg::CancelInvoice {
+            id: INVOICE_ID.into(),
+        };
+
+        let sender_info = mock_info(ADMIN, &[coin(amount.u128(), TEST_DENOM)]);
+
+        // execute cancel invoice
+        let cancel_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            cancel_msg.clone(),
+        );
+
+        assert_sent_funds_unsupported_error(cancel_response);
+    }
+
+    #[test]
+    fn cancel_invoice_unauthorized_error() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let amount = Uint128::new(5);
+        store_test_invoice(&mut deps.storage, &test_invoice(amount.u128()));
+
+        let cancel_msg = ExecuteMsg::CancelInvoice {
+            id: INVOICE_ID.into(),
+        };
+
+        let sender_info = mock_info("invalid_sender", &[]);
+
+        // execute cancel invoice
+        let cancel_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            cancel_msg.clone(),
+        );
+
+        assert_not_authorized_error(cancel_response);
+    }
+
+    #[test]
+    fn pay_invoice_success() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let amount = Uint128::new(5);
+        store_test_invoice(&mut deps.storage, &test_invoice(amount.u128()));
+
+        let pay_invoice = ExecuteMsg::PayInvoice {
+            id: INVOICE_ID.into(),
+            payment_ref: None,
+        };
+
+        let sender_info = mock_info("payer", &[coin(amount.u128(), TEST_DENOM)]);
+
+        // execute pay invoice
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            pay_invoice.clone(),
+        );
+
+        // verify invoice response
+        match pay_response {
+            Ok(response) => {
+                assert_eq!(response.attributes.len(), 6);
+                assert_eq!(
+                    response.attributes[0],
+                    attr("action", Action::Pay.to_string())
+                );
+                assert_eq!(response.attributes[1], attr("id", INVOICE_ID));
+                assert_eq!(response.attributes[2], attr("denom", TEST_DENOM));
+                assert_eq!(response.attributes[3], attr("amount", amount.to_string()));
+                assert_eq!(response.attributes[4], attr("sender", "payer"));
+                assert_eq!(response.attributes[5], attr("recipient", RECIPIENT));
+
+                assert_eq!(
+                    vec![InvoicePaid {
+                        id: INVOICE_ID.into(),
+                        amount,
+                        denom: TEST_DENOM.into(),
+                        payer: Addr::unchecked("payer"),
+                        payee: Addr::unchecked(RECIPIENT),
+                        receipt: 1,
+                    }],
+                    parse_events::<InvoicePaid>(&response.events).unwrap()
+                );
+
+                assert_eq!(response.messages.len(), 1);
+                assert_eq!(
+                    response.messages[0].msg,
+                    CosmosMsg::Bank(BankMsg::Send {
+                        to_address: RECIPIENT.to_string(),
+                        amount: coins(amount.u128(), TEST_DENOM),
+                    })
+                );
+            }
+            Err(error) => {
+                panic!("failed to create add invoice: {:?}", error)
+            }
+        }
+
+        // verify invoice stored
+        let invoice_storage = get_invoice_storage_read(&deps.storage);
+
+        match invoice_storage.load(INVOICE_ID.as_bytes()) {
+            Ok(..) => panic!("expected error, but found"),
+            Err(error) => match error {
+                StdError::NotFound { .. } => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+    }
+
+    #[test]
+    fn pay_invoice_not_found_error() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let pay_msg = ExecuteMsg::PayInvoice {
+            id: INVOICE_ID.into(),
+            payment_ref: None,
+        };
+
+        let amount = Uint128::new(5);
+        let sender_info = mock_info("payer", &[coin(amount.u128(), TEST_DENOM)]);
+
+        // execute pay invoice
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            pay_msg.clone(),
+        );
+
+        // verify invoice response
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::LoadInvoiceFailed { .. } => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+    }
+
+    #[test]
+    fn pay_invoice_mismatch_funds_error() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let amount = Uint128::new(5);
+        store_test_invoice(&mut deps.storage, &test_invoice(amount.u128()));
+
+        let pay_msg = ExecuteMsg::PayInvoice {
+            id: INVOICE_ID.into(),
+            payment_ref: None,
+        };
+
+        // mismatch sender on coin amount
+        let mut sender_info = mock_info("payer", &[coin(10, TEST_DENOM)]);
+
+        // execute pay invoice
+        let mut pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            pay_msg.clone(),
+        );
+
+        // verify invoice response
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::SentFundsInvoiceMismatch => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // mismatch sender on coin denom
+        sender_info = mock_info("payer", &[coin(5, "wrongdenom")]);
+
+        pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            sender_info.clone(),
+            pay_msg.clone(),
+        );
+
+        // verify invoice response
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::SentFundsInvoiceMismatch => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // verify invoice stored
+        let invoice_storage = get_invoice_storage_read(&deps.storage);
+
+        match invoice_storage.load(INVOICE_ID.as_bytes()) {
+            Ok(stored_invoice) => {
+                assert_eq!(stored_invoice, test_invoice(amount.u128()))
+            }
+            _ => {
+                panic!("invoice was not found in storage")
+            }
+        }
+    }
+
+    #[test]
+    fn get_stats_tracks_invoice_lifecycle() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let paid_id = "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5";
+        let cancelled_id = "0d8cf0a5-6b4b-4c43-9d1c-2f2f1b6f7e0a";
+
+        // add three invoices
+        for (id, amount) in [(INVOICE_ID, 5), (paid_id, 7), (cancelled_id, 11)] {
+            let add_msg = ExecuteMsg::AddInvoice {
+                id: id.into(),
+                amount: Uint128::new(amount),
+                description: Option::None,
+                due_date: None,
+                expires_at: None,
+                payer: None,
+                display_amount: None,
+            };
+            execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), add_msg).unwrap();
+        }
+
+        // pay one and cancel another
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(7, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: paid_id.into(),
+                payment_ref: None,
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::CancelInvoice {
+                id: cancelled_id.into(),
+            },
+        )
+        .unwrap();
+
+        // verify stats query
+        let stats = from_binary::<StatsResponse>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetStats {}).unwrap(),
+        )
+        .unwrap()
+        .stats;
+
+        assert_eq!(
+            stats,
+            vec![InvoiceStats {
+                denom: TEST_DENOM.into(),
+                open: Tally {
+                    count: 1,
+                    amount: Uint128::new(5),
+                },
+                paid: Tally {
+                    count: 1,
+                    amount: Uint128::new(7),
+                },
+                cancelled: Tally {
+                    count: 1,
+                    amount: Uint128::new(11),
+                },
+                expired: Tally::default(),
+                written_off: Tally::default(),
+            }]
+        );
+    }
+
+    #[test]
+    fn get_payer_statement_tracks_ledger() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let paid_id = "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5";
+        let cancelled_id = "0d8cf0a5-6b4b-4c43-9d1c-2f2f1b6f7e0a";
+
+        // bill three invoices to the customer
+        for (id, amount) in [(INVOICE_ID, 5), (paid_id, 7), (cancelled_id, 11)] {
+            let add_msg = ExecuteMsg::AddInvoice {
+                id: id.into(),
+                amount: Uint128::new(amount),
+                description: Option::None,
+                due_date: None,
+                expires_at: None,
+                payer: Option::Some("customer".into()),
+                display_amount: None,
+            };
+            let add_response =
+                execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), add_msg).unwrap();
+            assert_eq!(add_response.attributes[5], attr("payer", "customer"));
+        }
+
+        // pay one and cancel another
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("customer", &[coin(7, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: paid_id.into(),
+                payment_ref: None,
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::CancelInvoice {
+                id: cancelled_id.into(),
+            },
+        )
+        .unwrap();
+
+        // verify first page of the statement
+        let statement: PayerStatement = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetPayerStatement {
+                    payer: "customer".into(),
+                    start_after: None,
+                    limit: Some(3),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(statement.payer, Addr::unchecked("customer"));
+        assert_eq!(statement.balance, Uint128::new(5));
+        assert_eq!(
+            statement
+                .entries
+                .iter()
+                .map(|entry| (entry.sequence, entry.kind.clone(), entry.balance.u128()))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, LedgerEntryKind::Invoice, 5),
+                (2, LedgerEntryKind::Invoice, 12),
+                (3, LedgerEntryKind::Invoice, 23),
+            ]
+        );
+
+        // verify the next page
+        let statement: PayerStatement = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetPayerStatement {
+                    payer: "customer".into(),
+                    start_after: Some(3),
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            statement
+                .entries
+                .iter()
+                .map(|entry| (
+                    entry.sequence,
+                    entry.invoice_id.as_str(),
+                    entry.kind.clone(),
+                    entry.balance.u128()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (4, paid_id, LedgerEntryKind::Payment, 16),
+                (5, cancelled_id, LedgerEntryKind::Credit, 5),
+            ]
+        );
+
+        // nothing follows the last possible sequence
+        let statement: PayerStatement = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetPayerStatement {
+                    payer: "customer".into(),
+                    start_after: Some(u64::MAX),
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(statement.entries.is_empty());
+        assert_eq!(statement.balance, Uint128::new(5));
+    }
+
+    #[test]
+    fn pay_invoice_without_payer_bills_sender() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        store_test_invoice(&mut deps.storage, &test_invoice(5));
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: INVOICE_ID.into(),
+                payment_ref: None,
+            },
+        )
+        .unwrap();
+
+        let statement: PayerStatement = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetPayerStatement {
+                    payer: "payer".into(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(statement.balance, Uint128::zero());
+        assert_eq!(statement.entries.len(), 2);
+        assert_eq!(statement.entries[0].kind, LedgerEntryKind::Invoice);
+        assert_eq!(statement.entries[1].kind, LedgerEntryKind::Payment);
+    }
+
+    #[test]
+    fn pay_invoice_overpayment_credited() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(
+            &mut deps.storage,
+            &State {
+                overpayment: OverpaymentMode::Credit,
+                ..test_state()
+            },
+        );
+
+        let second_id = "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5";
+        for id in [INVOICE_ID, second_id] {
+            store_test_invoice(
+                &mut deps.storage,
+                &Invoice {
+                    id: id.into(),
+                    ..test_invoice(5)
+                },
+            );
+        }
+
+        // overpay the first invoice
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(8, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: INVOICE_ID.into(),
+                payment_ref: None,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(pay_response.attributes[6], attr("credit_applied", "0"));
+        assert_eq!(pay_response.attributes[7], attr("credit_added", "3"));
+        assert_eq!(
+            pay_response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: RECIPIENT.to_string(),
+                amount: coins(5, TEST_DENOM),
+            })
+        );
+        assert_eq!(query_credit(deps.as_ref(), "payer"), coin(3, TEST_DENOM));
+
+        // credit is applied to the next invoice
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(2, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: second_id.into(),
+                payment_ref: None,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(pay_response.attributes[6], attr("credit_applied", "3"));
+        assert_eq!(pay_response.attributes[7], attr("credit_added", "0"));
+        assert_eq!(
+            pay_response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: RECIPIENT.to_string(),
+                amount: coins(5, TEST_DENOM),
+            })
+        );
+        assert_eq!(query_credit(deps.as_ref(), "payer"), coin(0, TEST_DENOM));
+    }
+
+    #[test]
+    fn withdraw_credit_success() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(
+            &mut deps.storage,
+            &State {
+                overpayment: OverpaymentMode::Credit,
+                ..test_state()
+            },
+        );
+
+        get_credit_storage(&mut deps.storage)
+            .save(b"payer", &Uint128::new(3))
+            .unwrap();
+
+        let withdraw_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[]),
+            ExecuteMsg::WithdrawCredit {},
+        );
+
+        match withdraw_response {
+            Ok(response) => {
+                assert_eq!(
+                    response.attributes[0],
+                    attr("action", Action::WithdrawCredit.to_string())
+                );
+                assert_eq!(response.attributes[2], attr("amount", "3"));
+                assert_eq!(
+                    response.messages[0].msg,
+                    CosmosMsg::Bank(BankMsg::Send {
+                        to_address: "payer".into(),
+                        amount: coins(3, TEST_DENOM),
+                    })
+                );
+            }
+            Err(error) => panic!("failed to withdraw credit: {:?}", error),
+        }
+
+        assert_eq!(query_credit(deps.as_ref(), "payer"), coin(0, TEST_DENOM));
+
+        // nothing left to withdraw
+        let withdraw_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[]),
+            ExecuteMsg::WithdrawCredit {},
+        );
+
+        match withdraw_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::NoCreditBalance => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+    }
+
+    #[test]
+    fn deposit_settles_new_invoice() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        // prefund the customer's account
+        let deposit_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("customer", &[coin(12, TEST_DENOM)]),
+            ExecuteMsg::Deposit {},
+        )
+        .unwrap();
+        assert_eq!(
+            deposit_response.attributes[0],
+            attr("action", Action::Deposit.to_string())
+        );
+        assert_eq!(deposit_response.attributes[4], attr("balance", "12"));
+
+        // add invoice billed to the customer
+        let add_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::AddInvoice {
+                id: INVOICE_ID.into(),
+                amount: Uint128::new(5),
+                description: Option::None,
+                due_date: None,
+                expires_at: None,
+                payer: Option::Some("customer".into()),
+                display_amount: None,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            add_response.attributes[6],
+            attr("settled_from_deposit", "true")
+        );
+        assert_eq!(
+            add_response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: RECIPIENT.to_string(),
+                amount: coins(5, TEST_DENOM),
+            })
+        );
+
+        // verify invoice is settled and deposit reduced
+        assert!(get_invoice_storage_read(&deps.storage)
+            .may_load(INVOICE_ID.as_bytes())
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            query_deposit(deps.as_ref(), "customer"),
+            coins(7, TEST_DENOM)
+        );
+
+        // an invoice larger than the deposit stays open
+        let add_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::AddInvoice {
+                id: "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5".into(),
+                amount: Uint128::new(10),
+                description: Option::None,
+                due_date: None,
+                expires_at: None,
+                payer: Option::Some("customer".into()),
+                display_amount: None,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(add_response.attributes.len(), 6);
+        assert!(add_response.messages.is_empty());
+        assert_eq!(
+            query_deposit(deps.as_ref(), "customer"),
+            coins(7, TEST_DENOM)
+        );
+    }
+
+    #[test]
+    fn settle_from_deposit_success() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        store_test_invoice(
+            &mut deps.storage,
+            &Invoice {
+                payer: Some(Addr::unchecked("customer")),
+                ..test_invoice(5)
+            },
+        );
+
+        let settle_msg = ExecuteMsg::SettleFromDeposit {
+            id: INVOICE_ID.into(),
+        };
+
+        // nothing deposited yet
+        let settle_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("customer", &[]),
+            settle_msg.clone(),
+        );
+        match settle_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InsufficientDeposit => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("customer", &[coin(5, TEST_DENOM)]),
+            ExecuteMsg::Deposit {},
+        )
+        .unwrap();
+
+        // only the payer or admin may settle
+        let settle_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("stranger", &[]),
+            settle_msg.clone(),
+        );
+        match settle_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::Unauthorized { .. } => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        let settle_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("customer", &[]),
+            settle_msg,
+        )
+        .unwrap();
+
+        assert_eq!(
+            settle_response.attributes[0],
+            attr("action", Action::SettleFromDeposit.to_string())
+        );
+        assert_eq!(
+            settle_response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: RECIPIENT.to_string(),
+                amount: coins(5, TEST_DENOM),
+            })
+        );
+        assert!(get_invoice_storage_read(&deps.storage)
+            .may_load(INVOICE_ID.as_bytes())
+            .unwrap()
+            .is_none());
+        assert!(query_deposit(deps.as_ref(), "customer").is_empty());
+    }
+
+    #[test]
+    fn withdraw_deposit_success() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("customer", &[coin(5, TEST_DENOM)]),
+            ExecuteMsg::Deposit {},
+        )
+        .unwrap();
+
+        // cannot withdraw more than deposited
+        let withdraw_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("customer", &[]),
+            ExecuteMsg::Withdraw {
+                amount: coin(6, TEST_DENOM),
+            },
+        );
+        match withdraw_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InsufficientDeposit => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        let withdraw_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("customer", &[]),
+            ExecuteMsg::Withdraw {
+                amount: coin(2, TEST_DENOM),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(withdraw_response.attributes[4], attr("balance", "3"));
+        assert_eq!(
+            withdraw_response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "customer".into(),
+                amount: coins(2, TEST_DENOM),
+            })
+        );
+        assert_eq!(
+            query_deposit(deps.as_ref(), "customer"),
+            coins(3, TEST_DENOM)
+        );
+    }
+
+    #[test]
+    fn transfer_receivable_routes_payment() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        store_test_invoice(&mut deps.storage, &test_invoice(5));
+
+        // merchant sells the receivable, financier sells it on
+        let transfer_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::TransferReceivable {
+                id: INVOICE_ID.into(),
+                new_payee: "financier".into(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            transfer_response.attributes[0],
+            attr("action", Action::TransferReceivable.to_string())
+        );
+        assert_eq!(transfer_response.attributes[2], attr("from", RECIPIENT));
+        assert_eq!(transfer_response.attributes[3], attr("to", "financier"));
+
+        // the merchant no longer holds it
+        let transfer_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::TransferReceivable {
+                id: INVOICE_ID.into(),
+                new_payee: ADMIN.into(),
+            },
+        );
+        match transfer_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::Unauthorized { .. } => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("financier", &[]),
+            ExecuteMsg::TransferReceivable {
+                id: INVOICE_ID.into(),
+                new_payee: "fund".into(),
+            },
+        )
+        .unwrap();
+
+        // a sold invoice can no longer be cancelled
+        let cancel_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::CancelInvoice {
+                id: INVOICE_ID.into(),
+            },
+        );
+        match cancel_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::ReceivableTransferred { id } => assert_eq!(INVOICE_ID, id),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // payment goes to the current holder
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: INVOICE_ID.into(),
+                payment_ref: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(pay_response.attributes[5], attr("recipient", "fund"));
+        assert_eq!(
+            pay_response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "fund".into(),
+                amount: coins(5, TEST_DENOM),
+            })
+        );
+
+        // ownership chain outlives the invoice
+        let ownership: ReceivableOwnership = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetReceivable {
+                    id: INVOICE_ID.into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(ownership.payee, Addr::unchecked("fund"));
+        assert_eq!(
+            ownership
+                .transfers
+                .iter()
+                .map(|transfer| (transfer.from.as_str(), transfer.to.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(RECIPIENT, "financier"), ("financier", "fund")]
+        );
+    }
+
+    #[test]
+    fn fund_invoice_repays_financier_on_payment() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        store_test_invoice(&mut deps.storage, &test_invoice(1000));
+
+        let fund_msg = ExecuteMsg::FundInvoice {
+            id: INVOICE_ID.into(),
+        };
+
+        // only admin can whitelist financiers
+        let update_msg = ExecuteMsg::UpdateFinancing {
+            terms: Some(FinancingTerms {
+                advance_rate_bps: 9000,
+                fee_bps: 200,
+            }),
+            financiers: vec!["financier".into()],
+        };
+        assert_not_authorized_error(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("financier", &[]),
+            update_msg.clone(),
+        ));
+        let update_response =
+            execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update_msg).unwrap();
+        let config = parse_events::<ConfigUpdated>(&update_response.events).unwrap();
+        assert_eq!(vec![Addr::unchecked("financier")], config[0].financiers);
+        assert_eq!(
+            Some(FinancingTerms {
+                advance_rate_bps: 9000,
+                fee_bps: 200,
+            }),
+            config[0].financing_terms
+        );
+
+        // only whitelisted financiers can fund
+        let fund_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("stranger", &[coin(900, TEST_DENOM)]),
+            fund_msg.clone(),
+        );
+        match fund_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::Unauthorized { .. } => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // advance must match the terms
+        let fund_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("financier", &[coin(800, TEST_DENOM)]),
+            fund_msg.clone(),
+        );
+        match fund_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::SentFundsInvoiceMismatch => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        let fund_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("financier", &[coin(900, TEST_DENOM)]),
+            fund_msg.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            fund_response.attributes[0],
+            attr("action", Action::Fund.to_string())
+        );
+        assert_eq!(fund_response.attributes[3], attr("advance", "900"));
+        assert_eq!(fund_response.attributes[4], attr("fee", "20"));
+        assert_eq!(
+            fund_response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: RECIPIENT.to_string(),
+                amount: coins(900, TEST_DENOM),
+            })
+        );
+
+        // a financed invoice can no longer be cancelled
+        let cancel_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::CancelInvoice {
+                id: INVOICE_ID.into(),
+            },
+        );
+        match cancel_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvoiceFinanced { id } => assert_eq!(INVOICE_ID, id),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // an invoice is financed only once
+        let fund_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("financier", &[coin(900, TEST_DENOM)]),
+            fund_msg,
+        );
+        match fund_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::FinancingUnavailable { .. } => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // nor credited, which would cut into the financier's repayment
+        let credit_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::IssueCreditNote {
+                invoice_id: INVOICE_ID.into(),
+                amount: Uint128::new(10),
+                reason: "price adjustment".into(),
+            },
+        );
+        match credit_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvoiceFinanced { id } => assert_eq!(INVOICE_ID, id),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // payment repays the financier first
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(1000, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: INVOICE_ID.into(),
+                payment_ref: None,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(pay_response.messages.len(), 2);
+        assert_eq!(
+            pay_response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "financier".into(),
+                amount: coins(920, TEST_DENOM),
+            })
+        );
+        assert_eq!(
+            pay_response.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: RECIPIENT.into(),
+                amount: coins(80, TEST_DENOM),
+            })
+        );
+
+        // the paid receivable still names its holder and financier
+        let ownership: ReceivableOwnership = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetReceivable {
+                    id: INVOICE_ID.into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(Addr::unchecked(RECIPIENT), ownership.payee);
+        assert!(ownership.transfers.is_empty());
+        assert_eq!(
+            Some(Addr::unchecked("financier")),
+            ownership.financing.map(|financing| financing.financier)
+        );
+
+        let history: InvoiceHistory = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetInvoiceHistory {
+                    id: INVOICE_ID.into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(Uint128::new(1000), history.current.unwrap().amount);
+        assert!(history.revisions.is_empty());
+    }
+
+    #[test]
+    fn issue_credit_note_reduces_amount_due() {
+        let mut deps = mock_dependencies(&[]);
+
+        let state = State {
+            attribute_name: Some("invoice.shoeco.pb".into()),
+            ..test_state()
+        };
+        setup_test_base(&mut deps.storage, &state);
+
+        let scope_address = scope_id(INVOICE_ID).unwrap();
+        store_test_invoice(
+            &mut deps.storage,
+            &Invoice {
+                payer: Some(Addr::unchecked("payer")),
+                scope_id: Some(scope_address.to_owned()),
+                ..test_invoice(100)
+            },
+        );
+
+        // only admin can issue credit notes
+        assert_not_authorized_error(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[]),
+            ExecuteMsg::IssueCreditNote {
+                invoice_id: INVOICE_ID.into(),
+                amount: Uint128::new(30),
+                reason: "damaged goods".into(),
+            },
+        ));
+
+        let credit_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::IssueCreditNote {
+                invoice_id: INVOICE_ID.into(),
+                amount: Uint128::new(30),
+                reason: "damaged goods".into(),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            credit_response.attributes[0],
+            attr("action", Action::IssueCreditNote.to_string())
+        );
+        assert_eq!(credit_response.attributes[4], attr("amount_due", "70"));
+        assert_eq!(
+            vec![InvoiceCredited {
+                id: INVOICE_ID.into(),
+                amount: Uint128::new(30),
+                amount_due: Uint128::new(70),
+                denom: TEST_DENOM.into(),
+                reason: "damaged goods".into(),
+            }],
+            parse_events::<InvoiceCredited>(&credit_response.events).unwrap()
+        );
+
+        // the scope record carries the reduced amount due
+        let invoice = get_invoice_storage_read(&deps.storage)
+            .load(INVOICE_ID.as_bytes())
+            .unwrap();
+        let record = InvoiceRecord::new(&state, &invoice, RecordStatus::Open).unwrap();
+        assert_eq!(Uint128::new(100), record.amount);
+        assert_eq!(Uint128::new(70), record.amount_due);
+        assert_eq!(
+            credit_response.messages[1].msg,
+            add_json_attribute(
+                Addr::unchecked(&scope_address),
+                "invoice.shoeco.pb",
+                &record
+            )
+            .unwrap()
+        );
+
+        // invoice shows the original and adjusted amounts
+        let invoice = from_binary::<InvoiceResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetInvoice {
+                    id: INVOICE_ID.into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .invoice;
+        assert_eq!(Uint128::new(100), invoice.amount);
+        assert_eq!(Uint128::new(70), invoice.amount_due);
+        assert_eq!(1, invoice.credit_notes.len());
+        assert_eq!("damaged goods", invoice.credit_notes[0].reason);
+
+        // a credit note cannot clear the invoice
+        let credit_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::IssueCreditNote {
+                invoice_id: INVOICE_ID.into(),
+                amount: Uint128::new(70),
+                reason: "price adjustment".into(),
+            },
+        );
+        match credit_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::CreditNoteExceedsAmountDue => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // payment is for the adjusted amount
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(70, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: INVOICE_ID.into(),
+                payment_ref: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            pay_response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: RECIPIENT.into(),
+                amount: coins(70, TEST_DENOM),
+            })
+        );
+
+        let statement: PayerStatement = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetPayerStatement {
+                    payer: "payer".into(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(LedgerEntryKind::Credit, statement.entries[1].kind);
+        assert_eq!(Uint128::zero(), statement.balance);
+
+        let stats = from_binary::<StatsResponse>(
+            &query(deps.as_ref(), mock_env(), QueryMsg::GetStats {}).unwrap(),
+        )
+        .unwrap()
+        .stats;
+        assert_eq!(Uint128::zero(), stats[0].open.amount);
+        assert_eq!(Uint128::new(70), stats[0].paid.amount);
+    }
+
+    #[test]
+    fn issue_credit_note_only_while_open_and_held() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        store_test_invoice(
+            &mut deps.storage,
+            &Invoice {
+                expires_at: Some(mock_env().block.time.plus_seconds(60)),
+                ..test_invoice(100)
+            },
+        );
+
+        let credit_msg = ExecuteMsg::IssueCreditNote {
+            invoice_id: INVOICE_ID.into(),
+            amount: Uint128::new(30),
+            reason: "damaged goods".into(),
+        };
+
+        // an expired invoice can no longer be credited
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(60);
+        match execute(
+            deps.as_mut(),
+            env,
+            mock_info(ADMIN, &[]),
+            credit_msg.clone(),
+        ) {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvoiceExpired { id } => assert_eq!(INVOICE_ID, id),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // nor can a sold one, whose buyer is owed the full amount due
+        store_test_invoice(
+            &mut deps.storage,
+            &Invoice {
+                payee: Some(Addr::unchecked("factor")),
+                ..test_invoice(100)
+            },
+        );
+        match execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), credit_msg) {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::ReceivableTransferred { id } => assert_eq!(INVOICE_ID, id),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+        let invoice = get_invoice_storage_read(&deps.storage)
+            .load(INVOICE_ID.as_bytes())
+            .unwrap();
+        assert_eq!(Uint128::new(100), invoice.amount_due);
+    }
+
+    #[test]
+    fn amend_invoice_records_revisions() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        store_test_invoice(
+            &mut deps.storage,
+            &Invoice {
+                description: Option::Some(DESCRIPTION.into()),
+                payer: Some(Addr::unchecked("payer")),
+                ..test_invoice(100)
+            },
+        );
+
+        let amend_msg = ExecuteMsg::AmendInvoice {
+            id: INVOICE_ID.into(),
+            amount: Uint128::new(120),
+            description: Option::Some("Air Jordan High Royal".into()),
+            due_date: Some(Timestamp::from_seconds(1_700_000_000)),
+        };
+
+        // only admin can amend
+        assert_not_authorized_error(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[]),
+            amend_msg.clone(),
+        ));
+
+        let amend_response =
+            execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), amend_msg).unwrap();
+
+        assert_eq!(
+            amend_response.attributes[0],
+            attr("action", Action::Amend.to_string())
+        );
+        assert_eq!(amend_response.attributes[3], attr("amount", "120"));
+        assert_eq!(
+            vec![InvoiceAmended {
+                id: INVOICE_ID.into(),
+                amount: Uint128::new(120),
+                amount_due: Uint128::new(120),
+                denom: TEST_DENOM.into(),
+                due_date: Some(Timestamp::from_seconds(1_700_000_000)),
+            }],
+            parse_events::<InvoiceAmended>(&amend_response.events).unwrap()
+        );
+
+        let invoice = from_binary::<InvoiceResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetInvoice {
+                    id: INVOICE_ID.into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .invoice;
+        assert_eq!(Uint128::new(120), invoice.amount_due);
+        assert_eq!(
+            Some(Timestamp::from_seconds(1_700_000_000)),
+            invoice.due_date
+        );
+
+        // prior terms are kept with the amending sender
+        let history: InvoiceHistory = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetInvoiceHistory {
+                    id: INVOICE_ID.into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(1, history.revisions.len());
+        assert_eq!(Uint128::new(100), history.revisions[0].amount);
+        assert_eq!(
+            Some(DESCRIPTION.to_string()),
+            history.revisions[0].description
+        );
+        assert_eq!(Addr::unchecked(ADMIN), history.revisions[0].amended_by);
+        assert_eq!(mock_env().block.time, history.revisions[0].block_time);
+        assert_eq!(Some(invoice), history.current);
+
+        // the payer is billed for the increase
+        let statement: PayerStatement = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetPayerStatement {
+                    payer: "payer".into(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(Uint128::new(120), statement.balance);
+    }
+
+    #[test]
+    fn amend_invoice_only_while_open_and_held() {
+        let mut deps = mock_dependencies(&[]);
+
+        let state = State {
+            attribute_name: Some("invoice.shoeco.pb".into()),
+            ..test_state()
+        };
+        setup_test_base(&mut deps.storage, &state);
+
+        let scope_address = scope_id(INVOICE_ID).unwrap();
+        store_test_invoice(
+            &mut deps.storage,
+            &Invoice {
+                expires_at: Some(mock_env().block.time.plus_seconds(60)),
+                scope_id: Some(scope_address.to_owned()),
+                ..test_invoice(100)
+            },
+        );
+
+        let amend_msg = ExecuteMsg::AmendInvoice {
+            id: INVOICE_ID.into(),
+            amount: Uint128::new(120),
+            description: None,
+            due_date: None,
+        };
+
+        // the scope record is replaced with the new terms
+        let amend_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            amend_msg.clone(),
+        )
+        .unwrap();
+        let invoice = get_invoice_storage_read(&deps.storage)
+            .load(INVOICE_ID.as_bytes())
+            .unwrap();
+        assert_eq!(Uint128::new(120), invoice.amount);
+        assert_eq!(
+            amend_response.messages,
+            update_invoice_record(&state, &invoice, RecordStatus::Open)
+                .unwrap()
+                .into_iter()
+                .map(SubMsg::new)
+                .collect::<Vec<_>>()
+        );
+
+        // an expired invoice can no longer be amended
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(60);
+        match execute(deps.as_mut(), env, mock_info(ADMIN, &[]), amend_msg.clone()) {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvoiceExpired { id } => assert_eq!(INVOICE_ID, id),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // nor can a sold one
+        store_test_invoice(
+            &mut deps.storage,
+            &Invoice {
+                payee: Some(Addr::unchecked("factor")),
+                ..test_invoice(100)
+            },
+        );
+        match execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), amend_msg) {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::ReceivableTransferred { id } => assert_eq!(INVOICE_ID, id),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+    }
+
+    #[test]
+    fn prune_expired_closes_invoices() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let open_id = "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5";
+        for (id, expires_at) in [
+            (INVOICE_ID, Some(mock_env().block.time.plus_seconds(60))),
+            (open_id, None),
+        ] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(ADMIN, &[]),
+                ExecuteMsg::AddInvoice {
+                    id: id.into(),
+                    amount: Uint128::new(5),
+                    description: None,
+                    due_date: None,
+                    expires_at,
+                    payer: Some("payer".into()),
+                    display_amount: None,
+                },
+            )
+            .unwrap();
+        }
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(60);
+
+        // expired invoices can no longer be paid
+        let pay_response = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: INVOICE_ID.into(),
+                payment_ref: None,
+            },
+        );
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvoiceExpired { id } => assert_eq!(INVOICE_ID, id),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // anyone can prune
+        let prune_response = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::PruneExpired { limit: Some(5) },
+        )
+        .unwrap();
+
+        assert_eq!(
+            prune_response.attributes[0],
+            attr("action", Action::PruneExpired.to_string())
+        );
+        assert_eq!(prune_response.attributes[1], attr("count", "1"));
+        assert_eq!(prune_response.attributes[2], attr("ids", INVOICE_ID));
+
+        assert!(get_invoice_storage_read(&deps.storage)
+            .may_load(INVOICE_ID.as_bytes())
+            .unwrap()
+            .is_none());
+        assert!(get_invoice_storage_read(&deps.storage)
+            .may_load(open_id.as_bytes())
+            .unwrap()
+            .is_some());
+
+        // the expired invoice can still be looked up
+        let invoice = from_binary::<InvoiceResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::GetInvoice {
+                    id: INVOICE_ID.into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .invoice;
+        assert_eq!(Uint128::new(5), invoice.amount_due);
+
+        let ownership: ReceivableOwnership = from_binary(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::GetReceivable {
+                    id: INVOICE_ID.into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(Addr::unchecked(RECIPIENT), ownership.payee);
+
+        let history: InvoiceHistory = from_binary(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::GetInvoiceHistory {
+                    id: INVOICE_ID.into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(Some(invoice), history.current);
+        assert!(history.revisions.is_empty());
+
+        // and its id cannot be reused
+        let add_response = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::AddInvoice {
+                id: INVOICE_ID.into(),
+                amount: Uint128::new(5),
+                description: None,
+                due_date: None,
+                expires_at: None,
+                payer: Some("payer".into()),
+                display_amount: None,
+            },
+        );
+        match add_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvalidFields { fields } => assert_eq!(vec!["id"], fields),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        let stats = from_binary::<StatsResponse>(
+            &query(deps.as_ref(), env.clone(), QueryMsg::GetStats {}).unwrap(),
+        )
+        .unwrap()
+        .stats;
+        assert_eq!(1, stats[0].open.count);
+        assert_eq!(
+            Tally {
+                count: 1,
+                amount: Uint128::new(5),
+            },
+            stats[0].expired
+        );
+
+        // nothing left to prune
+        let prune_response = execute(
+            deps.as_mut(),
+            env,
+            mock_info("anyone", &[]),
+            ExecuteMsg::PruneExpired { limit: None },
+        )
+        .unwrap();
+        assert_eq!(prune_response.attributes[1], attr("count", "0"));
+    }
+
+    #[test]
+    fn list_invoices_pages_open_invoices() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let ids = [
+            "0d8cf0a5-6b4b-4c43-9d1c-2f2f1b6f7e0a",
+            INVOICE_ID,
+            "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5",
+        ];
+        for id in ids.iter().rev() {
+            store_test_invoice(
+                &mut deps.storage,
+                &Invoice {
+                    id: id.to_string(),
+                    ..test_invoice(5)
+                },
+            );
+        }
+
+        let list = |deps: Deps<ProvenanceQuery>, start_after: Option<&str>| -> Vec<String> {
+            from_binary::<InvoiceList>(
+                &query(
+                    deps,
+                    mock_env(),
+                    QueryMsg::ListInvoices {
+                        start_after: start_after.map(String::from),
+                        limit: Some(2),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+            .invoices
+            .into_iter()
+            .map(|invoice| invoice.id)
+            .collect()
+        };
+
+        // in id order, resuming after the last id seen
+        assert_eq!(ids[..2].to_vec(), list(deps.as_ref(), None));
+        assert_eq!(ids[2..].to_vec(), list(deps.as_ref(), Some(ids[1])));
+    }
+
+    #[test]
+    fn prune_expired_skips_dangling_expiry() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        // an expiry entry whose invoice is gone, ahead of one that is not
+        let expires_at = mock_env().block.time;
+        let dangling_key = expiry_key(expires_at.minus_seconds(1), INVOICE_ID);
+        get_expiry_storage(&mut deps.storage)
+            .save(&dangling_key, &INVOICE_ID.to_string())
+            .unwrap();
+
+        let expiring_id = "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5";
+        store_test_invoice(
+            &mut deps.storage,
+            &Invoice {
+                id: expiring_id.into(),
+                expires_at: Some(expires_at),
+                ..test_invoice(5)
+            },
+        );
+        get_expiry_storage(&mut deps.storage)
+            .save(
+                &expiry_key(expires_at, expiring_id),
+                &expiring_id.to_string(),
+            )
+            .unwrap();
+
+        let prune_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::PruneExpired { limit: None },
+        )
+        .unwrap();
+        assert_eq!(prune_response.attributes[1], attr("count", "1"));
+        assert_eq!(prune_response.attributes[2], attr("ids", expiring_id));
+
+        // the dangling entry is dropped
+        assert!(get_expiry_storage_read(&deps.storage)
+            .may_load(&dangling_key)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn prune_expired_writes_off_transferred_and_financed() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(
+            &mut deps.storage,
+            &State {
+                financing_terms: Some(FinancingTerms {
+                    advance_rate_bps: 9000,
+                    fee_bps: 200,
+                }),
+                financiers: vec![Addr::unchecked("financier")],
+                ..test_state()
+            },
+        );
+
+        let transferred_id = "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5";
+        let held_id = "0d8cf0a5-6b4b-4c43-9d1c-2f2f1b6f7e0a";
+        for id in [INVOICE_ID, transferred_id, held_id] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(ADMIN, &[]),
+                ExecuteMsg::AddInvoice {
+                    id: id.into(),
+                    amount: Uint128::new(1000),
+                    description: None,
+                    due_date: None,
+                    expires_at: Some(mock_env().block.time.plus_seconds(60)),
+                    payer: Some("payer".into()),
+                    display_amount: None,
+                },
+            )
+            .unwrap();
+        }
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("financier", &[coin(900, TEST_DENOM)]),
+            ExecuteMsg::FundInvoice {
+                id: INVOICE_ID.into(),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            add_msg.clone(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::TransferReceivable {
+                id: transferred_id.into(),
+                new_payee: "factor".into(),
+            },
+        )
+        .unwrap();
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(60);
+
+        // all expire, but only the sold and financed ones are a loss
+        let prune_response = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::PruneExpired { limit: None },
+        )
+        .unwrap();
+        assert_eq!(prune_response.attributes[1], attr("count", "3"));
+        assert_eq!(
+            prune_response.attributes[3],
+            attr("written_off", format!("{},{}", INVOICE_ID, transferred_id))
+        );
+        assert_eq!(
+            vec![(held_id, false), (INVOICE_ID, true), (transferred_id, true)],
+            parse_events::<InvoiceExpired>(&prune_response.events)
+                .unwrap()
+                .iter()
+                .map(|expired| (expired.id.as_str(), expired.written_off))
+                .collect::<Vec<_>>()
+        );
+
+        let stats = from_binary::<StatsResponse>(
+            &query(deps.as_ref(), env, QueryMsg::GetStats {}).unwrap(),
+        )
+        .unwrap()
+        .stats;
+        assert_eq!(3, stats[0].expired.count);
+        assert_eq!(
+            Tally {
+                count: 2,
+                amount: Uint128::new(2000),
+            },
+            stats[0].written_off
+        );
+    }
+
+    #[test]
+    fn accept_invoice_required_before_payment() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(
+            &mut deps.storage,
+            &State {
+                require_acceptance: true,
+                ..test_state()
+            },
+        );
+
+        store_test_invoice(
+            &mut deps.storage,
+            &Invoice {
+                payer: Some(Addr::unchecked("payer")),
+                ..test_invoice(5)
+            },
+        );
+
+        let pay_msg = ExecuteMsg::PayInvoice {
+            id: INVOICE_ID.into(),
+            payment_ref: None,
+        };
+
+        // payment waits for acceptance
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            pay_msg.clone(),
+        );
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvoiceNotAccepted { id } => assert_eq!(INVOICE_ID, id),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // only the designated payer can answer
+        let accept_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::AcceptInvoice {
+                id: INVOICE_ID.into(),
+            },
+        );
+        match accept_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::Unauthorized { .. } => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        let reject_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[]),
+            ExecuteMsg::Reject {
+                id: INVOICE_ID.into(),
+                reason: "wrong quantity".into(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            reject_response.attributes[0],
+            attr("action", Action::Reject.to_string())
+        );
+        assert_eq!(
+            reject_response.attributes[3],
+            attr("reason", "wrong quantity")
+        );
+
+        // a rejection stands until the terms change
+        let accept_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[]),
+            ExecuteMsg::AcceptInvoice {
+                id: INVOICE_ID.into(),
+            },
+        );
+        match accept_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::AcceptanceAlreadyRecorded { .. } => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::AmendInvoice {
+                id: INVOICE_ID.into(),
+                amount: Uint128::new(4),
+                description: None,
+                due_date: None,
+            },
+        )
+        .unwrap();
+
+        let accept_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[]),
+            ExecuteMsg::AcceptInvoice {
+                id: INVOICE_ID.into(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            accept_response.attributes[0],
+            attr("action", Action::Accept.to_string())
+        );
+
+        let invoice = get_invoice_storage_read(&deps.storage)
+            .load(INVOICE_ID.as_bytes())
+            .unwrap();
+        assert_eq!(
+            Some(Acceptance::Accepted {
+                block_time: mock_env().block.time,
+            }),
+            invoice.acceptance
+        );
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(4, TEST_DENOM)]),
+            pay_msg,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn update_signing_key_emits_config() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let public_key = Binary::from(vec![2u8; 33]);
+        let update_msg = ExecuteMsg::UpdateSigningKey {
+            public_key: Some(public_key.clone()),
+        };
+        assert_not_authorized_error(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[]),
+            update_msg.clone(),
+        ));
+
+        let update_response =
+            execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), update_msg).unwrap();
+        assert_eq!(
+            vec![ConfigUpdated {
+                signing_key: Some(public_key),
+                ..ConfigUpdated::new(&test_state())
+            }],
+            parse_events::<ConfigUpdated>(&update_response.events).unwrap()
+        );
+    }
+
+    #[test]
+    fn pay_with_signed_invoice_success() {
+        let mut deps = mock_dependencies(&[]);
+
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+        let public_key = VerifyingKey::from(&signing_key).to_bytes();
+
+        setup_test_base(
+            &mut deps.storage,
+            &State {
+                signing_key: Some(Binary::from(public_key.as_slice())),
+                ..test_state()
+            },
+        );
+
+        let signed = SignedInvoice {
+            id: INVOICE_ID.into(),
+            amount: Uint128::new(5),
+            denom: TEST_DENOM.into(),
+            expires_at: mock_env().block.time.plus_seconds(60),
+            payer: "payer".into(),
+        };
+        let sign = |key: &SigningKey, invoice: &SignedInvoice, contract: &Addr| {
+            let message = invoice
+                .signing_bytes(&mock_env().block.chain_id, contract)
+                .unwrap();
+            let signature: Signature = key.sign(&message);
+            Binary::from(signature.as_ref())
+        };
+        let contract = mock_env().contract.address;
+        let signature = sign(&signing_key, &signed, &contract);
+        let pay_msg = ExecuteMsg::PayWithSignedInvoice {
+            invoice: signed.clone(),
+            signature: signature.clone(),
+        };
+
+        // only the payer named in the invoice can pay it
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("stranger", &[coin(5, TEST_DENOM)]),
+            pay_msg.clone(),
+        );
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::Unauthorized { .. } => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // the signature covers every field
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(4, TEST_DENOM)]),
+            ExecuteMsg::PayWithSignedInvoice {
+                invoice: SignedInvoice {
+                    amount: Uint128::new(4),
+                    ..signed.clone()
+                },
+                signature,
+            },
+        );
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvalidSignature => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // signatures from another key or for another contract are rejected
+        let other_key = SigningKey::from_bytes(&[8u8; 32]).unwrap();
+        for signature in [
+            sign(&other_key, &signed, &contract),
+            sign(&signing_key, &signed, &Addr::unchecked("other_contract")),
+        ] {
+            let pay_response = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("payer", &[coin(5, TEST_DENOM)]),
+                ExecuteMsg::PayWithSignedInvoice {
+                    invoice: signed.clone(),
+                    signature,
+                },
+            );
+            match pay_response {
+                Ok(..) => panic!("expected error, but ok"),
+                Err(error) => match error {
+                    ContractError::InvalidSignature => {}
+                    error => panic!("unexpected error: {:?}", error),
+                },
+            }
+        }
+
+        // a validly signed invoice must still be in the contract denom
+        let other_denom = SignedInvoice {
+            denom: "nhash".into(),
+            ..signed.clone()
+        };
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, "nhash")]),
+            ExecuteMsg::PayWithSignedInvoice {
+                signature: sign(&signing_key, &other_denom, &contract),
+                invoice: other_denom,
+            },
+        );
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::UnsupportedDenom { denom } => assert_eq!(TEST_DENOM, denom),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // and unexpired
+        let mut env = mock_env();
+        env.block.time = signed.expires_at;
+        let pay_response = execute(
+            deps.as_mut(),
+            env,
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            pay_msg.clone(),
+        );
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvoiceExpired { id } => assert_eq!(INVOICE_ID, id),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            pay_msg.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            pay_response.attributes[0],
+            attr("action", Action::PayWithSignedInvoice.to_string())
+        );
+        assert_eq!(
+            pay_response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: RECIPIENT.into(),
+                amount: coins(5, TEST_DENOM),
+            })
+        );
+
+        let statement: PayerStatement = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetPayerStatement {
+                    payer: "payer".into(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(2, statement.entries.len());
+        assert_eq!(Uint128::zero(), statement.balance);
+
+        // a signed invoice is paid once
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            pay_msg,
+        );
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvalidFields { fields } => {
+                    assert_eq!(vec![String::from("id")], fields)
+                }
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+    }
+
+    #[test]
+    fn pay_invoice_rejects_duplicate_payment_ref() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        store_test_invoice(&mut deps.storage, &test_invoice(5));
+
+        let pay_msg = ExecuteMsg::PayInvoice {
+            id: INVOICE_ID.into(),
+            payment_ref: Some("processor-1234".into()),
+        };
+
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            pay_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            pay_response.attributes.last().unwrap(),
+            attr("payment_ref", "processor-1234")
+        );
+
+        // a retry is recognised rather than failing to find the invoice
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            pay_msg,
+        );
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::DuplicatePaymentRef { payment_ref } => {
+                    assert_eq!("processor-1234", payment_ref)
+                }
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        let payment = from_binary::<PaymentResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetPayment {
+                    invoice_id: INVOICE_ID.into(),
+                    payment_ref: "processor-1234".into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .payment;
+        assert_eq!(
+            Payment {
+                invoice_id: INVOICE_ID.into(),
+                payment_ref: "processor-1234".into(),
+                sender: Addr::unchecked("payer"),
+                amount: Uint128::new(5),
+                denom: TEST_DENOM.into(),
+                block_height: mock_env().block.height,
+                block_time: mock_env().block.time,
+            },
+            payment
+        );
+    }
+
+    #[test]
+    fn list_receipts_by_invoice_and_payer() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        let other_id = "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5";
+        for (id, amount) in [(INVOICE_ID, 5), (other_id, 7)] {
+            store_test_invoice(
+                &mut deps.storage,
+                &Invoice {
+                    id: id.into(),
+                    payer: Some(Addr::unchecked("payer")),
+                    ..test_invoice(amount)
+                },
+            );
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("payer", &[coin(amount, TEST_DENOM)]),
+                ExecuteMsg::PayInvoice {
+                    id: id.into(),
+                    payment_ref: None,
+                },
+            )
+            .unwrap();
+        }
+
+        let receipt = from_binary::<ReceiptResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetReceipt {
+                    invoice_id: other_id.into(),
+                    sequence: 2,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .receipt;
+        assert_eq!(
+            PaymentReceipt {
+                sequence: 2,
+                invoice_id: other_id.into(),
+                payer: Addr::unchecked("payer"),
+                amount: Uint128::new(7),
+                denom: TEST_DENOM.into(),
+                block_height: mock_env().block.height,
+                block_time: mock_env().block.time,
+                tx_index: mock_env().transaction.map(|transaction| transaction.index),
+            },
+            receipt
+        );
+
+        let list_receipts = |invoice_id: Option<&str>, payer: Option<&str>, start_after| {
+            from_binary::<ReceiptList>(
+                &query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::ListReceipts {
+                        invoice_id: invoice_id.map(String::from),
+                        payer: payer.map(String::from),
+                        start_after,
+                        limit: None,
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+            .receipts
+        };
+
+        let receipts = list_receipts(Some(INVOICE_ID), None, None);
+        assert_eq!(1, receipts.len());
+        assert_eq!(Uint128::new(5), receipts[0].amount);
+
+        let receipts = list_receipts(None, Some("payer"), None);
+        assert_eq!(
+            vec![1, 2],
+            receipts
+                .iter()
+                .map(|receipt| receipt.sequence)
+                .collect::<Vec<u64>>()
+        );
+
+        let receipts = list_receipts(None, Some("payer"), Some(1));
+        assert_eq!(1, receipts.len());
+        assert_eq!(other_id, receipts[0].invoice_id);
+
+        // nothing follows the last possible sequence
+        assert!(list_receipts(None, Some("payer"), Some(u64::MAX)).is_empty());
+    }
+
+    #[test]
+    fn pay_invoice_writes_payment_attribute() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        // attributes need a name bound to the contract
+        let update_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::UpdatePaymentAttributes { enabled: true },
+        );
+        match update_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::AttributeNameUnbound => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        config(&mut deps.storage)
+            .update(|mut state| -> StdResult<_> {
+                state.attribute_name = Some("paid.shoeco.pb".into());
+                Ok(state)
+            })
+            .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::UpdatePaymentAttributes { enabled: true },
+        )
+        .unwrap();
+
+        store_test_invoice(&mut deps.storage, &test_invoice(5));
+
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: INVOICE_ID.into(),
+                payment_ref: None,
+            },
+        )
+        .unwrap();
+
+        // the receipt is written to the payer's account under the bound name
+        let receipt = get_receipt_storage_read(&deps.storage, INVOICE_ID)
+            .load(&1u64.to_be_bytes())
+            .unwrap();
+        assert_eq!(pay_response.messages.len(), 2);
+        assert_eq!(
+            pay_response.messages[1].msg,
+            add_json_attribute(Addr::unchecked("payer"), "paid.shoeco.pb", &receipt).unwrap()
         );
-
-        assert_sent_funds_unsupported_error(add_response);
     }
 
     #[test]
-    fn create_invoice_invalid_data_error() {
+    fn pay_invoice_payer_not_verified_error() {
         let mut deps = mock_dependencies(&[]);
 
         setup_test_base(
             &mut deps.storage,
             &State {
-                admin: Addr::unchecked(ADMIN),
-                recipient: Addr::unchecked(RECIPIENT),
-                denom: TEST_DENOM.into(),
-                business_name: BUSINESS_NAME.into(),
+                required_attributes: vec!["kyc.onboarded.pb".into()],
+                ..test_state()
             },
         );
 
-        let amount = Uint128::new(100);
-        let add_msg = ExecuteMsg::AddInvoice {
-            id: "".into(),
-            amount: amount.into(),
-            description: Option::Some(DESCRIPTION.into()),
+        store_test_invoice(&mut deps.storage, &test_invoice(5));
+
+        let pay_msg = ExecuteMsg::PayInvoice {
+            id: INVOICE_ID.into(),
+            payment_ref: None,
         };
 
-        let sender_info = mock_info(ADMIN, &[]);
+        deps.querier
+            .with_attributes("payer", &[("other.pb", "true", "string")]);
 
-        // execute add invoice
-        let add_response = execute(
+        let pay_response = execute(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            add_msg.clone(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            pay_msg.clone(),
         );
-
-        // verify invoice response
-        match add_response {
+        match pay_response {
             Ok(..) => panic!("expected error, but ok"),
             Err(error) => match error {
-                ContractError::InvalidFields { fields } => {
-                    assert!(fields.contains(&"id".into()));
+                ContractError::PayerNotVerified { address, attribute } => {
+                    assert_eq!("payer", address);
+                    assert_eq!("kyc.onboarded.pb", attribute);
                 }
                 error => panic!("unexpected error: {:?}", error),
             },
         }
+
+        // onboarded payers can pay
+        deps.querier
+            .with_attributes("payer", &[("kyc.onboarded.pb", "true", "string")]);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            pay_msg,
+        )
+        .unwrap();
     }
 
     #[test]
-    fn create_invoice_existing_id_error() {
+    fn deposit_payer_not_verified_error() {
         let mut deps = mock_dependencies(&[]);
 
         setup_test_base(
             &mut deps.storage,
             &State {
-                admin: Addr::unchecked(ADMIN),
-                recipient: Addr::unchecked(RECIPIENT),
-                denom: TEST_DENOM.into(),
-                business_name: BUSINESS_NAME.into(),
+                required_attributes: vec!["kyc.onboarded.pb".into()],
+                ..test_state()
             },
         );
 
-        store_test_invoice(
-            &mut deps.storage,
-            &Invoice {
-                id: INVOICE_ID.into(),
-                amount: Uint128::new(1),
-                description: Option::None,
-            },
-        );
+        deps.querier
+            .with_attributes("payer", &[("other.pb", "true", "string")]);
 
-        let amount = Uint128::new(100);
-        let add_msg = ExecuteMsg::AddInvoice {
-            id: INVOICE_ID.into(),
-            amount: amount.into(),
-            description: Option::Some(DESCRIPTION.into()),
-        };
+        let assert_not_verified =
+            |response: Result<Response<ProvenanceMsg>, ContractError>| match response {
+                Ok(..) => panic!("expected error, but ok"),
+                Err(error) => match error {
+                    ContractError::PayerNotVerified { address, attribute } => {
+                        assert_eq!("payer", address);
+                        assert_eq!("kyc.onboarded.pb", attribute);
+                    }
+                    error => panic!("unexpected error: {:?}", error),
+                },
+            };
 
-        let sender_info = mock_info(ADMIN, &[]);
+        // an unverified payer cannot prefund
+        assert_not_verified(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(10, TEST_DENOM)]),
+            ExecuteMsg::Deposit {},
+        ));
+
+        // nor is a deposit made before verification lapsed used to settle
+        get_deposit_storage(&mut deps.storage, &Addr::unchecked("payer"))
+            .save(TEST_DENOM.as_bytes(), &Uint128::new(10))
+            .unwrap();
 
-        // execute add invoice
         let add_response = execute(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            add_msg.clone(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::AddInvoice {
+                id: INVOICE_ID.into(),
+                amount: Uint128::new(5),
+                description: None,
+                due_date: None,
+                expires_at: None,
+                payer: Some("payer".into()),
+                display_amount: None,
+            },
+        )
+        .unwrap();
+        assert!(add_response.messages.is_empty());
+        assert!(parse_events::<InvoicePaid>(&add_response.events)
+            .unwrap()
+            .is_empty());
+
+        for sender in ["payer", ADMIN] {
+            assert_not_verified(execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(sender, &[]),
+                ExecuteMsg::SettleFromDeposit {
+                    id: INVOICE_ID.into(),
+                },
+            ));
+        }
+        assert_eq!(
+            vec![coin(10, TEST_DENOM)],
+            query_deposit(deps.as_ref(), "payer")
         );
 
-        // verify invoice response
-        match add_response {
-            Ok(..) => panic!("expected error, but ok"),
-            Err(error) => match error {
-                ContractError::InvalidFields { fields } => {
-                    assert!(fields.contains(&"id".into()));
-                }
-                error => panic!("unexpected error: {:?}", error),
+        // onboarded payers can settle
+        deps.querier
+            .with_attributes("payer", &[("kyc.onboarded.pb", "true", "string")]);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[]),
+            ExecuteMsg::SettleFromDeposit {
+                id: INVOICE_ID.into(),
             },
-        }
+        )
+        .unwrap();
     }
 
     #[test]
-    fn create_invoice_unauthorized_error() {
+    fn denylist_blocks_payers_and_recipients() {
         let mut deps = mock_dependencies(&[]);
 
-        setup_test_base(
-            &mut deps.storage,
-            &State {
-                admin: Addr::unchecked(ADMIN),
-                recipient: Addr::unchecked(RECIPIENT),
-                denom: TEST_DENOM.into(),
-                business_name: BUSINESS_NAME.into(),
+        setup_test_base(&mut deps.storage, &test_state());
+
+        store_test_invoice(&mut deps.storage, &test_invoice(5));
+
+        // only the admin manages the denylist
+        let deny_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[]),
+            ExecuteMsg::AddToDenylist {
+                address: "payer".into(),
             },
         );
+        assert_not_authorized_error(deny_response);
+
+        for address in ["payer", "other_recipient"] {
+            let deny_response = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(ADMIN, &[]),
+                ExecuteMsg::AddToDenylist {
+                    address: address.into(),
+                },
+            )
+            .unwrap();
+            assert_eq!(
+                vec![DenylistUpdated {
+                    address: Addr::unchecked(address),
+                    denied: true,
+                }],
+                parse_events::<DenylistUpdated>(&deny_response.events).unwrap()
+            );
+        }
 
-        let amount = Uint128::new(100);
-        let add_msg = ExecuteMsg::AddInvoice {
+        let pay_msg = ExecuteMsg::PayInvoice {
             id: INVOICE_ID.into(),
-            amount: amount.into(),
-            description: Option::Some(DESCRIPTION.into()),
+            payment_ref: None,
         };
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            pay_msg.clone(),
+        );
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::SenderDenylisted { address } => assert_eq!("payer", address),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
 
-        let sender_info = mock_info("invalid_sender", &[]);
-
-        // execute add invoice
         let add_response = execute(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            add_msg.clone(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::AddInvoice {
+                id: "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5".into(),
+                amount: Uint128::new(5),
+                description: None,
+                due_date: None,
+                expires_at: None,
+                payer: Some("payer".into()),
+                display_amount: None,
+            },
         );
+        match add_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::PayerDenylisted { address } => assert_eq!("payer", address),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
 
-        assert_not_authorized_error(add_response);
+        let config_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::UpdateConfig {
+                recipient: Some("other_recipient".into()),
+                business_name: None,
+                contract_name: None,
+                attribute_name: None,
+            },
+        );
+        match config_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::RecipientDenylisted { address } => {
+                    assert_eq!("other_recipient", address)
+                }
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        let denylist: Denylist = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetDenylist {
+                    start_after: Some("other_recipient".into()),
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(1, denylist.entries.len());
+        assert_eq!(Addr::unchecked("payer"), denylist.entries[0].address);
+
+        // removed addresses can pay again
+        let allow_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::RemoveFromDenylist {
+                address: "payer".into(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            vec![DenylistUpdated {
+                address: Addr::unchecked("payer"),
+                denied: false,
+            }],
+            parse_events::<DenylistUpdated>(&allow_response.events).unwrap()
+        );
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            pay_msg,
+        )
+        .unwrap();
     }
 
     #[test]
-    fn cancel_invoice_success() {
+    fn denylist_blocks_settlement_transfers_and_financing() {
         let mut deps = mock_dependencies(&[]);
 
         setup_test_base(
             &mut deps.storage,
             &State {
-                admin: Addr::unchecked(ADMIN),
-                recipient: Addr::unchecked(RECIPIENT),
-                denom: TEST_DENOM.into(),
-                business_name: BUSINESS_NAME.into(),
+                financing_terms: Some(FinancingTerms {
+                    advance_rate_bps: 9000,
+                    fee_bps: 200,
+                }),
+                financiers: vec![Addr::unchecked("financier")],
+                ..test_state()
             },
         );
 
-        let amount = Uint128::new(5);
         store_test_invoice(
             &mut deps.storage,
             &Invoice {
-                id: INVOICE_ID.into(),
-                amount: amount.into(),
-                description: Option::None,
+                payer: Some(Addr::unchecked("payer")),
+                ..test_invoice(1000)
             },
         );
 
-        let cancel_msg = ExecuteMsg::CancelInvoice {
-            id: INVOICE_ID.into(),
-        };
-
-        let sender_info = mock_info(ADMIN, &[]);
+        for address in ["payer", "factor", "financier"] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(ADMIN, &[]),
+                ExecuteMsg::AddToDenylist {
+                    address: address.into(),
+                },
+            )
+            .unwrap();
+        }
 
-        // execute cancel invoice
-        let cancel_response = execute(
+        // the admin cannot settle from a denylisted payer's deposit
+        let settle_response = execute(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            cancel_msg.clone(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::SettleFromDeposit {
+                id: INVOICE_ID.into(),
+            },
         );
-
-        // verify invoice response
-        match cancel_response {
-            Ok(response) => {
-                assert_eq!(response.attributes.len(), 5);
-                assert_eq!(
-                    response.attributes[0],
-                    attr("action", Action::Cancel.to_string())
-                );
-                assert_eq!(response.attributes[1], attr("id", INVOICE_ID));
-                assert_eq!(response.attributes[2], attr("denom", TEST_DENOM));
-                assert_eq!(response.attributes[3], attr("amount", amount.to_string()));
-                assert_eq!(response.attributes[4], attr("recipient", RECIPIENT));
-            }
-            Err(error) => {
-                panic!("failed to create add invoice: {:?}", error)
-            }
+        match settle_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::PayerDenylisted { address } => assert_eq!("payer", address),
+                error => panic!("unexpected error: {:?}", error),
+            },
         }
 
-        // verify invoice stored
-        let invoice_storage = get_invoice_storage_read(&deps.storage);
+        // a receivable cannot be sold to a denylisted address
+        let transfer_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::TransferReceivable {
+                id: INVOICE_ID.into(),
+                new_payee: "factor".into(),
+            },
+        );
+        match transfer_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::PayeeDenylisted { address } => assert_eq!("factor", address),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
 
-        match invoice_storage.load(INVOICE_ID.as_bytes()) {
-            Ok(..) => panic!("expected error, but found"),
+        // nor financed by a denylisted financier
+        let fund_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("financier", &[coin(900, TEST_DENOM)]),
+            ExecuteMsg::FundInvoice {
+                id: INVOICE_ID.into(),
+            },
+        );
+        match fund_response {
+            Ok(..) => panic!("expected error, but ok"),
             Err(error) => match error {
-                StdError::NotFound { .. } => {}
+                ContractError::FinancierDenylisted { address } => {
+                    assert_eq!("financier", address)
+                }
                 error => panic!("unexpected error: {:?}", error),
             },
         }
     }
 
     #[test]
-    fn cancel_invoice_not_found_error() {
+    fn denylist_blocks_withdrawals() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(&mut deps.storage, &test_state());
+
+        get_deposit_storage(&mut deps.storage, &Addr::unchecked("payer"))
+            .save(TEST_DENOM.as_bytes(), &Uint128::new(10))
+            .unwrap();
+        get_credit_storage(&mut deps.storage)
+            .save(b"payer", &Uint128::new(3))
+            .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::AddToDenylist {
+                address: "payer".into(),
+            },
+        )
+        .unwrap();
+
+        for msg in [
+            ExecuteMsg::Withdraw {
+                amount: coin(10, TEST_DENOM),
+            },
+            ExecuteMsg::WithdrawCredit {},
+        ] {
+            match execute(deps.as_mut(), mock_env(), mock_info("payer", &[]), msg) {
+                Ok(..) => panic!("expected error, but ok"),
+                Err(error) => match error {
+                    ContractError::SenderDenylisted { address } => assert_eq!("payer", address),
+                    error => panic!("unexpected error: {:?}", error),
+                },
+            }
+        }
+
+        // the balances are held until the address is removed
+        assert_eq!(
+            vec![coin(10, TEST_DENOM)],
+            query_deposit(deps.as_ref(), "payer")
+        );
+        assert_eq!(coin(3, TEST_DENOM), query_credit(deps.as_ref(), "payer"));
+    }
+
+    #[test]
+    fn denylist_blocks_acceptance() {
         let mut deps = mock_dependencies(&[]);
 
         setup_test_base(
             &mut deps.storage,
             &State {
-                admin: Addr::unchecked(ADMIN),
-                recipient: Addr::unchecked(RECIPIENT),
-                denom: TEST_DENOM.into(),
-                business_name: BUSINESS_NAME.into(),
+                require_acceptance: true,
+                ..test_state()
             },
         );
 
-        let cancel_msg = ExecuteMsg::CancelInvoice {
-            id: INVOICE_ID.into(),
-        };
-
-        let sender_info = mock_info(ADMIN, &[]);
+        store_test_invoice(
+            &mut deps.storage,
+            &Invoice {
+                payer: Some(Addr::unchecked("payer")),
+                ..test_invoice(5)
+            },
+        );
 
-        // execute pay invoice
-        let cancel_response = execute(
+        execute(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            cancel_msg.clone(),
-        );
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::AddToDenylist {
+                address: "payer".into(),
+            },
+        )
+        .unwrap();
 
-        // verify invoice response
-        match cancel_response {
-            Ok(..) => panic!("expected error, but ok"),
-            Err(error) => match error {
-                ContractError::LoadInvoiceFailed { .. } => {}
-                error => panic!("unexpected error: {:?}", error),
+        for msg in [
+            ExecuteMsg::AcceptInvoice {
+                id: INVOICE_ID.into(),
+            },
+            ExecuteMsg::Reject {
+                id: INVOICE_ID.into(),
+                reason: "wrong size".into(),
             },
+        ] {
+            match execute(deps.as_mut(), mock_env(), mock_info("payer", &[]), msg) {
+                Ok(..) => panic!("expected error, but ok"),
+                Err(error) => match error {
+                    ContractError::SenderDenylisted { address } => assert_eq!("payer", address),
+                    error => panic!("unexpected error: {:?}", error),
+                },
+            }
         }
     }
 
     #[test]
-    fn cancel_invoice_with_funds_throws_error() {
+    fn denylist_blocks_payouts() {
         let mut deps = mock_dependencies(&[]);
 
         setup_test_base(
             &mut deps.storage,
             &State {
-                admin: Addr::unchecked(ADMIN),
-                recipient: Addr::unchecked(RECIPIENT),
-                denom: TEST_DENOM.into(),
-                business_name: BUSINESS_NAME.into(),
+                financing_terms: Some(FinancingTerms {
+                    advance_rate_bps: 9000,
+                    fee_bps: 200,
+                }),
+                financiers: vec![Addr::unchecked("financier")],
+                ..test_state()
             },
         );
 
-        let amount = Uint128::new(5);
+        // a receivable sold and an invoice financed before the holders were denylisted
+        let financed_id = "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5";
         store_test_invoice(
             &mut deps.storage,
             &Invoice {
-                id: INVOICE_ID.into(),
-                amount: amount.into(),
-                description: Option::None,
+                payee: Some(Addr::unchecked("factor")),
+                ..test_invoice(5)
+            },
+        );
+        store_test_invoice(
+            &mut deps.storage,
+            &Invoice {
+                id: financed_id.into(),
+                financing: Some(Financing {
+                    financier: Addr::unchecked("financier"),
+                    advance: Uint128::new(900),
+                    fee: Uint128::new(20),
+                    funded_at: mock_env().block.time,
+                }),
+                ..test_invoice(1000)
             },
         );
 
-        let cancel_msg = ExecuteMsg::CancelInvoice {
-            id: INVOICE_ID.into(),
-        };
+        for address in ["factor", "financier"] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(ADMIN, &[]),
+                ExecuteMsg::AddToDenylist {
+                    address: address.into(),
+                },
+            )
+            .unwrap();
+        }
 
-        let sender_info = mock_info(ADMIN, &[coin(amount.u128(), TEST_DENOM)]);
+        // the holder of a sold receivable is not paid
+        let pay_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: INVOICE_ID.into(),
+                payment_ref: None,
+            },
+        );
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::PayeeDenylisted { address } => assert_eq!("factor", address),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
 
-        // execute cancel invoice
-        let cancel_response = execute(
+        // nor is the financier repaid
+        let pay_response = execute(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            cancel_msg.clone(),
-        );
-
-        assert_sent_funds_unsupported_error(cancel_response);
-    }
-
-    #[test]
-    fn cancel_invoice_unauthorized_error() {
-        let mut deps = mock_dependencies(&[]);
-
-        setup_test_base(
-            &mut deps.storage,
-            &State {
-                admin: Addr::unchecked(ADMIN),
-                recipient: Addr::unchecked(RECIPIENT),
-                denom: TEST_DENOM.into(),
-                business_name: BUSINESS_NAME.into(),
+            mock_info("payer", &[coin(1000, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: financed_id.into(),
+                payment_ref: None,
             },
         );
+        match pay_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::FinancierDenylisted { address } => {
+                    assert_eq!("financier", address)
+                }
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
 
-        let amount = Uint128::new(5);
+        // nor is an advance paid to a denylisted recipient
         store_test_invoice(
             &mut deps.storage,
             &Invoice {
-                id: INVOICE_ID.into(),
-                amount: amount.into(),
-                description: Option::None,
+                id: "0d8cf0a5-6b4b-4c43-9d1c-2f2f1b6f7e0a".into(),
+                ..test_invoice(1000)
             },
         );
-
-        let cancel_msg = ExecuteMsg::CancelInvoice {
-            id: INVOICE_ID.into(),
-        };
-
-        let sender_info = mock_info("invalid_sender", &[]);
-
-        // execute cancel invoice
-        let cancel_response = execute(
+        for msg in [
+            ExecuteMsg::AddToDenylist {
+                address: RECIPIENT.into(),
+            },
+            ExecuteMsg::RemoveFromDenylist {
+                address: "financier".into(),
+            },
+        ] {
+            execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), msg).unwrap();
+        }
+        let fund_response = execute(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            cancel_msg.clone(),
+            mock_info("financier", &[coin(900, TEST_DENOM)]),
+            ExecuteMsg::FundInvoice {
+                id: "0d8cf0a5-6b4b-4c43-9d1c-2f2f1b6f7e0a".into(),
+            },
         );
-
-        assert_not_authorized_error(cancel_response);
+        match fund_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::RecipientDenylisted { address } => assert_eq!(RECIPIENT, address),
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
     }
 
     #[test]
-    fn pay_invoice_success() {
+    fn update_config_rebinds_contract_name() {
         let mut deps = mock_dependencies(&[]);
 
         setup_test_base(
             &mut deps.storage,
             &State {
-                admin: Addr::unchecked(ADMIN),
-                recipient: Addr::unchecked(RECIPIENT),
-                denom: TEST_DENOM.into(),
-                business_name: BUSINESS_NAME.into(),
+                contract_name: Some("acme.invoice.pb".into()),
+                ..test_state()
             },
         );
 
-        let amount = Uint128::new(5);
-        store_test_invoice(
-            &mut deps.storage,
-            &Invoice {
-                id: INVOICE_ID.into(),
-                amount: amount.into(),
-                description: Option::None,
+        let contract_address = mock_env().contract.address;
+        deps.querier
+            .with_names(&[("acme.invoice.pb", contract_address.as_str(), true)]);
+
+        let contract_name: ContractName =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetContractName {}).unwrap())
+                .unwrap();
+        let name = contract_name.name.unwrap();
+        assert_eq!("acme.invoice.pb", name.name);
+        assert_eq!(contract_address, name.address);
+
+        // rebinding unbinds the previous name
+        let rebind_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::UpdateConfig {
+                recipient: None,
+                business_name: None,
+                contract_name: Some(ContractNameUpdate::Bind {
+                    name: "shoes.invoice.pb".into(),
+                }),
+                attribute_name: None,
             },
+        )
+        .unwrap();
+        assert_eq!(2, rebind_response.messages.len());
+        assert_eq!(
+            unbind_name("acme.invoice.pb").unwrap(),
+            rebind_response.messages[0].msg
+        );
+        assert_eq!(
+            bind_name(
+                "shoes.invoice.pb",
+                contract_address,
+                NameBinding::Restricted
+            )
+            .unwrap(),
+            rebind_response.messages[1].msg
         );
 
-        let pay_invoice = ExecuteMsg::PayInvoice {
-            id: INVOICE_ID.into(),
+        let unbind_msg = ExecuteMsg::UpdateConfig {
+            recipient: None,
+            business_name: None,
+            contract_name: Some(ContractNameUpdate::Unbind {}),
+            attribute_name: None,
         };
-
-        let sender_info = mock_info("payer", &[coin(amount.u128(), TEST_DENOM)]);
-
-        // execute pay invoice
-        let pay_response = execute(
+        let unbind_response = execute(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            pay_invoice.clone(),
+            mock_info(ADMIN, &[]),
+            unbind_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(1, unbind_response.messages.len());
+        assert_eq!(
+            unbind_name("shoes.invoice.pb").unwrap(),
+            unbind_response.messages[0].msg
         );
 
-        // verify invoice response
-        match pay_response {
-            Ok(response) => {
-                assert_eq!(response.attributes.len(), 6);
-                assert_eq!(
-                    response.attributes[0],
-                    attr("action", Action::Pay.to_string())
-                );
-                assert_eq!(response.attributes[1], attr("id", INVOICE_ID));
-                assert_eq!(response.attributes[2], attr("denom", TEST_DENOM));
-                assert_eq!(response.attributes[3], attr("amount", amount.to_string()));
-                assert_eq!(response.attributes[4], attr("sender", "payer"));
-                assert_eq!(response.attributes[5], attr("recipient", RECIPIENT));
-
-                assert_eq!(response.messages.len(), 1);
-                assert_eq!(
-                    response.messages[0].msg,
-                    CosmosMsg::Bank(BankMsg::Send {
-                        to_address: RECIPIENT.to_string(),
-                        amount: coins(amount.u128(), TEST_DENOM),
-                    })
-                );
-            }
-            Err(error) => {
-                panic!("failed to create add invoice: {:?}", error)
-            }
-        }
-
-        // verify invoice stored
-        let invoice_storage = get_invoice_storage_read(&deps.storage);
+        let contract_name: ContractName =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetContractName {}).unwrap())
+                .unwrap();
+        assert_eq!(None, contract_name.name);
 
-        match invoice_storage.load(INVOICE_ID.as_bytes()) {
-            Ok(..) => panic!("expected error, but found"),
+        let unbind_response = execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), unbind_msg);
+        match unbind_response {
+            Ok(..) => panic!("expected error, but ok"),
             Err(error) => match error {
-                StdError::NotFound { .. } => {}
+                ContractError::ContractNameUnbound => {}
                 error => panic!("unexpected error: {:?}", error),
             },
         }
     }
 
     #[test]
-    fn pay_invoice_not_found_error() {
+    fn update_config_binds_attribute_name() {
         let mut deps = mock_dependencies(&[]);
 
-        setup_test_base(
-            &mut deps.storage,
-            &State {
-                admin: Addr::unchecked(ADMIN),
-                recipient: Addr::unchecked(RECIPIENT),
-                denom: TEST_DENOM.into(),
-                business_name: BUSINESS_NAME.into(),
-            },
-        );
+        setup_test_base(&mut deps.storage, &test_state());
 
-        let pay_msg = ExecuteMsg::PayInvoice {
-            id: INVOICE_ID.into(),
+        let bind_msg = ExecuteMsg::UpdateConfig {
+            recipient: None,
+            business_name: None,
+            contract_name: None,
+            attribute_name: Some("paid.shoeco.pb".into()),
         };
 
-        let amount = Uint128::new(5);
-        let sender_info = mock_info("payer", &[coin(amount.u128(), TEST_DENOM)]);
+        // only the admin can bind it
+        assert_not_authorized_error(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other", &[]),
+            bind_msg.clone(),
+        ));
 
-        // execute pay invoice
-        let pay_response = execute(
+        // contracts instantiated without a name cannot publish attributes yet
+        let enable_msg = ExecuteMsg::UpdatePaymentAttributes { enabled: true };
+        match execute(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            pay_msg.clone(),
+            mock_info(ADMIN, &[]),
+            enable_msg.clone(),
+        ) {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::AttributeNameUnbound => {}
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        let bind_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            bind_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(1, bind_response.messages.len());
+        assert_eq!(
+            bind_name(
+                "paid.shoeco.pb",
+                mock_env().contract.address,
+                NameBinding::Restricted
+            )
+            .unwrap(),
+            bind_response.messages[0].msg
+        );
+        assert_eq!(
+            Some("paid.shoeco.pb".into()),
+            config_read(&deps.storage).load().unwrap().attribute_name
         );
 
-        // verify invoice response
-        match pay_response {
+        // binding the same name again is a noop
+        let rebind_response =
+            execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), bind_msg).unwrap();
+        assert!(rebind_response.messages.is_empty());
+
+        execute(deps.as_mut(), mock_env(), mock_info(ADMIN, &[]), enable_msg).unwrap();
+
+        // but it cannot be replaced
+        let replace_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::UpdateConfig {
+                recipient: None,
+                business_name: None,
+                contract_name: None,
+                attribute_name: Some("other.shoeco.pb".into()),
+            },
+        );
+        match replace_response {
             Ok(..) => panic!("expected error, but ok"),
             Err(error) => match error {
-                ContractError::LoadInvoiceFailed { .. } => {}
+                ContractError::AttributeNameBound { name } => assert_eq!("paid.shoeco.pb", name),
                 error => panic!("unexpected error: {:?}", error),
             },
         }
     }
 
     #[test]
-    fn pay_invoice_mismatch_funds_error() {
+    fn add_invoice_records_metadata_scope() {
         let mut deps = mock_dependencies(&[]);
 
         setup_test_base(
             &mut deps.storage,
             &State {
-                admin: Addr::unchecked(ADMIN),
-                recipient: Addr::unchecked(RECIPIENT),
-                denom: TEST_DENOM.into(),
-                business_name: BUSINESS_NAME.into(),
+                attribute_name: Some("invoice.shoeco.pb".into()),
+                scope_specification_id: Some("scopespec1qnwg86nsatx5pl56muw0v9ytlz3qu3jx6m".into()),
+                ..test_state()
             },
         );
 
-        let amount = Uint128::new(5);
-        store_test_invoice(
-            &mut deps.storage,
-            &Invoice {
+        let add_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::AddInvoice {
                 id: INVOICE_ID.into(),
-                amount: amount.into(),
-                description: Option::None,
+                amount: Uint128::new(5),
+                description: None,
+                due_date: None,
+                expires_at: None,
+                payer: Some("payer".into()),
+                display_amount: None,
             },
+        )
+        .unwrap();
+
+        // the scope address is stored on the invoice
+        let invoice = get_invoice_storage_read(&deps.storage)
+            .load(INVOICE_ID.as_bytes())
+            .unwrap();
+        let scope_address = scope_id(INVOICE_ID).unwrap();
+        assert_eq!(Some(scope_address.to_owned()), invoice.scope_id);
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(
+            add_response.messages,
+            create_invoice_scope(&mock_env(), &state, &invoice)
+                .unwrap()
+                .into_iter()
+                .map(SubMsg::new)
+                .collect::<Vec<_>>()
         );
+        match &add_response.messages[0].msg {
+            CosmosMsg::Custom(msg) => assert_eq!(
+                msg.params,
+                ProvenanceMsgParams::Metadata(MetadataMsgParams::WriteScope {
+                    scope: Scope {
+                        scope_id: scope_address.to_owned(),
+                        specification_id: "scopespec1qnwg86nsatx5pl56muw0v9ytlz3qu3jx6m".into(),
+                        owners: vec![Party {
+                            address: mock_env().contract.address,
+                            role: PartyType::Owner,
+                        }],
+                        data_access: vec![Addr::unchecked(RECIPIENT), Addr::unchecked("payer")],
+                        value_owner_address: Addr::unchecked(RECIPIENT),
+                    },
+                    signers: vec![mock_env().contract.address],
+                })
+            ),
+            msg => panic!("unexpected message: {:?}", msg),
+        }
 
-        let pay_msg = ExecuteMsg::PayInvoice {
-            id: INVOICE_ID.into(),
-        };
-
-        // mismatch sender on coin amount
-        let mut sender_info = mock_info("payer", &[coin(10, TEST_DENOM)]);
-
-        // execute pay invoice
-        let mut pay_response = execute(
+        // paying replaces the record with its new status
+        let pay_response = execute(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            pay_msg.clone(),
+            mock_info("payer", &[coin(5, TEST_DENOM)]),
+            ExecuteMsg::PayInvoice {
+                id: INVOICE_ID.into(),
+                payment_ref: None,
+            },
+        )
+        .unwrap();
+
+        let record = InvoiceRecord::new(&state, &invoice, RecordStatus::Paid).unwrap();
+        assert_eq!(pay_response.messages.len(), 3);
+        assert_eq!(
+            pay_response.messages[1].msg,
+            delete_attributes(Addr::unchecked(&scope_address), "invoice.shoeco.pb").unwrap()
         );
+        assert_eq!(
+            pay_response.messages[2].msg,
+            add_json_attribute(
+                Addr::unchecked(&scope_address),
+                "invoice.shoeco.pb",
+                &record
+            )
+            .unwrap()
+        );
+    }
 
-        // verify invoice response
-        match pay_response {
-            Ok(..) => panic!("expected error, but ok"),
-            Err(error) => match error {
-                ContractError::SentFundsInvoiceMismatch => {}
-                error => panic!("unexpected error: {:?}", error),
+    #[test]
+    fn transfer_and_fund_update_invoice_record() {
+        let mut deps = mock_dependencies(&[]);
+
+        setup_test_base(
+            &mut deps.storage,
+            &State {
+                attribute_name: Some("invoice.shoeco.pb".into()),
+                scope_specification_id: Some("scopespec1qnwg86nsatx5pl56muw0v9ytlz3qu3jx6m".into()),
+                financing_terms: Some(FinancingTerms {
+                    advance_rate_bps: 9000,
+                    fee_bps: 200,
+                }),
+                financiers: vec![Addr::unchecked("financier")],
+                ..test_state()
             },
-        }
+        );
 
-        // mismatch sender on coin denom
-        sender_info = mock_info("payer", &[coin(5, "wrongdenom")]);
+        let transferred_id = "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5";
+        for id in [INVOICE_ID, transferred_id] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(ADMIN, &[]),
+                ExecuteMsg::AddInvoice {
+                    id: id.into(),
+                    amount: Uint128::new(1000),
+                    description: None,
+                    due_date: None,
+                    expires_at: None,
+                    payer: Some("payer".into()),
+                    display_amount: None,
+                },
+            )
+            .unwrap();
+        }
+        let state = config_read(&deps.storage).load().unwrap();
+
+        let assert_record =
+            |storage: &dyn Storage, messages: &[SubMsg<ProvenanceMsg>], id: &str| {
+                let invoice = get_invoice_storage_read(storage)
+                    .load(id.as_bytes())
+                    .unwrap();
+                let scope_address = Addr::unchecked(scope_id(id).unwrap());
+                let record = InvoiceRecord::new(&state, &invoice, RecordStatus::Open).unwrap();
+                assert_eq!(
+                    vec![
+                        delete_attributes(scope_address.to_owned(), "invoice.shoeco.pb").unwrap(),
+                        add_json_attribute(scope_address, "invoice.shoeco.pb", &record).unwrap(),
+                    ],
+                    messages
+                        .iter()
+                        .map(|msg| msg.msg.to_owned())
+                        .collect::<Vec<_>>()
+                );
+                record
+            };
 
-        pay_response = execute(
+        // funding names the financier on the record
+        let fund_response = execute(
             deps.as_mut(),
             mock_env(),
-            sender_info.clone(),
-            pay_msg.clone(),
+            mock_info("financier", &[coin(900, TEST_DENOM)]),
+            ExecuteMsg::FundInvoice {
+                id: INVOICE_ID.into(),
+            },
+        )
+        .unwrap();
+        assert_eq!(3, fund_response.messages.len());
+        let record = assert_record(&deps.storage, &fund_response.messages[1..], INVOICE_ID);
+        assert_eq!(Some(Addr::unchecked("financier")), record.financier);
+        assert_eq!(Addr::unchecked(RECIPIENT), record.payee);
+        assert_eq!(
+            vec![InvoiceFinanced {
+                id: INVOICE_ID.into(),
+                denom: TEST_DENOM.into(),
+                financier: Addr::unchecked("financier"),
+                advance: Uint128::new(900),
+                fee: Uint128::new(20),
+            }],
+            parse_events::<InvoiceFinanced>(&fund_response.events).unwrap()
         );
 
-        // verify invoice response
-        match pay_response {
-            Ok(..) => panic!("expected error, but ok"),
-            Err(error) => match error {
-                ContractError::SentFundsInvoiceMismatch => {}
-                error => panic!("unexpected error: {:?}", error),
+        // transferring names the new payee
+        let transfer_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            ExecuteMsg::TransferReceivable {
+                id: transferred_id.into(),
+                new_payee: "factor".into(),
             },
-        }
+        )
+        .unwrap();
+        let record = assert_record(&deps.storage, &transfer_response.messages, transferred_id);
+        assert_eq!(Addr::unchecked("factor"), record.payee);
+        assert_eq!(None, record.financier);
+        assert_eq!(
+            vec![ReceivableTransferred {
+                id: transferred_id.into(),
+                from: Addr::unchecked(RECIPIENT),
+                to: Addr::unchecked("factor"),
+            }],
+            parse_events::<ReceivableTransferred>(&transfer_response.events).unwrap()
+        );
+    }
 
-        // verify invoice stored
-        let invoice_storage = get_invoice_storage_read(&deps.storage);
+    fn query_deposit(deps: Deps<ProvenanceQuery>, address: &str) -> Vec<Coin> {
+        from_binary::<DepositResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::GetDeposit {
+                    address: address.into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .deposits
+    }
 
-        match invoice_storage.load(INVOICE_ID.as_bytes()) {
-            Ok(stored_invoice) => {
-                assert_eq!(
-                    stored_invoice,
-                    Invoice {
-                        id: INVOICE_ID.into(),
-                        amount,
-                        description: Option::None
-                    }
-                )
-            }
-            _ => {
-                panic!("invoice was not found in storage")
-            }
-        }
+    fn query_credit(deps: Deps<ProvenanceQuery>, address: &str) -> Coin {
+        from_binary::<CreditResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::GetCredit {
+                    address: address.into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .credit
     }
 
     fn assert_sent_funds_unsupported_error(
@@ -862,8 +6021,127 @@ mod tests {
         }
     }
 
+    #[test]
+    fn add_invoice_in_display_unit() {
+        let mut deps = mock_dependencies(&[]);
+
+        let mut state = test_state();
+        setup_test_base(&mut deps.storage, &state);
+
+        let add_msg = |display_amount: &str| ExecuteMsg::AddInvoice {
+            id: INVOICE_ID.into(),
+            amount: Uint128::zero(),
+            description: None,
+            due_date: None,
+            expires_at: None,
+            payer: None,
+            display_amount: Some(display_amount.into()),
+        };
+
+        // display amounts need the denom's metadata
+        match execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            add_msg("1.5"),
+        ) {
+            Err(ContractError::DenomMetadataUnavailable) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        state.denom_metadata = Some(DenomMetadata {
+            display: "usd".into(),
+            decimals: 6,
+        });
+        setup_test_base(&mut deps.storage, &state);
+
+        // amounts finer than one base unit are rejected
+        match execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            add_msg("1.0000005"),
+        ) {
+            Err(ContractError::FractionalBaseUnits { amount, decimals }) => {
+                assert_eq!("1.0000005", amount);
+                assert_eq!(6, decimals);
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        let add_response = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(ADMIN, &[]),
+            add_msg("1.5"),
+        )
+        .unwrap();
+        assert_eq!(add_response.attributes[3], attr("amount", "1500000"));
+
+        // the invoice query returns both representations
+        let response: InvoiceResponse = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetInvoice {
+                    id: INVOICE_ID.into(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(Uint128::new(1_500_000), response.invoice.amount);
+        assert_eq!(
+            Some(DisplayAmounts {
+                denom: "usd".into(),
+                amount: "1.5".into(),
+                amount_due: "1.5".into(),
+            }),
+            response.display
+        );
+    }
+
+    /// The contract config most tests start from; override fields with `..test_state()`.
+    pub(crate) fn test_state() -> State {
+        State {
+            admin: Addr::unchecked(ADMIN),
+            recipient: Addr::unchecked(RECIPIENT),
+            denom: TEST_DENOM.into(),
+            business_name: BUSINESS_NAME.into(),
+            overpayment: OverpaymentMode::Reject,
+            financing_terms: None,
+            financiers: vec![],
+            require_acceptance: false,
+            signing_key: None,
+            attribute_name: None,
+            payment_attributes: false,
+            required_attributes: vec![],
+            contract_name: None,
+            scope_specification_id: None,
+            denom_metadata: None,
+        }
+    }
+
+    /// An open invoice for `amount`; override fields with `..test_invoice(amount)`.
+    pub(crate) fn test_invoice(amount: u128) -> Invoice {
+        Invoice {
+            id: INVOICE_ID.into(),
+            amount: Uint128::new(amount),
+            amount_due: Uint128::new(amount),
+            description: Option::None,
+            due_date: None,
+            expires_at: None,
+            payer: None,
+            payee: None,
+            financing: None,
+            credit_notes: vec![],
+            acceptance: None,
+            scope_id: None,
+        }
+    }
+
     fn setup_test_base(storage: &mut dyn Storage, contract_info: &State) {
-        if let Err(error) = config(storage).save(&contract_info) {
+        if let Err(error) = config(storage).save(contract_info) {
             panic!("unexpected error: {:?}", error)
         }
     }
@@ -873,5 +6151,22 @@ mod tests {
         if let Err(error) = invoice_storage.save(invoice.id.as_bytes(), invoice) {
             panic!("unexpected error: {:?}", error)
         };
+        if let Err(error) =
+            update_stats(storage, TEST_DENOM, |stats| stats.open.add(invoice.amount))
+        {
+            panic!("unexpected error: {:?}", error)
+        };
+        if let Some(payer) = &invoice.payer {
+            let entry = LedgerEntry::new(
+                &invoice.id,
+                LedgerEntryKind::Invoice,
+                invoice.amount,
+                TEST_DENOM,
+                &mock_env().block,
+            );
+            if let Err(error) = append_ledger_entry(storage, payer, entry) {
+                panic!("unexpected error: {:?}", error)
+            };
+        }
     }
 }
@@ -0,0 +1,414 @@
+//! Denom metadata and amounts in a denom's display unit.
+//!
+//! Neither cosmwasm-std nor provwasm-std has a typed query for bank denom metadata, so it is
+//! fetched with a Stargate query and the few protobuf fields needed are decoded here. This makes
+//! the contract require the chain's `stargate` capability.
+use cosmwasm_std::{
+    to_vec, Binary, ContractResult, QuerierWrapper, QueryRequest, StdError, StdResult,
+    SystemResult, Uint128,
+};
+use provwasm_std::ProvenanceQuery;
+use std::convert::TryFrom;
+
+use crate::state::DenomMetadata;
+use crate::ContractError;
+
+const DENOM_METADATA_PATH: &str = "/cosmos.bank.v1beta1.Query/DenomMetadata";
+
+/// Look up the display unit of `denom` in the bank module.
+///
+/// Returns `None` when the chain has no metadata for the denom or refuses the query, leaving
+/// amounts in base units only. A response that is not a valid `QueryDenomMetadataResponse` is
+/// an error.
+pub fn query_denom_metadata(
+    querier: &QuerierWrapper<ProvenanceQuery>,
+    denom: &str,
+) -> StdResult<Option<DenomMetadata>> {
+    let request: QueryRequest<ProvenanceQuery> = QueryRequest::Stargate {
+        path: DENOM_METADATA_PATH.into(),
+        data: Binary::from(encode_string(1, denom)),
+    };
+
+    match querier.raw_query(&to_vec(&request)?) {
+        SystemResult::Ok(ContractResult::Ok(response)) => {
+            decode_denom_metadata(denom, response.as_slice())
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Whether `amount` is a plain decimal number, such as `12` or `12.50`.
+pub fn is_decimal(amount: &str) -> bool {
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    match amount.split_once('.') {
+        Some((whole, fraction)) => digits(whole) && digits(fraction),
+        None => digits(amount),
+    }
+}
+
+/// Convert an amount in the display unit to base units.
+///
+/// The conversion is exact: amounts finer than one base unit are rejected rather than rounded.
+pub fn to_base_units(
+    display_amount: &str,
+    metadata: &DenomMetadata,
+) -> Result<Uint128, ContractError> {
+    let invalid = || ContractError::InvalidFields {
+        fields: vec![String::from("display_amount")],
+    };
+    if !is_decimal(display_amount) {
+        return Err(invalid());
+    }
+
+    let (whole, fraction) = display_amount
+        .split_once('.')
+        .unwrap_or((display_amount, ""));
+    // trailing zeros do not make an amount fractional
+    let fraction = fraction.trim_end_matches('0');
+
+    let decimals = metadata.decimals as usize;
+    if fraction.len() > decimals {
+        return Err(ContractError::FractionalBaseUnits {
+            amount: display_amount.into(),
+            decimals: metadata.decimals,
+        });
+    }
+
+    // the digits shifted by the denom's decimals are the amount in base units
+    format!(
+        "{}{}{:0<width$}",
+        whole,
+        fraction,
+        "",
+        width = decimals - fraction.len()
+    )
+    .parse::<u128>()
+    .map(Uint128::new)
+    .map_err(|_| invalid())
+}
+
+/// Format an amount of base units in the display unit, without trailing zeros.
+pub fn to_display_amount(amount: Uint128, metadata: &DenomMetadata) -> String {
+    let decimals = metadata.decimals as usize;
+    let digits = format!("{:0>width$}", amount.u128(), width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+
+    match fraction.trim_end_matches('0') {
+        "" => whole.into(),
+        fraction => format!("{}.{}", whole, fraction),
+    }
+}
+
+/// Read the display unit from a `QueryDenomMetadataResponse`.
+///
+/// Metadata for another base denom, or without a usable display unit, is `None`; bytes that do
+/// not decode as protobuf are an error.
+fn decode_denom_metadata(denom: &str, response: &[u8]) -> StdResult<Option<DenomMetadata>> {
+    let malformed = || {
+        StdError::parse_err(
+            "QueryDenomMetadataResponse",
+            "malformed protobuf in denom metadata response",
+        )
+    };
+
+    let metadata = match bytes_field(&decode_fields(response).ok_or_else(malformed)?, 1) {
+        Some(metadata) => decode_fields(metadata).ok_or_else(malformed)?,
+        None => return Ok(None),
+    };
+    let units = metadata
+        .iter()
+        .filter_map(|(number, value)| match (number, value) {
+            (2, Value::Bytes(unit)) => Some(decode_fields(unit).ok_or_else(malformed)),
+            _ => None,
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let display = match (string_field(&metadata, 3), string_field(&metadata, 4)) {
+        (Some(base), Some(display)) if base == denom => display,
+        _ => return Ok(None),
+    };
+
+    // the exponent of the denom unit named by `display`, omitted when zero
+    let decimals = units
+        .iter()
+        .find(|unit| string_field(unit, 1) == Some(display))
+        .map(|unit| match unit.iter().find(|(number, _)| *number == 2) {
+            Some((_, Value::Varint(exponent))) => *exponent,
+            _ => 0,
+        });
+
+    // amounts must stay representable in a Uint128
+    Ok(decimals
+        .and_then(|decimals| u32::try_from(decimals).ok())
+        .filter(|decimals| 10u128.checked_pow(*decimals).is_some())
+        .map(|decimals| DenomMetadata {
+            display: display.into(),
+            decimals,
+        }))
+}
+
+// A protobuf field value; fixed-width values are kept as bytes.
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+fn decode_fields(mut data: &[u8]) -> Option<Vec<(u64, Value<'_>)>> {
+    let mut fields = vec![];
+    while !data.is_empty() {
+        let key = read_varint(&mut data)?;
+        let value = match key & 0x7 {
+            0 => Value::Varint(read_varint(&mut data)?),
+            1 => Value::Bytes(take(&mut data, 8)?),
+            2 => {
+                let len = usize::try_from(read_varint(&mut data)?).ok()?;
+                Value::Bytes(take(&mut data, len)?)
+            }
+            5 => Value::Bytes(take(&mut data, 4)?),
+            _ => return None,
+        };
+        fields.push((key >> 3, value));
+    }
+    Some(fields)
+}
+
+fn bytes_field<'a>(fields: &[(u64, Value<'a>)], number: u64) -> Option<&'a [u8]> {
+    fields.iter().find_map(|field| match field {
+        (n, Value::Bytes(bytes)) if *n == number => Some(*bytes),
+        _ => None,
+    })
+}
+
+fn string_field<'a>(fields: &[(u64, Value<'a>)], number: u64) -> Option<&'a str> {
+    std::str::from_utf8(bytes_field(fields, number)?).ok()
+}
+
+fn read_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = data.split_first()?;
+        *data = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if len > data.len() {
+        return None;
+    }
+    let (bytes, rest) = data.split_at(len);
+    *data = rest;
+    Some(bytes)
+}
+
+fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn encode_bytes(number: u64, bytes: &[u8]) -> Vec<u8> {
+    let mut out = vec![];
+    encode_varint(number << 3 | 2, &mut out);
+    encode_varint(bytes.len() as u64, &mut out);
+    out.extend_from_slice(bytes);
+    out
+}
+
+fn encode_string(number: u64, value: &str) -> Vec<u8> {
+    encode_bytes(number, value.as_bytes())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{MockApi, MockStorage};
+    use cosmwasm_std::{from_slice, OwnedDeps, Querier, QuerierResult};
+    use provwasm_mocks::{mock_dependencies, ProvenanceMockQuerier};
+    use std::marker::PhantomData;
+
+    /// Answers denom metadata queries ahead of the Provenance mock querier.
+    pub struct DenomMetadataQuerier {
+        pub base: ProvenanceMockQuerier,
+        pub denom: String,
+        pub response: Vec<u8>,
+    }
+
+    impl Querier for DenomMetadataQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            match from_slice::<QueryRequest<ProvenanceQuery>>(bin_request) {
+                Ok(QueryRequest::Stargate { path, data })
+                    if path == DENOM_METADATA_PATH
+                        && data.as_slice() == encode_string(1, &self.denom).as_slice() =>
+                {
+                    SystemResult::Ok(ContractResult::Ok(Binary::from(self.response.clone())))
+                }
+                _ => self.base.raw_query(bin_request),
+            }
+        }
+    }
+
+    /// Mock dependencies for a chain holding display unit metadata for `denom`.
+    pub fn mock_dependencies_with_metadata(
+        denom: &str,
+        display: &str,
+        decimals: u64,
+    ) -> OwnedDeps<MockStorage, MockApi, DenomMetadataQuerier, ProvenanceQuery> {
+        let deps = mock_dependencies(&[]);
+        OwnedDeps {
+            storage: deps.storage,
+            api: deps.api,
+            querier: DenomMetadataQuerier {
+                base: deps.querier,
+                denom: denom.into(),
+                response: metadata_response(denom, display, decimals),
+            },
+            custom_query_type: PhantomData,
+        }
+    }
+
+    // QueryDenomMetadataResponse with a base unit and a display unit
+    fn metadata_response(denom: &str, display: &str, decimals: u64) -> Vec<u8> {
+        let mut display_unit = encode_string(1, display);
+        display_unit.push(2 << 3);
+        encode_varint(decimals, &mut display_unit);
+
+        let mut metadata = encode_string(1, "a test denom");
+        metadata.extend(encode_bytes(2, &encode_string(1, denom)));
+        metadata.extend(encode_bytes(2, &display_unit));
+        metadata.extend(encode_string(3, denom));
+        metadata.extend(encode_string(4, display));
+
+        encode_bytes(1, &metadata)
+    }
+
+    fn hash() -> DenomMetadata {
+        DenomMetadata {
+            display: "hash".into(),
+            decimals: 9,
+        }
+    }
+
+    #[test]
+    fn looks_up_display_unit() {
+        let deps = mock_dependencies_with_metadata("nhash", "hash", 9);
+        let querier = QuerierWrapper::<ProvenanceQuery>::new(&deps.querier);
+
+        assert_eq!(
+            Some(hash()),
+            query_denom_metadata(&querier, "nhash").unwrap()
+        );
+        // the mock chain refuses Stargate queries for other denoms
+        assert_eq!(None, query_denom_metadata(&querier, "usdx.c").unwrap());
+
+        // metadata for a different base denom is ignored
+        assert_eq!(
+            None,
+            decode_denom_metadata("uhash", &metadata_response("nhash", "hash", 9)).unwrap()
+        );
+        // a display unit beyond what a Uint128 can hold is ignored
+        assert_eq!(
+            None,
+            decode_denom_metadata("nhash", &metadata_response("nhash", "hash", 39)).unwrap()
+        );
+        // an empty response carries no metadata
+        assert_eq!(None, decode_denom_metadata("nhash", &[]).unwrap());
+    }
+
+    #[test]
+    fn converts_display_amounts_exactly() {
+        assert_eq!(
+            Uint128::new(1_500_000_000),
+            to_base_units("1.5", &hash()).unwrap()
+        );
+        assert_eq!(
+            Uint128::new(100_000_000_000),
+            to_base_units("100", &hash()).unwrap()
+        );
+        assert_eq!(
+            Uint128::new(1),
+            to_base_units("0.000000001", &hash()).unwrap()
+        );
+        assert_eq!(
+            Uint128::new(2_000_000_000),
+            to_base_units("2.000000000000", &hash()).unwrap()
+        );
+
+        match to_base_units("0.0000000015", &hash()) {
+            Err(ContractError::FractionalBaseUnits { amount, decimals }) => {
+                assert_eq!("0.0000000015", amount);
+                assert_eq!(9, decimals);
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+        for amount in &[
+            "",
+            ".5",
+            "1.",
+            "1,5",
+            "-1",
+            "1e9",
+            "340282366920938463463374607432",
+        ] {
+            match to_base_units(amount, &hash()) {
+                Err(ContractError::InvalidFields { fields }) => {
+                    assert_eq!(vec!["display_amount"], fields)
+                }
+                result => panic!("unexpected result for {:?}: {:?}", amount, result),
+            }
+        }
+
+        assert_eq!(
+            "1.5",
+            to_display_amount(Uint128::new(1_500_000_000), &hash())
+        );
+        assert_eq!(
+            "100",
+            to_display_amount(Uint128::new(100_000_000_000), &hash())
+        );
+        assert_eq!("0.000000001", to_display_amount(Uint128::new(1), &hash()));
+        assert_eq!("0", to_display_amount(Uint128::zero(), &hash()));
+    }
+
+    #[test]
+    fn rejects_malformed_responses() {
+        assert!(decode_fields(&[0x0a, 0x05, b'n']).is_none());
+        assert!(decode_fields(&[0x08, 0xff]).is_none());
+
+        // a truncated response, and a denom unit that does not decode, are errors
+        let response = metadata_response("nhash", "hash", 9);
+        let mut metadata = encode_string(3, "nhash");
+        metadata.extend(encode_bytes(2, &[0x0a, 0x05, b'n']));
+        for response in &[
+            response[..response.len() - 1].to_vec(),
+            encode_bytes(1, &[0x08, 0xff]),
+            encode_bytes(1, &metadata),
+        ] {
+            match decode_denom_metadata("nhash", response) {
+                Err(StdError::ParseErr { target_type, .. }) => {
+                    assert_eq!("QueryDenomMetadataResponse", target_type)
+                }
+                result => panic!("unexpected result: {:?}", result),
+            }
+        }
+
+        let deps: OwnedDeps<_, _, _, ProvenanceQuery> = OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: DenomMetadataQuerier {
+                base: mock_dependencies(&[]).querier,
+                denom: "nhash".into(),
+                response: response[..response.len() - 1].to_vec(),
+            },
+            custom_query_type: PhantomData,
+        };
+        let querier = QuerierWrapper::<ProvenanceQuery>::new(&deps.querier);
+        assert!(query_denom_metadata(&querier, "nhash").is_err());
+    }
+}
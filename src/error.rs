@@ -6,6 +6,57 @@ pub enum ContractError {
     #[error("Invalid fields: {fields:?}")]
     InvalidFields { fields: Vec<String> },
 
+    #[error("Invoice cannot be financed: {reason}")]
+    FinancingUnavailable { reason: String },
+
+    #[error("Invoice {id} has not been accepted by the payer")]
+    InvoiceNotAccepted { id: String },
+
+    #[error("Invoice {id} has already been accepted or rejected")]
+    AcceptanceAlreadyRecorded { id: String },
+
+    #[error("Payment {payment_ref} has already been processed")]
+    DuplicatePaymentRef { payment_ref: String },
+
+    #[error("Sender {address} is denylisted")]
+    SenderDenylisted { address: String },
+
+    #[error("Payer {address} is denylisted")]
+    PayerDenylisted { address: String },
+
+    #[error("Recipient {address} is denylisted")]
+    RecipientDenylisted { address: String },
+
+    #[error("Payee {address} is denylisted")]
+    PayeeDenylisted { address: String },
+
+    #[error("Financier {address} is denylisted")]
+    FinancierDenylisted { address: String },
+
+    #[error("Payer {address} is missing required attribute {attribute}")]
+    PayerNotVerified { address: String, attribute: String },
+
+    #[error("No attribute name is bound to the contract")]
+    AttributeNameUnbound,
+
+    #[error("Attribute name {name} is already bound to the contract")]
+    AttributeNameBound { name: String },
+
+    #[error("No name is bound to the contract")]
+    ContractNameUnbound,
+
+    #[error("Invoice signature is invalid")]
+    InvalidSignature,
+
+    #[error("Invoice {id} has expired")]
+    InvoiceExpired { id: String },
+
+    #[error("Invoice {id} receivable has been transferred")]
+    ReceivableTransferred { id: String },
+
+    #[error("Invoice {id} has been financed")]
+    InvoiceFinanced { id: String },
+
     #[error("Failed to load invoice: {error:?}")]
     LoadInvoiceFailed { error: StdError },
 
@@ -18,16 +69,38 @@ pub enum ContractError {
     #[error("bank send funds do not match invoice")]
     SentFundsInvoiceMismatch,
 
+    #[error("credit note exceeds amount due")]
+    CreditNoteExceedsAmountDue,
+
+    #[error("deposit is insufficient")]
+    InsufficientDeposit,
+
+    #[error("no credit balance to withdraw")]
+    NoCreditBalance,
+
     #[error("Unauthorized: {error:?}")]
     Unauthorized { error: String },
 
-    // #[error("Unsupported upgrade: {source_version:?} => {target_version:?}")]
-    // UnsupportedUpgrade {
-    //     source_version: String,
-    //     target_version: String,
-    // },
+    #[error("Unsupported upgrade: {source_version:?} => {target_version:?}")]
+    UnsupportedUpgrade {
+        source_version: String,
+        target_version: String,
+    },
+
+    #[error("Invalid contract name: {contract_name:?}")]
+    InvalidContractName { contract_name: String },
+
+    #[error("Only {denom:?} funds are supported")]
+    UnsupportedDenom { denom: String },
+
     #[error("Only unrestricted markers are supported")]
     UnsupportedMarkerType,
+
+    #[error("{amount:?} is finer than one base unit of a denom with {decimals} decimals")]
+    FractionalBaseUnits { amount: String, decimals: u32 },
+
+    #[error("No display unit is known for the contract denom")]
+    DenomMetadataUnavailable,
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
 
@@ -0,0 +1,782 @@
+//! Typed `wasm-` events emitted by the contract.
+//!
+//! Every event carries an `event_version` attribute, and any change to an event's attributes
+//! bumps [`EVENT_VERSION`]. Only events of the current version parse, since earlier versions
+//! lack attributes the structs require. Optional values are omitted rather than emitted empty,
+//! and timestamps are nanoseconds since epoch.
+//!
+//! Off-chain consumers can rebuild the structs with [`ContractEvent::parse`], which accepts
+//! both the event type as built by the contract and the `wasm-` prefixed type seen on chain.
+//!
+//! | Event | Attributes |
+//! |-------|------------|
+//! | `invoice_added` | `id`, `amount`, `denom`, `recipient`, `payer`?, `due_date`?, `expires_at`?, `scope_id`? |
+//! | `invoice_paid` | `id`, `amount`, `denom`, `payer`, `payee`, `receipt` |
+//! | `invoice_cancelled` | `id`, `amount`, `denom` |
+//! | `invoice_credited` | `id`, `amount`, `amount_due`, `denom`, `reason` |
+//! | `invoice_amended` | `id`, `amount`, `amount_due`, `denom`, `due_date`? |
+//! | `receivable_transferred` | `id`, `from`, `to` |
+//! | `invoice_financed` | `id`, `denom`, `financier`, `advance`, `fee` |
+//! | `invoice_expired` | `id`, `amount`, `denom`, `written_off` |
+//! | `denylist_updated` | `address`, `denied` |
+//! | `config_updated` | `admin`, `recipient`, `denom`, `business_name`, `overpayment`, `require_acceptance`, `payment_attributes`, `required_attributes`?, `attribute_name`?, `contract_name`?, `scope_specification_id`?, `signing_key`?, `advance_rate_bps`?, `fee_bps`?, `financiers`? |
+use cosmwasm_std::{Addr, Binary, Event, StdError, StdResult, Timestamp, Uint128};
+use std::str::FromStr;
+
+use crate::state::{
+    CreditNote, Financing, FinancingTerms, Invoice, OverpaymentMode, PaymentReceipt,
+    ReceivableTransfer, State,
+};
+
+/// Version of the attribute schema documented above.
+pub const EVENT_VERSION: &str = "2";
+
+const VERSION_KEY: &str = "event_version";
+const CHAIN_EVENT_PREFIX: &str = "wasm-";
+
+/// An event with a stable attribute schema.
+pub trait ContractEvent: Sized {
+    /// Event type without the `wasm-` prefix added on chain
+    const EVENT_TYPE: &'static str;
+
+    /// Build the event, including its version attribute.
+    fn to_event(&self) -> Event;
+
+    /// Rebuild the event from its attributes.
+    fn parse(event: &Event) -> StdResult<Self>;
+
+    /// Whether `event` has this event's type, with or without the `wasm-` prefix.
+    fn matches(event: &Event) -> bool {
+        event.ty == Self::EVENT_TYPE
+            || event.ty.strip_prefix(CHAIN_EVENT_PREFIX) == Some(Self::EVENT_TYPE)
+    }
+}
+
+/// Parse every event of type `T` in `events`, skipping other types.
+pub fn parse_events<T: ContractEvent>(events: &[Event]) -> StdResult<Vec<T>> {
+    events
+        .iter()
+        .filter(|event| T::matches(event))
+        .map(T::parse)
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvoiceAdded {
+    pub id: String,
+    pub amount: Uint128,
+    pub denom: String,
+    pub recipient: Addr,
+    pub payer: Option<Addr>,
+    pub due_date: Option<Timestamp>,
+    pub expires_at: Option<Timestamp>,
+    pub scope_id: Option<String>,
+}
+
+impl InvoiceAdded {
+    pub fn new(state: &State, invoice: &Invoice) -> Self {
+        InvoiceAdded {
+            id: invoice.id.to_owned(),
+            amount: invoice.amount,
+            denom: state.denom.to_owned(),
+            recipient: state.recipient.to_owned(),
+            payer: invoice.payer.to_owned(),
+            due_date: invoice.due_date,
+            expires_at: invoice.expires_at,
+            scope_id: invoice.scope_id.to_owned(),
+        }
+    }
+}
+
+impl ContractEvent for InvoiceAdded {
+    const EVENT_TYPE: &'static str = "invoice_added";
+
+    fn to_event(&self) -> Event {
+        let event = new_event(Self::EVENT_TYPE)
+            .add_attribute("id", &self.id)
+            .add_attribute("amount", self.amount)
+            .add_attribute("denom", &self.denom)
+            .add_attribute("recipient", &self.recipient);
+        let event = optional(event, "payer", &self.payer);
+        let event = optional(event, "due_date", &self.due_date.map(|time| time.nanos()));
+        let event = optional(
+            event,
+            "expires_at",
+            &self.expires_at.map(|time| time.nanos()),
+        );
+        optional(event, "scope_id", &self.scope_id)
+    }
+
+    fn parse(event: &Event) -> StdResult<Self> {
+        let reader = EventReader::new::<Self>(event)?;
+        Ok(InvoiceAdded {
+            id: reader.required("id")?.into(),
+            amount: reader.parse("amount")?,
+            denom: reader.required("denom")?.into(),
+            recipient: Addr::unchecked(reader.required("recipient")?),
+            payer: reader.optional("payer").map(Addr::unchecked),
+            due_date: reader.timestamp("due_date")?,
+            expires_at: reader.timestamp("expires_at")?,
+            scope_id: reader.optional("scope_id").map(String::from),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvoicePaid {
+    pub id: String,
+    pub amount: Uint128,
+    pub denom: String,
+    pub payer: Addr,
+    pub payee: Addr,
+    // Sequence of the payment receipt
+    pub receipt: u64,
+}
+
+impl InvoicePaid {
+    pub fn new(state: &State, invoice: &Invoice, receipt: &PaymentReceipt) -> Self {
+        InvoicePaid {
+            id: invoice.id.to_owned(),
+            amount: receipt.amount,
+            denom: receipt.denom.to_owned(),
+            payer: receipt.payer.to_owned(),
+            payee: invoice.payee(state).to_owned(),
+            receipt: receipt.sequence,
+        }
+    }
+}
+
+impl ContractEvent for InvoicePaid {
+    const EVENT_TYPE: &'static str = "invoice_paid";
+
+    fn to_event(&self) -> Event {
+        new_event(Self::EVENT_TYPE)
+            .add_attribute("id", &self.id)
+            .add_attribute("amount", self.amount)
+            .add_attribute("denom", &self.denom)
+            .add_attribute("payer", &self.payer)
+            .add_attribute("payee", &self.payee)
+            .add_attribute("receipt", self.receipt.to_string())
+    }
+
+    fn parse(event: &Event) -> StdResult<Self> {
+        let reader = EventReader::new::<Self>(event)?;
+        Ok(InvoicePaid {
+            id: reader.required("id")?.into(),
+            amount: reader.parse("amount")?,
+            denom: reader.required("denom")?.into(),
+            payer: Addr::unchecked(reader.required("payer")?),
+            payee: Addr::unchecked(reader.required("payee")?),
+            receipt: reader.parse("receipt")?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvoiceCancelled {
+    pub id: String,
+    // Amount that was still due
+    pub amount: Uint128,
+    pub denom: String,
+}
+
+impl InvoiceCancelled {
+    pub fn new(state: &State, invoice: &Invoice) -> Self {
+        InvoiceCancelled {
+            id: invoice.id.to_owned(),
+            amount: invoice.amount_due,
+            denom: state.denom.to_owned(),
+        }
+    }
+}
+
+impl ContractEvent for InvoiceCancelled {
+    const EVENT_TYPE: &'static str = "invoice_cancelled";
+
+    fn to_event(&self) -> Event {
+        new_event(Self::EVENT_TYPE)
+            .add_attribute("id", &self.id)
+            .add_attribute("amount", self.amount)
+            .add_attribute("denom", &self.denom)
+    }
+
+    fn parse(event: &Event) -> StdResult<Self> {
+        let reader = EventReader::new::<Self>(event)?;
+        Ok(InvoiceCancelled {
+            id: reader.required("id")?.into(),
+            amount: reader.parse("amount")?,
+            denom: reader.required("denom")?.into(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvoiceCredited {
+    pub id: String,
+    // Amount of the credit note
+    pub amount: Uint128,
+    // Amount still due after the credit note
+    pub amount_due: Uint128,
+    pub denom: String,
+    pub reason: String,
+}
+
+impl InvoiceCredited {
+    pub fn new(state: &State, invoice: &Invoice, note: &CreditNote) -> Self {
+        InvoiceCredited {
+            id: invoice.id.to_owned(),
+            amount: note.amount,
+            amount_due: invoice.amount_due,
+            denom: state.denom.to_owned(),
+            reason: note.reason.to_owned(),
+        }
+    }
+}
+
+impl ContractEvent for InvoiceCredited {
+    const EVENT_TYPE: &'static str = "invoice_credited";
+
+    fn to_event(&self) -> Event {
+        new_event(Self::EVENT_TYPE)
+            .add_attribute("id", &self.id)
+            .add_attribute("amount", self.amount)
+            .add_attribute("amount_due", self.amount_due)
+            .add_attribute("denom", &self.denom)
+            .add_attribute("reason", &self.reason)
+    }
+
+    fn parse(event: &Event) -> StdResult<Self> {
+        let reader = EventReader::new::<Self>(event)?;
+        Ok(InvoiceCredited {
+            id: reader.required("id")?.into(),
+            amount: reader.parse("amount")?,
+            amount_due: reader.parse("amount_due")?,
+            denom: reader.required("denom")?.into(),
+            reason: reader.required("reason")?.into(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvoiceAmended {
+    pub id: String,
+    pub amount: Uint128,
+    // Amount still due after earlier credit notes
+    pub amount_due: Uint128,
+    pub denom: String,
+    pub due_date: Option<Timestamp>,
+}
+
+impl InvoiceAmended {
+    pub fn new(state: &State, invoice: &Invoice) -> Self {
+        InvoiceAmended {
+            id: invoice.id.to_owned(),
+            amount: invoice.amount,
+            amount_due: invoice.amount_due,
+            denom: state.denom.to_owned(),
+            due_date: invoice.due_date,
+        }
+    }
+}
+
+impl ContractEvent for InvoiceAmended {
+    const EVENT_TYPE: &'static str = "invoice_amended";
+
+    fn to_event(&self) -> Event {
+        let event = new_event(Self::EVENT_TYPE)
+            .add_attribute("id", &self.id)
+            .add_attribute("amount", self.amount)
+            .add_attribute("amount_due", self.amount_due)
+            .add_attribute("denom", &self.denom);
+        optional(event, "due_date", &self.due_date.map(|time| time.nanos()))
+    }
+
+    fn parse(event: &Event) -> StdResult<Self> {
+        let reader = EventReader::new::<Self>(event)?;
+        Ok(InvoiceAmended {
+            id: reader.required("id")?.into(),
+            amount: reader.parse("amount")?,
+            amount_due: reader.parse("amount_due")?,
+            denom: reader.required("denom")?.into(),
+            due_date: reader.timestamp("due_date")?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReceivableTransferred {
+    pub id: String,
+    pub from: Addr,
+    pub to: Addr,
+}
+
+impl ReceivableTransferred {
+    pub fn new(invoice: &Invoice, transfer: &ReceivableTransfer) -> Self {
+        ReceivableTransferred {
+            id: invoice.id.to_owned(),
+            from: transfer.from.to_owned(),
+            to: transfer.to.to_owned(),
+        }
+    }
+}
+
+impl ContractEvent for ReceivableTransferred {
+    const EVENT_TYPE: &'static str = "receivable_transferred";
+
+    fn to_event(&self) -> Event {
+        new_event(Self::EVENT_TYPE)
+            .add_attribute("id", &self.id)
+            .add_attribute("from", &self.from)
+            .add_attribute("to", &self.to)
+    }
+
+    fn parse(event: &Event) -> StdResult<Self> {
+        let reader = EventReader::new::<Self>(event)?;
+        Ok(ReceivableTransferred {
+            id: reader.required("id")?.into(),
+            from: Addr::unchecked(reader.required("from")?),
+            to: Addr::unchecked(reader.required("to")?),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvoiceFinanced {
+    pub id: String,
+    pub denom: String,
+    pub financier: Addr,
+    // Paid to the merchant when funded
+    pub advance: Uint128,
+    // Owed to the financier on top of the advance
+    pub fee: Uint128,
+}
+
+impl InvoiceFinanced {
+    pub fn new(state: &State, invoice: &Invoice, financing: &Financing) -> Self {
+        InvoiceFinanced {
+            id: invoice.id.to_owned(),
+            denom: state.denom.to_owned(),
+            financier: financing.financier.to_owned(),
+            advance: financing.advance,
+            fee: financing.fee,
+        }
+    }
+}
+
+impl ContractEvent for InvoiceFinanced {
+    const EVENT_TYPE: &'static str = "invoice_financed";
+
+    fn to_event(&self) -> Event {
+        new_event(Self::EVENT_TYPE)
+            .add_attribute("id", &self.id)
+            .add_attribute("denom", &self.denom)
+            .add_attribute("financier", &self.financier)
+            .add_attribute("advance", self.advance)
+            .add_attribute("fee", self.fee)
+    }
+
+    fn parse(event: &Event) -> StdResult<Self> {
+        let reader = EventReader::new::<Self>(event)?;
+        Ok(InvoiceFinanced {
+            id: reader.required("id")?.into(),
+            denom: reader.required("denom")?.into(),
+            financier: Addr::unchecked(reader.required("financier")?),
+            advance: reader.parse("advance")?,
+            fee: reader.parse("fee")?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvoiceExpired {
+    pub id: String,
+    // Amount that was still due
+    pub amount: Uint128,
+    pub denom: String,
+    // Whether the receivable had been transferred or financed
+    pub written_off: bool,
+}
+
+impl InvoiceExpired {
+    pub fn new(state: &State, invoice: &Invoice, written_off: bool) -> Self {
+        InvoiceExpired {
+            id: invoice.id.to_owned(),
+            amount: invoice.amount_due,
+            denom: state.denom.to_owned(),
+            written_off,
+        }
+    }
+}
+
+impl ContractEvent for InvoiceExpired {
+    const EVENT_TYPE: &'static str = "invoice_expired";
+
+    fn to_event(&self) -> Event {
+        new_event(Self::EVENT_TYPE)
+            .add_attribute("id", &self.id)
+            .add_attribute("amount", self.amount)
+            .add_attribute("denom", &self.denom)
+            .add_attribute("written_off", self.written_off.to_string())
+    }
+
+    fn parse(event: &Event) -> StdResult<Self> {
+        let reader = EventReader::new::<Self>(event)?;
+        Ok(InvoiceExpired {
+            id: reader.required("id")?.into(),
+            amount: reader.parse("amount")?,
+            denom: reader.required("denom")?.into(),
+            written_off: reader.parse("written_off")?,
+        })
+    }
+}
+
+/// An address added to (`denied`) or removed from the denylist.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DenylistUpdated {
+    pub address: Addr,
+    pub denied: bool,
+}
+
+impl ContractEvent for DenylistUpdated {
+    const EVENT_TYPE: &'static str = "denylist_updated";
+
+    fn to_event(&self) -> Event {
+        new_event(Self::EVENT_TYPE)
+            .add_attribute("address", &self.address)
+            .add_attribute("denied", self.denied.to_string())
+    }
+
+    fn parse(event: &Event) -> StdResult<Self> {
+        let reader = EventReader::new::<Self>(event)?;
+        Ok(DenylistUpdated {
+            address: Addr::unchecked(reader.required("address")?),
+            denied: reader.parse("denied")?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigUpdated {
+    pub admin: Addr,
+    pub recipient: Addr,
+    pub denom: String,
+    pub business_name: String,
+    pub overpayment: OverpaymentMode,
+    pub require_acceptance: bool,
+    pub payment_attributes: bool,
+    pub required_attributes: Vec<String>,
+    pub attribute_name: Option<String>,
+    pub contract_name: Option<String>,
+    pub scope_specification_id: Option<String>,
+    pub signing_key: Option<Binary>,
+    pub financing_terms: Option<FinancingTerms>,
+    pub financiers: Vec<Addr>,
+}
+
+impl ConfigUpdated {
+    pub fn new(state: &State) -> Self {
+        ConfigUpdated {
+            admin: state.admin.to_owned(),
+            recipient: state.recipient.to_owned(),
+            denom: state.denom.to_owned(),
+            business_name: state.business_name.to_owned(),
+            overpayment: state.overpayment.to_owned(),
+            require_acceptance: state.require_acceptance,
+            payment_attributes: state.payment_attributes,
+            required_attributes: state.required_attributes.to_owned(),
+            attribute_name: state.attribute_name.to_owned(),
+            contract_name: state.contract_name.to_owned(),
+            scope_specification_id: state.scope_specification_id.to_owned(),
+            signing_key: state.signing_key.to_owned(),
+            financing_terms: state.financing_terms.to_owned(),
+            financiers: state.financiers.to_owned(),
+        }
+    }
+}
+
+impl ContractEvent for ConfigUpdated {
+    const EVENT_TYPE: &'static str = "config_updated";
+
+    fn to_event(&self) -> Event {
+        let overpayment = match self.overpayment {
+            OverpaymentMode::Reject => "reject",
+            OverpaymentMode::Credit => "credit",
+        };
+        let event = new_event(Self::EVENT_TYPE)
+            .add_attribute("admin", &self.admin)
+            .add_attribute("recipient", &self.recipient)
+            .add_attribute("denom", &self.denom)
+            .add_attribute("business_name", &self.business_name)
+            .add_attribute("overpayment", overpayment)
+            .add_attribute("require_acceptance", self.require_acceptance.to_string())
+            .add_attribute("payment_attributes", self.payment_attributes.to_string());
+        let event = optional(
+            event,
+            "required_attributes",
+            &join(&self.required_attributes),
+        );
+        let event = optional(event, "attribute_name", &self.attribute_name);
+        let event = optional(event, "contract_name", &self.contract_name);
+        let event = optional(
+            event,
+            "scope_specification_id",
+            &self.scope_specification_id,
+        );
+        let event = optional(
+            event,
+            "signing_key",
+            &self.signing_key.as_ref().map(Binary::to_base64),
+        );
+        let terms = self.financing_terms.as_ref();
+        let event = optional(
+            event,
+            "advance_rate_bps",
+            &terms.map(|terms| terms.advance_rate_bps),
+        );
+        let event = optional(event, "fee_bps", &terms.map(|terms| terms.fee_bps));
+        optional(event, "financiers", &join(&self.financiers))
+    }
+
+    fn parse(event: &Event) -> StdResult<Self> {
+        let reader = EventReader::new::<Self>(event)?;
+        let overpayment = match reader.required("overpayment")? {
+            "reject" => OverpaymentMode::Reject,
+            "credit" => OverpaymentMode::Credit,
+            other => {
+                return Err(StdError::parse_err(
+                    "OverpaymentMode",
+                    format!("unknown mode {}", other),
+                ))
+            }
+        };
+        Ok(ConfigUpdated {
+            admin: Addr::unchecked(reader.required("admin")?),
+            recipient: Addr::unchecked(reader.required("recipient")?),
+            denom: reader.required("denom")?.into(),
+            business_name: reader.required("business_name")?.into(),
+            overpayment,
+            require_acceptance: reader.parse("require_acceptance")?,
+            payment_attributes: reader.parse("payment_attributes")?,
+            required_attributes: reader
+                .optional("required_attributes")
+                .map(|names| names.split(',').map(String::from).collect())
+                .unwrap_or_default(),
+            attribute_name: reader.optional("attribute_name").map(String::from),
+            contract_name: reader.optional("contract_name").map(String::from),
+            scope_specification_id: reader.optional("scope_specification_id").map(String::from),
+            signing_key: reader
+                .optional("signing_key")
+                .map(Binary::from_base64)
+                .transpose()?,
+            financing_terms: match reader.optional("advance_rate_bps") {
+                Some(_) => Some(FinancingTerms {
+                    advance_rate_bps: reader.parse("advance_rate_bps")?,
+                    fee_bps: reader.parse("fee_bps")?,
+                }),
+                None => None,
+            },
+            financiers: reader
+                .optional("financiers")
+                .map(|names| names.split(',').map(Addr::unchecked).collect())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+fn new_event(ty: &str) -> Event {
+    Event::new(ty).add_attribute(VERSION_KEY, EVENT_VERSION)
+}
+
+// Comma separated values, or None when there are none.
+fn join<T: ToString>(values: &[T]) -> Option<String> {
+    Some(
+        values
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(","),
+    )
+    .filter(|joined| !joined.is_empty())
+}
+
+fn optional<T: ToString>(event: Event, key: &str, value: &Option<T>) -> Event {
+    match value {
+        Some(value) => event.add_attribute(key, value.to_string()),
+        None => event,
+    }
+}
+
+/// Attribute lookups for an event already checked for type and version.
+struct EventReader<'a> {
+    event: &'a Event,
+}
+
+impl<'a> EventReader<'a> {
+    fn new<T: ContractEvent>(event: &'a Event) -> StdResult<Self> {
+        if !T::matches(event) {
+            return Err(StdError::parse_err(
+                T::EVENT_TYPE,
+                format!("unexpected event type {}", event.ty),
+            ));
+        }
+
+        let reader = EventReader { event };
+        let version = reader.required(VERSION_KEY)?;
+        if version != EVENT_VERSION {
+            return Err(StdError::parse_err(
+                T::EVENT_TYPE,
+                format!("unsupported event version {}", version),
+            ));
+        }
+        Ok(reader)
+    }
+
+    fn optional(&self, key: &str) -> Option<&'a str> {
+        self.event
+            .attributes
+            .iter()
+            .find(|attribute| attribute.key == key)
+            .map(|attribute| attribute.value.as_str())
+    }
+
+    fn required(&self, key: &str) -> StdResult<&'a str> {
+        self.optional(key)
+            .ok_or_else(|| StdError::not_found(format!("{} attribute {}", self.event.ty, key)))
+    }
+
+    fn parse<T: FromStr>(&self, key: &str) -> StdResult<T> {
+        self.required(key)?
+            .parse()
+            .map_err(|_| StdError::parse_err(key, format!("invalid {} attribute", self.event.ty)))
+    }
+
+    fn timestamp(&self, key: &str) -> StdResult<Option<Timestamp>> {
+        self.optional(key)
+            .map(|_| self.parse(key).map(Timestamp::from_nanos))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invoice_added_round_trip() {
+        let added = InvoiceAdded {
+            id: "63069195-bc51-41bd-80d7-0ab84b98e283".into(),
+            amount: Uint128::new(5),
+            denom: "usdx.c".into(),
+            recipient: Addr::unchecked("recipient"),
+            payer: Some(Addr::unchecked("payer")),
+            due_date: None,
+            expires_at: Some(Timestamp::from_seconds(1_700_000_000)),
+            scope_id: None,
+        };
+
+        let event = added.to_event();
+        assert_eq!("invoice_added", event.ty);
+        assert!(!event.attributes.iter().any(|attr| attr.key == "due_date"));
+        assert_eq!(added, InvoiceAdded::parse(&event).unwrap());
+
+        // wasmd prefixes custom event types on chain
+        let mut chain_event = event;
+        chain_event.ty = "wasm-invoice_added".into();
+        assert_eq!(added, InvoiceAdded::parse(&chain_event).unwrap());
+    }
+
+    #[test]
+    fn invoice_credited_round_trip() {
+        let credited = InvoiceCredited {
+            id: "63069195-bc51-41bd-80d7-0ab84b98e283".into(),
+            amount: Uint128::new(30),
+            amount_due: Uint128::new(70),
+            denom: "usdx.c".into(),
+            reason: "damaged goods".into(),
+        };
+
+        assert_eq!(
+            credited,
+            InvoiceCredited::parse(&credited.to_event()).unwrap()
+        );
+    }
+
+    #[test]
+    fn invoice_amended_round_trip() {
+        let amended = InvoiceAmended {
+            id: "63069195-bc51-41bd-80d7-0ab84b98e283".into(),
+            amount: Uint128::new(120),
+            amount_due: Uint128::new(90),
+            denom: "usdx.c".into(),
+            due_date: Some(Timestamp::from_seconds(1_700_000_000)),
+        };
+
+        assert_eq!(amended, InvoiceAmended::parse(&amended.to_event()).unwrap());
+    }
+
+    #[test]
+    fn invoice_financed_round_trip() {
+        let financed = InvoiceFinanced {
+            id: "63069195-bc51-41bd-80d7-0ab84b98e283".into(),
+            denom: "usdx.c".into(),
+            financier: Addr::unchecked("financier"),
+            advance: Uint128::new(900),
+            fee: Uint128::new(20),
+        };
+
+        assert_eq!(
+            financed,
+            InvoiceFinanced::parse(&financed.to_event()).unwrap()
+        );
+    }
+
+    #[test]
+    fn config_updated_round_trip() {
+        let config = ConfigUpdated {
+            admin: Addr::unchecked("admin"),
+            recipient: Addr::unchecked("recipient"),
+            denom: "usdx.c".into(),
+            business_name: "Shoe Co, LLC".into(),
+            overpayment: OverpaymentMode::Credit,
+            require_acceptance: true,
+            payment_attributes: false,
+            required_attributes: vec!["kyc.onboarded.pb".into(), "aml.checked.pb".into()],
+            attribute_name: None,
+            contract_name: Some("shoeco.invoice.pb".into()),
+            scope_specification_id: None,
+            signing_key: Some(Binary::from(vec![2u8; 33])),
+            financing_terms: Some(FinancingTerms {
+                advance_rate_bps: 9000,
+                fee_bps: 200,
+            }),
+            financiers: vec![Addr::unchecked("financier"), Addr::unchecked("factor")],
+        };
+
+        assert_eq!(config, ConfigUpdated::parse(&config.to_event()).unwrap());
+    }
+
+    #[test]
+    fn parse_rejects_other_types_and_versions() {
+        let cancelled = InvoiceCancelled {
+            id: "63069195-bc51-41bd-80d7-0ab84b98e283".into(),
+            amount: Uint128::new(5),
+            denom: "usdx.c".into(),
+        };
+        let event = cancelled.to_event();
+        assert!(InvoicePaid::parse(&event).is_err());
+
+        // only the current version parses
+        for version in ["1", "3"] {
+            let mut other_event = event.clone();
+            other_event.attributes[0].value = version.into();
+            assert!(InvoiceCancelled::parse(&other_event).is_err());
+        }
+
+        // other event types are skipped
+        let events = vec![Event::new("transfer"), event];
+        assert_eq!(
+            vec![cancelled],
+            parse_events::<InvoiceCancelled>(&events).unwrap()
+        );
+    }
+}
@@ -0,0 +1,737 @@
+//! Rebuilds invoice history off-chain from the events the contract emits.
+//!
+//! Transactions are folded in block order with [`InvoiceIndex::apply`]. Only the typed events in
+//! [`crate::events`] emitted by the indexed contract are read, matched on the `_contract_address`
+//! attribute wasmd adds, so other contracts in the same transaction are ignored. The index can be
+//! queried in memory or exported as JSON or CSV.
+//!
+//! Only available with the `indexer` feature, which keeps it out of the wasm build.
+use cosmwasm_std::{Addr, Env, Event, Response, StdError, StdResult, Timestamp, Uint128};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::events::{
+    ConfigUpdated, ContractEvent, InvoiceAdded, InvoiceAmended, InvoiceCancelled, InvoiceCredited,
+    InvoiceExpired, InvoiceFinanced, InvoicePaid, ReceivableTransferred,
+};
+
+const WASM_EVENT_TYPE: &str = "wasm";
+const CONTRACT_ADDRESS_KEY: &str = "_contract_address";
+const CSV_HEADER: &str =
+    "id,status,amount,amount_due,denom,payer,payee,financier,due_date,expires_at,scope_id,added_at,closed_at";
+
+/// The events of one transaction, with the block that included it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexedTx {
+    pub height: u64,
+    pub time: Timestamp,
+    pub events: Vec<Event>,
+}
+
+impl IndexedTx {
+    /// The custom events `response` produces on chain, with the `wasm-` prefix and the address of
+    /// the contract that emitted them.
+    pub fn from_response<T>(env: &Env, response: &Response<T>) -> Self {
+        let events = response
+            .events
+            .iter()
+            .map(|event| {
+                let mut chain_event = Event::new(format!("{}-{}", WASM_EVENT_TYPE, event.ty))
+                    .add_attribute(CONTRACT_ADDRESS_KEY, &env.contract.address);
+                chain_event
+                    .attributes
+                    .extend(event.attributes.iter().cloned());
+                chain_event
+            })
+            .collect();
+
+        IndexedTx {
+            height: env.block.height,
+            time: env.block.time,
+            events,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    Open,
+    Paid,
+    Cancelled,
+    Expired,
+}
+
+impl InvoiceStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            InvoiceStatus::Open => "open",
+            InvoiceStatus::Paid => "paid",
+            InvoiceStatus::Cancelled => "cancelled",
+            InvoiceStatus::Expired => "expired",
+        }
+    }
+}
+
+/// A change to an invoice, in the order it was indexed.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceChange {
+    Added {
+        amount: Uint128,
+    },
+    Amended {
+        amount: Uint128,
+        amount_due: Uint128,
+    },
+    Credited {
+        amount: Uint128,
+        amount_due: Uint128,
+    },
+    Paid {
+        amount: Uint128,
+        payer: Addr,
+        receipt: u64,
+    },
+    Cancelled {
+        amount: Uint128,
+    },
+    Expired {},
+    Transferred {
+        from: Addr,
+        to: Addr,
+    },
+    Financed {
+        financier: Addr,
+        advance: Uint128,
+        fee: Uint128,
+    },
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub height: u64,
+    pub time: Timestamp,
+    pub change: InvoiceChange,
+}
+
+/// Everything known about an invoice from its events.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct InvoiceRecord {
+    pub id: String,
+    pub status: InvoiceStatus,
+    pub amount: Uint128,
+    pub amount_due: Uint128,
+    pub denom: String,
+    pub payer: Option<Addr>,
+    // Current holder of the receivable
+    pub payee: Addr,
+    // Repaid the advance and fee out of the payment
+    pub financier: Option<Addr>,
+    pub due_date: Option<Timestamp>,
+    pub expires_at: Option<Timestamp>,
+    pub scope_id: Option<String>,
+    pub history: Vec<HistoryEntry>,
+}
+
+impl InvoiceRecord {
+    fn push(&mut self, tx: &IndexedTx, change: InvoiceChange) {
+        self.history.push(HistoryEntry {
+            height: tx.height,
+            time: tx.time,
+            change,
+        });
+    }
+}
+
+/// In-memory invoice history for one contract.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvoiceIndex {
+    contract: Addr,
+    invoices: BTreeMap<String, InvoiceRecord>,
+    config: Option<ConfigUpdated>,
+}
+
+impl InvoiceIndex {
+    /// An empty index of the invoice contract at `contract`.
+    pub fn new(contract: Addr) -> Self {
+        InvoiceIndex {
+            contract,
+            invoices: BTreeMap::new(),
+            config: None,
+        }
+    }
+
+    /// Fold one transaction into the index. Transactions must be applied in block order.
+    pub fn apply(&mut self, tx: &IndexedTx) -> StdResult<()> {
+        for event in &tx.events {
+            if !self.is_own(event) {
+                continue;
+            } else if InvoiceAdded::matches(event) {
+                self.apply_added(tx, InvoiceAdded::parse(event)?);
+            } else if InvoicePaid::matches(event) {
+                self.apply_paid(tx, InvoicePaid::parse(event)?);
+            } else if InvoiceCancelled::matches(event) {
+                let cancelled = InvoiceCancelled::parse(event)?;
+                let invoice = self.load_mut(&cancelled.id)?;
+                invoice.status = InvoiceStatus::Cancelled;
+                invoice.push(
+                    tx,
+                    InvoiceChange::Cancelled {
+                        amount: cancelled.amount,
+                    },
+                );
+            } else if InvoiceAmended::matches(event) {
+                let amended = InvoiceAmended::parse(event)?;
+                let invoice = self.load_mut(&amended.id)?;
+                invoice.amount = amended.amount;
+                invoice.amount_due = amended.amount_due;
+                invoice.due_date = amended.due_date;
+                invoice.push(
+                    tx,
+                    InvoiceChange::Amended {
+                        amount: amended.amount,
+                        amount_due: amended.amount_due,
+                    },
+                );
+            } else if InvoiceCredited::matches(event) {
+                let credited = InvoiceCredited::parse(event)?;
+                let invoice = self.load_mut(&credited.id)?;
+                invoice.amount_due = credited.amount_due;
+                invoice.push(
+                    tx,
+                    InvoiceChange::Credited {
+                        amount: credited.amount,
+                        amount_due: credited.amount_due,
+                    },
+                );
+            } else if ReceivableTransferred::matches(event) {
+                let transferred = ReceivableTransferred::parse(event)?;
+                let invoice = self.load_mut(&transferred.id)?;
+                invoice.payee = transferred.to.to_owned();
+                invoice.push(
+                    tx,
+                    InvoiceChange::Transferred {
+                        from: transferred.from,
+                        to: transferred.to,
+                    },
+                );
+            } else if InvoiceFinanced::matches(event) {
+                let financed = InvoiceFinanced::parse(event)?;
+                let invoice = self.load_mut(&financed.id)?;
+                invoice.financier = Some(financed.financier.to_owned());
+                invoice.push(
+                    tx,
+                    InvoiceChange::Financed {
+                        financier: financed.financier,
+                        advance: financed.advance,
+                        fee: financed.fee,
+                    },
+                );
+            } else if InvoiceExpired::matches(event) {
+                let expired = InvoiceExpired::parse(event)?;
+                let invoice = self.load_mut(&expired.id)?;
+                invoice.status = InvoiceStatus::Expired;
+                invoice.push(tx, InvoiceChange::Expired {});
+            } else if ConfigUpdated::matches(event) {
+                self.config = Some(ConfigUpdated::parse(event)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `event` was emitted by the indexed contract.
+    fn is_own(&self, event: &Event) -> bool {
+        event.attributes.iter().any(|attribute| {
+            attribute.key == CONTRACT_ADDRESS_KEY && attribute.value == self.contract
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&InvoiceRecord> {
+        self.invoices.get(id)
+    }
+
+    /// All invoices in id order.
+    pub fn invoices(&self) -> impl Iterator<Item = &InvoiceRecord> {
+        self.invoices.values()
+    }
+
+    pub fn by_status(&self, status: InvoiceStatus) -> impl Iterator<Item = &InvoiceRecord> {
+        self.invoices()
+            .filter(move |invoice| invoice.status == status)
+    }
+
+    pub fn by_payer<'a>(&'a self, payer: &'a Addr) -> impl Iterator<Item = &'a InvoiceRecord> {
+        self.invoices()
+            .filter(move |invoice| invoice.payer.as_ref() == Some(payer))
+    }
+
+    /// The latest configuration seen.
+    pub fn config(&self) -> Option<&ConfigUpdated> {
+        self.config.as_ref()
+    }
+
+    /// Every invoice with its history, as a JSON array.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(&self.invoices().collect::<Vec<_>>())
+    }
+
+    /// One row per invoice, without history. Timestamps are nanoseconds since epoch.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        csv.push('\n');
+        for invoice in self.invoices() {
+            let added_at = invoice.history.first().map(|entry| entry.time);
+            let closed_at = invoice
+                .history
+                .last()
+                .filter(|_| invoice.status != InvoiceStatus::Open)
+                .map(|entry| entry.time);
+            let row = vec![
+                invoice.id.to_owned(),
+                invoice.status.as_str().into(),
+                invoice.amount.to_string(),
+                invoice.amount_due.to_string(),
+                invoice.denom.to_owned(),
+                invoice
+                    .payer
+                    .as_ref()
+                    .map(Addr::to_string)
+                    .unwrap_or_default(),
+                invoice.payee.to_string(),
+                invoice
+                    .financier
+                    .as_ref()
+                    .map(Addr::to_string)
+                    .unwrap_or_default(),
+                csv_time(invoice.due_date),
+                csv_time(invoice.expires_at),
+                invoice.scope_id.to_owned().unwrap_or_default(),
+                csv_time(added_at),
+                csv_time(closed_at),
+            ];
+            csv.push_str(
+                &row.iter()
+                    .map(|field| csv_field(field))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+            csv.push('\n');
+        }
+        csv
+    }
+
+    fn apply_added(&mut self, tx: &IndexedTx, added: InvoiceAdded) {
+        let mut invoice = InvoiceRecord {
+            id: added.id.to_owned(),
+            status: InvoiceStatus::Open,
+            amount: added.amount,
+            amount_due: added.amount,
+            denom: added.denom,
+            payer: added.payer,
+            payee: added.recipient,
+            financier: None,
+            due_date: added.due_date,
+            expires_at: added.expires_at,
+            scope_id: added.scope_id,
+            history: vec![],
+        };
+        invoice.push(
+            tx,
+            InvoiceChange::Added {
+                amount: added.amount,
+            },
+        );
+        self.invoices.insert(added.id, invoice);
+    }
+
+    fn apply_paid(&mut self, tx: &IndexedTx, paid: InvoicePaid) {
+        // signed invoices are paid without being added first
+        let invoice = self
+            .invoices
+            .entry(paid.id.to_owned())
+            .or_insert_with(|| InvoiceRecord {
+                id: paid.id.to_owned(),
+                status: InvoiceStatus::Open,
+                amount: paid.amount,
+                amount_due: paid.amount,
+                denom: paid.denom.to_owned(),
+                payer: Some(paid.payer.to_owned()),
+                payee: paid.payee.to_owned(),
+                financier: None,
+                due_date: None,
+                expires_at: None,
+                scope_id: None,
+                history: vec![],
+            });
+        invoice.status = InvoiceStatus::Paid;
+        invoice.amount_due = Uint128::zero();
+        invoice.payee = paid.payee;
+        invoice.push(
+            tx,
+            InvoiceChange::Paid {
+                amount: paid.amount,
+                payer: paid.payer,
+                receipt: paid.receipt,
+            },
+        );
+    }
+
+    fn load_mut(&mut self, id: &str) -> StdResult<&mut InvoiceRecord> {
+        self.invoices
+            .get_mut(id)
+            .ok_or_else(|| StdError::not_found(format!("indexed invoice {}", id)))
+    }
+}
+
+fn csv_time(time: Option<Timestamp>) -> String {
+    time.map(|time| time.nanos().to_string())
+        .unwrap_or_default()
+}
+
+/// Quote a field when it contains a delimiter, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::execute;
+    use crate::contract::tests::test_state;
+    use crate::msg::ExecuteMsg;
+    use crate::state::{config, FinancingTerms, State};
+    use cosmwasm_std::testing::{mock_env, mock_info};
+    use cosmwasm_std::{coin, Env};
+    use provwasm_mocks::{mock_dependencies, ProvenanceMockQuerier};
+    use provwasm_std::ProvenanceQuery;
+
+    const ADMIN: &str = "contract_admin";
+    const RECIPIENT: &str = "recipient";
+    const DENOM: &str = "usdx.c";
+    const PAID_ID: &str = "63069195-bc51-41bd-80d7-0ab84b98e283";
+    const CANCELLED_ID: &str = "a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5";
+    const EXPIRED_ID: &str = "0d8cf0a5-6b4b-4c43-9d1c-2f2f1b6f7e0a";
+
+    type MockDeps = cosmwasm_std::OwnedDeps<
+        cosmwasm_std::MemoryStorage,
+        cosmwasm_std::testing::MockApi,
+        ProvenanceMockQuerier,
+        ProvenanceQuery,
+    >;
+
+    /// Execute `msg` at `height` and return the transaction it would be indexed from.
+    fn run(
+        deps: &mut MockDeps,
+        height: u64,
+        sender: &str,
+        funds: u128,
+        msg: ExecuteMsg,
+    ) -> IndexedTx {
+        let env = block_env(height);
+        let funds = if funds > 0 {
+            vec![coin(funds, DENOM)]
+        } else {
+            vec![]
+        };
+        let response = execute(deps.as_mut(), env.clone(), mock_info(sender, &funds), msg).unwrap();
+        IndexedTx::from_response(&env, &response)
+    }
+
+    fn block_env(height: u64) -> Env {
+        let mut env = mock_env();
+        env.block.height = height;
+        env.block.time = Timestamp::from_seconds(1_700_000_000 + height * 5);
+        env
+    }
+
+    fn add_msg(id: &str, amount: u128, expires_at: Option<Timestamp>) -> ExecuteMsg {
+        ExecuteMsg::AddInvoice {
+            id: id.into(),
+            amount: Uint128::new(amount),
+            description: None,
+            due_date: None,
+            expires_at,
+            payer: Some("payer".into()),
+            display_amount: None,
+        }
+    }
+
+    fn setup_deps() -> MockDeps {
+        let mut deps = mock_dependencies(&[]);
+        config(&mut deps.storage)
+            .save(&State {
+                admin: Addr::unchecked(ADMIN),
+                recipient: Addr::unchecked(RECIPIENT),
+                denom: DENOM.into(),
+                business_name: "Shoe Co, LLC".into(),
+                financing_terms: Some(FinancingTerms {
+                    advance_rate_bps: 9000,
+                    fee_bps: 200,
+                }),
+                financiers: vec![Addr::unchecked("financier")],
+                ..test_state()
+            })
+            .unwrap();
+        deps
+    }
+
+    /// Transactions produced by a full invoice lifecycle against the contract.
+    fn fixture() -> Vec<IndexedTx> {
+        let mut deps = setup_deps();
+
+        let expires_at = block_env(14).block.time;
+        vec![
+            run(&mut deps, 10, ADMIN, 0, add_msg(PAID_ID, 100, None)),
+            run(&mut deps, 11, ADMIN, 0, add_msg(CANCELLED_ID, 50, None)),
+            run(
+                &mut deps,
+                12,
+                ADMIN,
+                0,
+                add_msg(EXPIRED_ID, 25, Some(expires_at)),
+            ),
+            run(
+                &mut deps,
+                13,
+                ADMIN,
+                0,
+                ExecuteMsg::AmendInvoice {
+                    id: PAID_ID.into(),
+                    amount: Uint128::new(120),
+                    description: None,
+                    due_date: None,
+                },
+            ),
+            run(
+                &mut deps,
+                14,
+                ADMIN,
+                0,
+                ExecuteMsg::IssueCreditNote {
+                    invoice_id: PAID_ID.into(),
+                    amount: Uint128::new(20),
+                    reason: "Damaged box".into(),
+                },
+            ),
+            run(
+                &mut deps,
+                15,
+                "payer",
+                100,
+                ExecuteMsg::PayInvoice {
+                    id: PAID_ID.into(),
+                    payment_ref: None,
+                },
+            ),
+            run(
+                &mut deps,
+                16,
+                ADMIN,
+                0,
+                ExecuteMsg::CancelInvoice {
+                    id: CANCELLED_ID.into(),
+                },
+            ),
+            run(
+                &mut deps,
+                17,
+                "pruner",
+                0,
+                ExecuteMsg::PruneExpired { limit: None },
+            ),
+            run(
+                &mut deps,
+                18,
+                ADMIN,
+                0,
+                ExecuteMsg::UpdateConfig {
+                    recipient: None,
+                    business_name: Some("Shoe Co, Inc".into()),
+                    contract_name: None,
+                    attribute_name: None,
+                },
+            ),
+        ]
+    }
+
+    fn index(txs: &[IndexedTx]) -> InvoiceIndex {
+        let mut index = InvoiceIndex::new(mock_env().contract.address);
+        for tx in txs {
+            index.apply(tx).unwrap();
+        }
+        index
+    }
+
+    #[test]
+    fn folds_invoice_lifecycle() {
+        let index = index(&fixture());
+
+        let paid = index.get(PAID_ID).unwrap();
+        assert_eq!(InvoiceStatus::Paid, paid.status);
+        assert_eq!(Uint128::new(120), paid.amount);
+        assert_eq!(Uint128::zero(), paid.amount_due);
+        assert_eq!(
+            vec![
+                InvoiceChange::Added {
+                    amount: Uint128::new(100)
+                },
+                InvoiceChange::Amended {
+                    amount: Uint128::new(120),
+                    amount_due: Uint128::new(120)
+                },
+                InvoiceChange::Credited {
+                    amount: Uint128::new(20),
+                    amount_due: Uint128::new(100)
+                },
+                InvoiceChange::Paid {
+                    amount: Uint128::new(100),
+                    payer: Addr::unchecked("payer"),
+                    receipt: 1
+                },
+            ],
+            paid.history
+                .iter()
+                .map(|entry| entry.change.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(15, paid.history[3].height);
+
+        assert_eq!(
+            InvoiceStatus::Cancelled,
+            index.get(CANCELLED_ID).unwrap().status
+        );
+        assert_eq!(
+            InvoiceStatus::Expired,
+            index.get(EXPIRED_ID).unwrap().status
+        );
+        assert_eq!(3, index.by_payer(&Addr::unchecked("payer")).count());
+        assert_eq!(0, index.by_status(InvoiceStatus::Open).count());
+        assert_eq!("Shoe Co, Inc", index.config().unwrap().business_name);
+    }
+
+    #[test]
+    fn folds_transfers_and_financing_into_the_holder() {
+        let mut deps = setup_deps();
+        let txs = vec![
+            run(&mut deps, 10, ADMIN, 0, add_msg(PAID_ID, 100, None)),
+            run(&mut deps, 11, ADMIN, 0, add_msg(CANCELLED_ID, 1000, None)),
+            run(
+                &mut deps,
+                12,
+                ADMIN,
+                0,
+                ExecuteMsg::TransferReceivable {
+                    id: PAID_ID.into(),
+                    new_payee: "factor".into(),
+                },
+            ),
+            run(
+                &mut deps,
+                13,
+                "financier",
+                900,
+                ExecuteMsg::FundInvoice {
+                    id: CANCELLED_ID.into(),
+                },
+            ),
+            run(
+                &mut deps,
+                14,
+                "payer",
+                100,
+                ExecuteMsg::PayInvoice {
+                    id: PAID_ID.into(),
+                    payment_ref: None,
+                },
+            ),
+        ];
+        let index = index(&txs);
+
+        let sold = index.get(PAID_ID).unwrap();
+        assert_eq!(Addr::unchecked("factor"), sold.payee);
+        assert_eq!(
+            InvoiceChange::Transferred {
+                from: Addr::unchecked(RECIPIENT),
+                to: Addr::unchecked("factor"),
+            },
+            sold.history[1].change
+        );
+        assert_eq!(InvoiceStatus::Paid, sold.status);
+
+        let financed = index.get(CANCELLED_ID).unwrap();
+        assert_eq!(Addr::unchecked(RECIPIENT), financed.payee);
+        assert_eq!(Some(Addr::unchecked("financier")), financed.financier);
+        assert_eq!(
+            InvoiceChange::Financed {
+                financier: Addr::unchecked("financier"),
+                advance: Uint128::new(900),
+                fee: Uint128::new(20),
+            },
+            financed.history[1].change
+        );
+    }
+
+    #[test]
+    fn ignores_other_contracts() {
+        let mut deps = setup_deps();
+        let mut env = block_env(10);
+        env.contract.address = Addr::unchecked("other_contract");
+        let response = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ADMIN, &[]),
+            add_msg(PAID_ID, 100, None),
+        )
+        .unwrap();
+
+        let index = index(&[IndexedTx::from_response(&env, &response)]);
+        assert_eq!(None, index.get(PAID_ID));
+    }
+
+    #[test]
+    fn exports_csv() {
+        let csv = index(&fixture()).to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(CSV_HEADER, lines[0]);
+        assert_eq!(
+            "0d8cf0a5-6b4b-4c43-9d1c-2f2f1b6f7e0a,expired,25,25,usdx.c,payer,recipient,,,1700000070000000000,,1700000060000000000,1700000085000000000",
+            lines[1]
+        );
+        assert_eq!(4, lines.len());
+    }
+
+    #[test]
+    fn exports_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&index(&fixture()).to_json().unwrap()).unwrap();
+
+        let invoices = json.as_array().unwrap();
+        assert_eq!(3, invoices.len());
+        assert_eq!("cancelled", invoices[2]["status"]);
+        assert_eq!(
+            "50",
+            invoices[2]["history"][1]["change"]["cancelled"]["amount"]
+        );
+    }
+
+    #[test]
+    fn unknown_invoice_is_an_error() {
+        let txs = fixture();
+        let mut index = InvoiceIndex::new(mock_env().contract.address);
+
+        // cancelling an invoice that was never added
+        assert!(index.apply(&txs[6]).is_err());
+    }
+
+    #[test]
+    fn csv_field_quotes_delimiters() {
+        assert_eq!("plain", csv_field("plain"));
+        assert_eq!("\"Shoe Co, \"\"LLC\"\"\"", csv_field("Shoe Co, \"LLC\""));
+    }
+}
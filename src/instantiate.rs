@@ -1,17 +1,21 @@
 use crate::contract::{CRATE_NAME, PACKAGE_VERSION};
+use crate::denom::query_denom_metadata;
 use crate::error::contract_err;
+use crate::events::{ConfigUpdated, ContractEvent};
 use crate::msg::{InstantiateMsg, Validate};
-use crate::state::{config, config_read, State};
+use crate::state::{config, State};
 use crate::ContractError;
-use cosmwasm_std::{attr, entry_point, DepsMut, Env, MessageInfo, Response};
+use cosmwasm_std::{entry_point, DepsMut, Env, MessageInfo, Response};
 use cw2::set_contract_version;
-use provwasm_std::{Marker, MarkerType, ProvenanceMsg, ProvenanceQuerier, ProvenanceQuery};
+use provwasm_std::{
+    bind_name, Marker, MarkerType, NameBinding, ProvenanceMsg, ProvenanceQuerier, ProvenanceQuery,
+};
 
 /// Create the initial configuration state
 #[entry_point]
 pub fn instantiate(
     deps: DepsMut<ProvenanceQuery>,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
@@ -34,32 +38,63 @@ pub fn instantiate(
         return Err(ContractError::UnsupportedMarkerType);
     }
 
+    // cache the display unit so invoices can be billed and shown in it
+    let denom_metadata = query_denom_metadata(&deps.querier, &msg.denom)?;
+
     // create and store config state
     let contract_info = State {
         admin: info.sender.clone(),
         recipient: deps.api.addr_validate(&msg.recipient)?,
         denom: msg.denom.clone(),
         business_name: msg.business_name.clone(),
+        overpayment: msg.overpayment.clone().unwrap_or_default(),
+        financing_terms: None,
+        financiers: vec![],
+        require_acceptance: msg.require_acceptance.unwrap_or_default(),
+        signing_key: msg.signing_key.clone(),
+        attribute_name: msg.attribute_name.clone(),
+        payment_attributes: msg.payment_attributes.unwrap_or_default(),
+        required_attributes: msg.required_attributes.clone().unwrap_or_default(),
+        contract_name: msg.contract_name.clone(),
+        scope_specification_id: msg.scope_specification_id.clone(),
+        denom_metadata,
     };
     config(deps.storage).save(&contract_info)?;
 
+    // bind the attribute name so only this contract can write payment proofs under it
+    let mut response = Response::new();
+    if let Some(name) = &contract_info.attribute_name {
+        response = response.add_message(bind_name(
+            name,
+            env.contract.address.to_owned(),
+            NameBinding::Restricted,
+        )?);
+    }
+
+    // bind the contract name so customers can resolve the merchant's contract
+    if let Some(name) = &contract_info.contract_name {
+        response = response.add_message(bind_name(
+            name,
+            env.contract.address,
+            NameBinding::Restricted,
+        )?);
+    }
+
     set_contract_version(deps.storage, CRATE_NAME, PACKAGE_VERSION)?;
 
     // build response
-    Ok(Response::new().add_attributes(vec![
-        attr(
-            "contract_info",
-            format!("{:?}", config_read(deps.storage).load()?),
-        ),
-        attr("action", "init"),
-    ]))
+    Ok(response
+        .add_event(ConfigUpdated::new(&contract_info).to_event())
+        .add_attribute("action", "init"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::denom::tests::mock_dependencies_with_metadata;
+    use crate::state::{config_read, DenomMetadata, OverpaymentMode};
     use cosmwasm_std::testing::{mock_env, mock_info};
-    use cosmwasm_std::{from_binary, Addr, Binary};
+    use cosmwasm_std::{attr, from_binary, Addr, Binary};
     use provwasm_mocks::mock_dependencies;
 
     #[test]
@@ -75,6 +110,14 @@ mod tests {
             denom: denom.into(),
             recipient: recipient_address.to_string(),
             business_name: business_name.into(),
+            overpayment: None,
+            require_acceptance: None,
+            signing_key: None,
+            attribute_name: None,
+            payment_attributes: None,
+            required_attributes: None,
+            contract_name: None,
+            scope_specification_id: None,
         };
 
         let test_marker: Marker = setup_unrestricted_marker();
@@ -87,20 +130,33 @@ mod tests {
             Ok(init_response) => {
                 assert_eq!(init_response.messages.len(), 0);
 
-                assert_eq!(init_response.attributes.len(), 2);
+                assert_eq!(init_response.attributes.len(), 1);
+                assert_eq!(init_response.events.len(), 1);
 
                 let expected_state = State {
-                    admin: info.sender.into(),
+                    admin: info.sender,
                     denom: denom.into(),
                     recipient: recipient_address.to_owned(),
                     business_name: business_name.into(),
+                    overpayment: OverpaymentMode::Reject,
+                    financing_terms: None,
+                    financiers: vec![],
+                    require_acceptance: false,
+                    signing_key: None,
+                    attribute_name: None,
+                    payment_attributes: false,
+                    required_attributes: vec![],
+                    contract_name: None,
+                    scope_specification_id: None,
+                    denom_metadata: None,
                 };
 
                 assert_eq!(
-                    init_response.attributes[0],
-                    attr("contract_info", format!("{:?}", expected_state))
+                    ConfigUpdated::parse(&init_response.events[0]).unwrap(),
+                    ConfigUpdated::new(&expected_state)
                 );
-                assert_eq!(init_response.attributes[1], attr("action", "init"));
+                assert_eq!(init_response.attributes[0], attr("action", "init"));
+                assert_eq!(expected_state, config_read(&deps.storage).load().unwrap());
 
                 let version_info = cw2::get_contract_version(&deps.storage).unwrap();
 
@@ -111,6 +167,83 @@ mod tests {
         }
     }
 
+    #[test]
+    fn initialization_binds_attribute_name() {
+        let mut deps = mock_dependencies(&[]);
+        let info = mock_info("contract_admin", &[]);
+
+        let init_msg = InstantiateMsg {
+            denom: "unrestricted".into(),
+            recipient: "recipient".into(),
+            business_name: "please transfer me".into(),
+            overpayment: None,
+            require_acceptance: None,
+            signing_key: None,
+            attribute_name: Some("paid.transfer.pb".into()),
+            payment_attributes: Some(true),
+            required_attributes: None,
+            contract_name: None,
+            scope_specification_id: None,
+        };
+
+        deps.querier.with_markers(vec![setup_unrestricted_marker()]);
+
+        let init_response = instantiate(deps.as_mut(), mock_env(), info, init_msg).unwrap();
+
+        assert_eq!(init_response.messages.len(), 1);
+        assert_eq!(
+            init_response.messages[0].msg,
+            bind_name(
+                "paid.transfer.pb",
+                mock_env().contract.address,
+                NameBinding::Restricted
+            )
+            .unwrap()
+        );
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Some("paid.transfer.pb".into()), state.attribute_name);
+        assert!(state.payment_attributes);
+    }
+
+    #[test]
+    fn initialization_caches_denom_metadata() {
+        let mut deps = mock_dependencies_with_metadata("unrestricted", "restricted", 6);
+        deps.querier
+            .base
+            .with_markers(vec![setup_unrestricted_marker()]);
+
+        let init_msg = InstantiateMsg {
+            denom: "unrestricted".into(),
+            recipient: "recipient".into(),
+            business_name: "please transfer me".into(),
+            overpayment: None,
+            require_acceptance: None,
+            signing_key: None,
+            attribute_name: None,
+            payment_attributes: None,
+            required_attributes: None,
+            contract_name: None,
+            scope_specification_id: None,
+        };
+
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("contract_admin", &[]),
+            init_msg,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(DenomMetadata {
+                display: "restricted".into(),
+                decimals: 6,
+            }),
+            config_read(&deps.storage).load().unwrap().denom_metadata
+        );
+    }
+
     fn setup_unrestricted_marker() -> Marker {
         let marker_json = b"{
               \"address\": \"tp1l330sxue4suxz9dhc40e2pns0ymrytf8uz4squ\",
@@ -142,6 +275,6 @@ mod tests {
               \"supply_fixed\": false
             }";
 
-        return from_binary(&Binary::from(marker_json)).unwrap();
+        from_binary(&Binary::from(marker_json)).unwrap()
     }
 }
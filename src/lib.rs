@@ -1,7 +1,16 @@
+#[cfg(feature = "cli")]
+pub mod cli;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod contract;
+pub mod denom;
 mod error;
-mod instantiate;
-mod migrate;
+pub mod events;
+#[cfg(feature = "indexer")]
+pub mod indexer;
+pub mod instantiate;
+pub mod metadata;
+pub mod migrate;
 pub mod msg;
 pub mod state;
 
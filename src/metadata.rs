@@ -0,0 +1,209 @@
+use cosmwasm_std::{to_vec, Addr, Binary, CosmosMsg, Env, StdError, StdResult, Uint128};
+use provwasm_std::{
+    add_json_attribute, delete_attributes, write_scope, Party, PartyType, ProvenanceMsg, Scope,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::state::{Invoice, State};
+
+// Provenance metadata address prefixes
+const SCOPE_HRP: &str = "scope";
+const SCOPE_KEY_PREFIX: u8 = 0x00;
+
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32_GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+
+/// Where an invoice stands, as recorded on its metadata scope.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordStatus {
+    Open,
+    Paid,
+    Cancelled,
+    Expired,
+}
+
+/// The invoice record written to a scope's attributes, since the metadata module
+/// only accepts scope writes from contracts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InvoiceRecord {
+    pub invoice_id: String,
+    // SHA-256 of the invoice as stored by the contract
+    pub invoice_hash: Binary,
+    pub amount: Uint128,
+    // What is left to pay once credit notes are applied
+    pub amount_due: Uint128,
+    pub denom: String,
+    pub payer: Option<Addr>,
+    pub payee: Addr,
+    // Repaid ahead of the payee once the invoice is financed
+    pub financier: Option<Addr>,
+    pub status: RecordStatus,
+}
+
+impl InvoiceRecord {
+    pub fn new(state: &State, invoice: &Invoice, status: RecordStatus) -> StdResult<Self> {
+        Ok(InvoiceRecord {
+            invoice_id: invoice.id.to_owned(),
+            invoice_hash: Binary::from(Sha256::digest(&to_vec(invoice)?).as_slice()),
+            amount: invoice.amount,
+            amount_due: invoice.amount_due,
+            denom: state.denom.to_owned(),
+            payer: invoice.payer.to_owned(),
+            payee: invoice.payee(state).to_owned(),
+            financier: invoice
+                .financing
+                .as_ref()
+                .map(|financing| financing.financier.to_owned()),
+            status,
+        })
+    }
+}
+
+/// The bech32 scope address derived from an invoice id, so the scope can be found from
+/// the invoice alone.
+pub fn scope_id(invoice_id: &str) -> StdResult<String> {
+    let uuid = Uuid::parse_str(invoice_id)
+        .map_err(|_| StdError::generic_err(format!("invalid invoice id: {}", invoice_id)))?;
+
+    let mut data = vec![SCOPE_KEY_PREFIX];
+    data.extend_from_slice(uuid.as_bytes());
+    Ok(bech32_encode(SCOPE_HRP, &data))
+}
+
+/// Create the scope for a new invoice and write its opening record.
+pub fn create_invoice_scope(
+    env: &Env,
+    state: &State,
+    invoice: &Invoice,
+) -> StdResult<Vec<CosmosMsg<ProvenanceMsg>>> {
+    let (specification_id, scope_id) = match (&state.scope_specification_id, &invoice.scope_id) {
+        (Some(specification_id), Some(scope_id)) => (specification_id, scope_id),
+        _ => return Ok(vec![]),
+    };
+
+    let mut data_access = vec![invoice.payee(state).to_owned()];
+    data_access.extend(invoice.payer.to_owned());
+
+    let scope = Scope {
+        scope_id: scope_id.to_owned(),
+        specification_id: specification_id.to_owned(),
+        owners: vec![Party {
+            address: env.contract.address.to_owned(),
+            role: PartyType::Owner,
+        }],
+        data_access,
+        value_owner_address: invoice.payee(state).to_owned(),
+    };
+
+    let mut messages = vec![write_scope(scope, vec![env.contract.address.to_owned()])?];
+    messages.extend(write_record(state, invoice, RecordStatus::Open, false)?);
+    Ok(messages)
+}
+
+/// Replace the record on an invoice's scope, e.g. when it is paid, transferred or financed.
+pub fn update_invoice_record(
+    state: &State,
+    invoice: &Invoice,
+    status: RecordStatus,
+) -> StdResult<Vec<CosmosMsg<ProvenanceMsg>>> {
+    write_record(state, invoice, status, true)
+}
+
+fn write_record(
+    state: &State,
+    invoice: &Invoice,
+    status: RecordStatus,
+    replace: bool,
+) -> StdResult<Vec<CosmosMsg<ProvenanceMsg>>> {
+    let (name, scope_id) = match (&state.attribute_name, &invoice.scope_id) {
+        (Some(name), Some(scope_id)) => (name, scope_id),
+        _ => return Ok(vec![]),
+    };
+    let scope_address = Addr::unchecked(scope_id);
+
+    let mut messages = vec![];
+    if replace {
+        messages.push(delete_attributes(scope_address.to_owned(), name)?);
+    }
+    messages.push(add_json_attribute(
+        scope_address,
+        name,
+        &InvoiceRecord::new(state, invoice, status)?,
+    )?);
+    Ok(messages)
+}
+
+fn bech32_encode(hrp: &str, data: &[u8]) -> String {
+    // regroup 8-bit bytes into 5-bit words, padding the last one
+    let mut words = vec![];
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for byte in data {
+        acc = (acc << 8) | u32::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            words.push(((acc >> bits) & 0x1f) as u8);
+        }
+    }
+    if bits > 0 {
+        words.push(((acc << (5 - bits)) & 0x1f) as u8);
+    }
+
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 0x1f));
+    values.extend(&words);
+    values.extend([0u8; 6]);
+    let checksum = bech32_polymod(&values) ^ 1;
+    words.extend((0..6).map(|i| ((checksum >> (5 * (5 - i))) & 0x1f) as u8));
+
+    let mut encoded = String::from(hrp);
+    encoded.push('1');
+    encoded.extend(
+        words
+            .iter()
+            .map(|word| BECH32_CHARSET[*word as usize] as char),
+    );
+    encoded
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    values.iter().fold(1u32, |chk, value| {
+        let top = chk >> 25;
+        let chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(*value);
+        BECH32_GENERATOR
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| (top >> i) & 1 == 1)
+            .fold(chk, |chk, (_, generator)| chk ^ generator)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_id_matches_provenance_encoding() {
+        // example from the Provenance metadata address specification
+        assert_eq!(
+            "scope1qzge0zaztu65tx5x5llv5xc9ztsqxlkwel",
+            scope_id("91978ba2-5f35-459a-86a7-feca1b0512e0").unwrap()
+        );
+    }
+
+    #[test]
+    fn scope_id_invalid_invoice_id() {
+        assert!(scope_id("not-a-uuid").is_err());
+    }
+}
@@ -1,43 +1,241 @@
-use cosmwasm_std::{entry_point, DepsMut, Env, Response};
-use cw2::set_contract_version;
+use cosmwasm_std::{attr, entry_point, DepsMut, Env, Order, Response, StdResult};
+use cw2::{get_contract_version, set_contract_version};
 use provwasm_std::ProvenanceQuery;
+use semver::Version;
 
 use crate::contract::{CRATE_NAME, PACKAGE_VERSION};
+use crate::denom::query_denom_metadata;
 use crate::error::ContractError;
+use crate::events::{ConfigUpdated, ContractEvent};
 use crate::msg::MigrateMsg;
+use crate::state::{
+    config, config_read, get_invoice_storage, get_invoice_storage_read, update_stats, Invoice,
+};
+
+/// A state upgrade that must run when migrating from a version older than `version`.
+pub struct MigrationStep {
+    pub version: &'static str,
+    pub migrate: fn(DepsMut<ProvenanceQuery>, &Env, &MigrateMsg) -> Result<(), ContractError>,
+}
+
+/// Registered state upgrades, in ascending version order.
+pub const MIGRATIONS: &[MigrationStep] = &[
+    MigrationStep {
+        version: "0.2.0",
+        migrate: rebuild_invoice_stats,
+    },
+    MigrationStep {
+        version: "0.3.0",
+        migrate: set_overpayment_mode,
+    },
+    MigrationStep {
+        version: "0.4.0",
+        migrate: set_invoice_amount_due,
+    },
+    MigrationStep {
+        version: "0.5.0",
+        migrate: set_acceptance_requirement,
+    },
+    MigrationStep {
+        version: "0.6.0",
+        migrate: set_denom_metadata,
+    },
+];
 
 #[entry_point]
 pub fn migrate(
-    deps: DepsMut<ProvenanceQuery>,
-    _env: Env,
-    _msg: MigrateMsg,
+    mut deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    msg: MigrateMsg,
 ) -> Result<Response, ContractError> {
+    let stored = get_contract_version(deps.storage)?;
+
+    // only allow migrations from this contract
+    if stored.contract != CRATE_NAME {
+        return Err(ContractError::InvalidContractName {
+            contract_name: stored.contract,
+        });
+    }
+
+    // refuse downgrades
+    let source_version = parse_version(&stored.version)?;
+    let target_version = parse_version(PACKAGE_VERSION)?;
+    if source_version > target_version {
+        return Err(unsupported_upgrade(&stored.version));
+    }
+
+    run_migrations(
+        deps.branch(),
+        &env,
+        &msg,
+        &source_version,
+        &target_version,
+        MIGRATIONS,
+    )?;
+
     set_contract_version(deps.storage, CRATE_NAME, PACKAGE_VERSION)?;
-    Ok(Response::default())
+
+    // migration steps may have changed the configuration
+    let state = config_read(deps.storage).load()?;
+
+    Ok(Response::new()
+        .add_event(ConfigUpdated::new(&state).to_event())
+        .add_attributes(vec![
+            attr("action", "migrate"),
+            attr("source_version", stored.version),
+            attr("target_version", PACKAGE_VERSION),
+        ]))
+}
+
+/// Apply every step whose version is newer than the source and no newer than the target.
+fn run_migrations(
+    mut deps: DepsMut<ProvenanceQuery>,
+    env: &Env,
+    msg: &MigrateMsg,
+    source_version: &Version,
+    target_version: &Version,
+    steps: &[MigrationStep],
+) -> Result<(), ContractError> {
+    for step in steps {
+        let step_version = parse_version(step.version)?;
+        if step_version > *source_version && step_version <= *target_version {
+            (step.migrate)(deps.branch(), env, msg)?;
+        }
+    }
+    Ok(())
+}
+
+/// Seed the open invoice totals from invoices stored before stats were tracked.
+fn rebuild_invoice_stats(
+    deps: DepsMut<ProvenanceQuery>,
+    _env: &Env,
+    _msg: &MigrateMsg,
+) -> Result<(), ContractError> {
+    let state = config_read(deps.storage).load()?;
+
+    let invoices = get_invoice_storage_read(deps.storage)
+        .range(None, None, Order::Ascending)
+        .map(|item| item.map(|(_, invoice)| invoice))
+        .collect::<StdResult<Vec<Invoice>>>()?;
+
+    for invoice in invoices {
+        update_stats(deps.storage, &state.denom, |stats| {
+            stats.open.add(invoice.amount)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Configure overpayment handling, which defaults to rejecting mismatched funds.
+fn set_overpayment_mode(
+    deps: DepsMut<ProvenanceQuery>,
+    _env: &Env,
+    msg: &MigrateMsg,
+) -> Result<(), ContractError> {
+    config(deps.storage).update(|mut state| -> Result<_, ContractError> {
+        state.overpayment = msg.overpayment.clone().unwrap_or_default();
+        Ok(state)
+    })?;
+    Ok(())
+}
+
+/// Start the amount due of invoices stored before credit notes at their full amount.
+fn set_invoice_amount_due(
+    deps: DepsMut<ProvenanceQuery>,
+    _env: &Env,
+    _msg: &MigrateMsg,
+) -> Result<(), ContractError> {
+    let invoices = get_invoice_storage_read(deps.storage)
+        .range(None, None, Order::Ascending)
+        .map(|item| item.map(|(_, invoice)| invoice))
+        .collect::<StdResult<Vec<Invoice>>>()?;
+
+    let mut invoice_storage = get_invoice_storage(deps.storage);
+    for mut invoice in invoices {
+        invoice.amount_due = invoice.amount;
+        invoice_storage.save(invoice.id.as_bytes(), &invoice)?;
+    }
+
+    Ok(())
+}
+
+/// Configure payer acceptance, which defaults to not being required.
+fn set_acceptance_requirement(
+    deps: DepsMut<ProvenanceQuery>,
+    _env: &Env,
+    msg: &MigrateMsg,
+) -> Result<(), ContractError> {
+    config(deps.storage).update(|mut state| -> Result<_, ContractError> {
+        state.require_acceptance = msg.require_acceptance.unwrap_or_default();
+        Ok(state)
+    })?;
+    Ok(())
+}
+
+/// Cache the display unit of the denom from the chain's bank metadata.
+fn set_denom_metadata(
+    deps: DepsMut<ProvenanceQuery>,
+    _env: &Env,
+    _msg: &MigrateMsg,
+) -> Result<(), ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    state.denom_metadata = query_denom_metadata(&deps.querier, &state.denom)?;
+    config(deps.storage).save(&state)?;
+    Ok(())
+}
+
+fn parse_version(version: &str) -> Result<Version, ContractError> {
+    Version::parse(version).map_err(|_| unsupported_upgrade(version))
+}
+
+fn unsupported_upgrade(source_version: &str) -> ContractError {
+    ContractError::UnsupportedUpgrade {
+        source_version: source_version.into(),
+        target_version: PACKAGE_VERSION.into(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use cosmwasm_std::testing::mock_env;
+    use cosmwasm_std::{Addr, Storage, Uint128};
     use provwasm_mocks::mock_dependencies;
 
+    use crate::state::{get_stats_storage_read, OverpaymentMode, State, Tally};
+
     use super::*;
+    use crate::contract::tests::test_state;
 
     #[test]
     fn migrate_test() {
         let mut deps = mock_dependencies(&[]);
 
-        let result = cw2::set_contract_version(deps.as_mut().storage, CRATE_NAME, "v0");
+        let result = cw2::set_contract_version(deps.as_mut().storage, CRATE_NAME, "0.0.1");
         match result {
             Ok(..) => {}
             Err(error) => panic!("unexpected error: {:?}", error),
         }
+        setup_test_base(&mut deps.storage);
 
-        let migrate_response = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
+        let migrate_response = migrate(deps.as_mut(), mock_env(), MigrateMsg::default());
 
         // verify migrate response
         match migrate_response {
-            Ok(..) => {
+            Ok(response) => {
+                assert_eq!(response.attributes[0], attr("action", "migrate"));
+                assert_eq!(response.attributes[1], attr("source_version", "0.0.1"));
+                assert_eq!(
+                    response.attributes[2],
+                    attr("target_version", PACKAGE_VERSION)
+                );
+                // the migrated configuration is announced to indexers
+                let config = ConfigUpdated::parse(&response.events[0]).unwrap();
+                assert_eq!(
+                    config_read(&deps.storage).load().unwrap().denom,
+                    config.denom
+                );
+
                 let version_info = cw2::get_contract_version(&deps.storage).unwrap();
 
                 assert_eq!(PACKAGE_VERSION, version_info.version);
@@ -46,4 +244,215 @@ mod tests {
             error => panic!("failed to initialize: {:?}", error),
         }
     }
+
+    #[test]
+    fn migrate_downgrade_error() {
+        let mut deps = mock_dependencies(&[]);
+
+        cw2::set_contract_version(deps.as_mut().storage, CRATE_NAME, "999.0.0").unwrap();
+
+        let migrate_response = migrate(deps.as_mut(), mock_env(), MigrateMsg::default());
+
+        match migrate_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::UnsupportedUpgrade {
+                    source_version,
+                    target_version,
+                } => {
+                    assert_eq!("999.0.0", source_version);
+                    assert_eq!(PACKAGE_VERSION, target_version);
+                }
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+
+        // stored version is untouched
+        let version_info = cw2::get_contract_version(&deps.storage).unwrap();
+        assert_eq!("999.0.0", version_info.version);
+    }
+
+    #[test]
+    fn migrate_invalid_contract_name_error() {
+        let mut deps = mock_dependencies(&[]);
+
+        cw2::set_contract_version(deps.as_mut().storage, "other_contract", "0.0.1").unwrap();
+
+        let migrate_response = migrate(deps.as_mut(), mock_env(), MigrateMsg::default());
+
+        match migrate_response {
+            Ok(..) => panic!("expected error, but ok"),
+            Err(error) => match error {
+                ContractError::InvalidContractName { contract_name } => {
+                    assert_eq!("other_contract", contract_name);
+                }
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+    }
+
+    #[test]
+    fn migrate_rebuilds_invoice_stats() {
+        let mut deps = mock_dependencies(&[]);
+
+        cw2::set_contract_version(deps.as_mut().storage, CRATE_NAME, "0.1.0").unwrap();
+        setup_test_base(&mut deps.storage);
+
+        let mut invoice_storage = get_invoice_storage(&mut deps.storage);
+        for (id, amount) in [
+            ("63069195-bc51-41bd-80d7-0ab84b98e283", 5),
+            ("a3c36b1f-e7f5-4d3b-9d34-2dd1e0bfb2c5", 7),
+        ] {
+            invoice_storage
+                .save(
+                    id.as_bytes(),
+                    &Invoice {
+                        id: id.into(),
+                        amount: Uint128::new(amount),
+                        amount_due: Uint128::new(amount),
+                        description: None,
+                        due_date: None,
+                        expires_at: None,
+                        payer: None,
+                        payee: None,
+                        financing: None,
+                        credit_notes: vec![],
+                        acceptance: None,
+                        scope_id: None,
+                    },
+                )
+                .unwrap();
+        }
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+
+        let stats = get_stats_storage_read(&deps.storage)
+            .load(b"testdenom")
+            .unwrap();
+        assert_eq!(2, stats.open.count);
+        assert_eq!(Uint128::new(12), stats.open.amount);
+        assert_eq!(Tally::default(), stats.paid);
+        assert_eq!(Tally::default(), stats.cancelled);
+    }
+
+    #[test]
+    fn migrate_sets_overpayment_mode() {
+        let mut deps = mock_dependencies(&[]);
+
+        cw2::set_contract_version(deps.as_mut().storage, CRATE_NAME, "0.2.0").unwrap();
+        setup_test_base(&mut deps.storage);
+
+        let msg = MigrateMsg {
+            overpayment: Some(OverpaymentMode::Credit),
+            ..MigrateMsg::default()
+        };
+        migrate(deps.as_mut(), mock_env(), msg).unwrap();
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(OverpaymentMode::Credit, state.overpayment);
+    }
+
+    #[test]
+    fn migrate_sets_acceptance_requirement() {
+        let mut deps = mock_dependencies(&[]);
+
+        cw2::set_contract_version(deps.as_mut().storage, CRATE_NAME, "0.4.0").unwrap();
+        setup_test_base(&mut deps.storage);
+
+        let msg = MigrateMsg {
+            require_acceptance: Some(true),
+            ..MigrateMsg::default()
+        };
+        migrate(deps.as_mut(), mock_env(), msg).unwrap();
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert!(state.require_acceptance);
+    }
+
+    #[test]
+    fn migrate_sets_invoice_amount_due() {
+        let mut deps = mock_dependencies(&[]);
+
+        cw2::set_contract_version(deps.as_mut().storage, CRATE_NAME, "0.3.0").unwrap();
+        setup_test_base(&mut deps.storage);
+
+        let id = "63069195-bc51-41bd-80d7-0ab84b98e283";
+        get_invoice_storage(&mut deps.storage)
+            .save(
+                id.as_bytes(),
+                &Invoice {
+                    id: id.into(),
+                    amount: Uint128::new(5),
+                    ..Invoice::default()
+                },
+            )
+            .unwrap();
+
+        migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+
+        let invoice = get_invoice_storage_read(&deps.storage)
+            .load(id.as_bytes())
+            .unwrap();
+        assert_eq!(Uint128::new(5), invoice.amount_due);
+    }
+
+    #[test]
+    fn run_migrations_applies_steps_in_range() {
+        fn mark(storage: &mut dyn Storage, version: &str) {
+            let mut applied = storage.get(b"applied").unwrap_or_default();
+            applied.extend_from_slice(version.as_bytes());
+            applied.push(b';');
+            storage.set(b"applied", &applied);
+        }
+
+        let steps = [
+            MigrationStep {
+                version: "0.1.0",
+                migrate: |deps, _, _| {
+                    mark(deps.storage, "0.1.0");
+                    Ok(())
+                },
+            },
+            MigrationStep {
+                version: "0.2.0",
+                migrate: |deps, _, _| {
+                    mark(deps.storage, "0.2.0");
+                    Ok(())
+                },
+            },
+            MigrationStep {
+                version: "0.3.0",
+                migrate: |deps, _, _| {
+                    mark(deps.storage, "0.3.0");
+                    Ok(())
+                },
+            },
+        ];
+
+        let mut deps = mock_dependencies(&[]);
+
+        run_migrations(
+            deps.as_mut(),
+            &mock_env(),
+            &MigrateMsg::default(),
+            &Version::parse("0.1.0").unwrap(),
+            &Version::parse("0.2.0").unwrap(),
+            &steps,
+        )
+        .unwrap();
+
+        assert_eq!(b"0.2.0;".to_vec(), deps.storage.get(b"applied").unwrap());
+    }
+
+    fn setup_test_base(storage: &mut dyn Storage) {
+        config(storage)
+            .save(&State {
+                admin: Addr::unchecked("admin"),
+                recipient: Addr::unchecked("recipient"),
+                denom: "testdenom".into(),
+                business_name: "company".into(),
+                ..test_state()
+            })
+            .unwrap();
+    }
 }
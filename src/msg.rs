@@ -1,7 +1,15 @@
+use crate::denom::is_decimal;
 use crate::error::ContractError;
-use cosmwasm_std::Uint128;
+use crate::state::{
+    DenomMetadata, DenylistEntry, Financing, FinancingTerms, Invoice, InvoiceRevision,
+    InvoiceStats, LedgerEntry, OverpaymentMode, Payment, PaymentReceipt, ReceivableTransfer, State,
+};
+use cosmwasm_std::{to_vec, Addr, Binary, Coin, StdResult, Timestamp, Uint128};
+use cw2::ContractVersion;
+use provwasm_std::Name;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -9,6 +17,14 @@ pub struct InstantiateMsg {
     pub denom: String,
     pub recipient: String,
     pub business_name: String,
+    pub overpayment: Option<OverpaymentMode>,
+    pub require_acceptance: Option<bool>,
+    pub signing_key: Option<Binary>,
+    pub attribute_name: Option<String>,
+    pub payment_attributes: Option<bool>,
+    pub required_attributes: Option<Vec<String>>,
+    pub contract_name: Option<String>,
+    pub scope_specification_id: Option<String>,
 }
 
 /// Simple validation of InstantiateMsg data
@@ -34,6 +50,38 @@ impl Validate for InstantiateMsg {
             invalid_fields.push("business_name");
         }
 
+        if !self.signing_key.iter().all(is_public_key) {
+            invalid_fields.push("signing_key");
+        }
+
+        if let Some(attributes) = &self.required_attributes {
+            if attributes.iter().any(|name| name.is_empty()) {
+                invalid_fields.push("required_attributes");
+            }
+        }
+
+        if self.contract_name.as_deref() == Some("") {
+            invalid_fields.push("contract_name");
+        }
+
+        if self.scope_specification_id.as_deref() == Some("") {
+            invalid_fields.push("scope_specification_id");
+        }
+
+        match &self.attribute_name {
+            Some(name) if name.is_empty() => {
+                invalid_fields.push("attribute_name");
+            }
+            None if self.payment_attributes == Some(true)
+                || self.scope_specification_id.is_some() =>
+            {
+                invalid_fields.push("attribute_name");
+            }
+            _ => {
+                // noop
+            }
+        }
+
         match invalid_fields.len() {
             0 => Ok(()),
             _ => Err(ContractError::InvalidFields {
@@ -43,24 +91,110 @@ impl Validate for InstantiateMsg {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+/// Optional parameters consumed by migration steps when new configuration fields are
+/// introduced. Omitted fields fall back to each step's default.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
-pub struct MigrateMsg {}
+pub struct MigrateMsg {
+    // Overpayment handling for contracts upgraded from before 0.3.0
+    pub overpayment: Option<OverpaymentMode>,
+    // Whether payers must accept invoices, for contracts upgraded from before 0.5.0
+    pub require_acceptance: Option<bool>,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
     AddInvoice {
         id: String,
+        // In base units, omitted when `display_amount` is given
+        #[serde(default)]
         amount: Uint128,
         description: Option<String>,
+        due_date: Option<Timestamp>,
+        expires_at: Option<Timestamp>,
+        payer: Option<String>,
+        // Decimal amount in the denom's display unit, such as "1.5"
+        #[serde(default)]
+        display_amount: Option<String>,
     },
     PayInvoice {
         id: String,
+        payment_ref: Option<String>,
+    },
+    PayWithSignedInvoice {
+        invoice: SignedInvoice,
+        signature: Binary,
+    },
+    UpdateSigningKey {
+        public_key: Option<Binary>,
+    },
+    UpdatePaymentAttributes {
+        enabled: bool,
+    },
+    UpdateRequiredAttributes {
+        attributes: Vec<String>,
+    },
+    UpdateScopeSpecification {
+        specification_id: Option<String>,
+    },
+    UpdateConfig {
+        recipient: Option<String>,
+        business_name: Option<String>,
+        contract_name: Option<ContractNameUpdate>,
+        // Binds the attribute name for contracts instantiated without one
+        #[serde(default)]
+        attribute_name: Option<String>,
+    },
+    AddToDenylist {
+        address: String,
+    },
+    RemoveFromDenylist {
+        address: String,
     },
     CancelInvoice {
         id: String,
     },
+    AmendInvoice {
+        id: String,
+        amount: Uint128,
+        description: Option<String>,
+        due_date: Option<Timestamp>,
+    },
+    IssueCreditNote {
+        invoice_id: String,
+        amount: Uint128,
+        reason: String,
+    },
+    AcceptInvoice {
+        id: String,
+    },
+    Reject {
+        id: String,
+        reason: String,
+    },
+    PruneExpired {
+        limit: Option<u32>,
+    },
+    WithdrawCredit {},
+    Deposit {},
+    Withdraw {
+        amount: Coin,
+    },
+    SettleFromDeposit {
+        id: String,
+    },
+    TransferReceivable {
+        id: String,
+        new_payee: String,
+    },
+    FundInvoice {
+        id: String,
+    },
+    UpdateFinancing {
+        terms: Option<FinancingTerms>,
+        financiers: Vec<String>,
+    },
 }
 
 impl Validate for ExecuteMsg {
@@ -84,6 +218,139 @@ impl Validate for ExecuteMsg {
                 id,
                 amount,
                 description,
+                payer,
+                display_amount,
+                ..
+            } => {
+                if Uuid::parse_str(id).is_err() {
+                    invalid_fields.push("id");
+                }
+
+                // the amount is given in exactly one unit
+                match display_amount {
+                    Some(display_amount) => {
+                        if !amount.is_zero() {
+                            invalid_fields.push("amount");
+                        }
+
+                        if !is_decimal(display_amount)
+                            || display_amount.bytes().all(|b| b == b'0' || b == b'.')
+                        {
+                            invalid_fields.push("display_amount");
+                        }
+                    }
+                    None => {
+                        if amount.lt(&Uint128::new(1)) {
+                            invalid_fields.push("amount");
+                        }
+                    }
+                }
+
+                match description {
+                    Some(d) if d.is_empty() || d.len() > 64 => {
+                        invalid_fields.push("description");
+                    }
+                    _ => {
+                        // noop
+                    }
+                }
+
+                if let Some(payer) = payer {
+                    if payer.is_empty() {
+                        invalid_fields.push("payer");
+                    }
+                }
+            }
+            ExecuteMsg::PayInvoice { id, payment_ref } => {
+                if Uuid::parse_str(id).is_err() {
+                    invalid_fields.push("id");
+                }
+
+                match payment_ref {
+                    Some(r) if r.is_empty() || r.len() > 64 => {
+                        invalid_fields.push("payment_ref");
+                    }
+                    _ => {
+                        // noop
+                    }
+                }
+            }
+            ExecuteMsg::PayWithSignedInvoice { invoice, signature } => {
+                if Uuid::parse_str(&invoice.id).is_err() {
+                    invalid_fields.push("invoice.id");
+                }
+
+                if invoice.amount.is_zero() {
+                    invalid_fields.push("invoice.amount");
+                }
+
+                if invoice.denom.is_empty() {
+                    invalid_fields.push("invoice.denom");
+                }
+
+                if invoice.payer.is_empty() {
+                    invalid_fields.push("invoice.payer");
+                }
+
+                if signature.len() != 64 {
+                    invalid_fields.push("signature");
+                }
+            }
+            ExecuteMsg::UpdateSigningKey { public_key } => {
+                if !public_key.iter().all(is_public_key) {
+                    invalid_fields.push("public_key");
+                }
+            }
+            ExecuteMsg::UpdatePaymentAttributes { .. } => {}
+            ExecuteMsg::UpdateScopeSpecification { specification_id } => {
+                if specification_id.as_deref() == Some("") {
+                    invalid_fields.push("specification_id");
+                }
+            }
+            ExecuteMsg::UpdateConfig {
+                recipient,
+                business_name,
+                contract_name,
+                attribute_name,
+            } => {
+                if let Some(ContractNameUpdate::Bind { name }) = contract_name {
+                    if name.is_empty() {
+                        invalid_fields.push("contract_name");
+                    }
+                }
+
+                if attribute_name.as_deref() == Some("") {
+                    invalid_fields.push("attribute_name");
+                }
+
+                if recipient.as_deref() == Some("") {
+                    invalid_fields.push("recipient");
+                }
+
+                if business_name.as_deref() == Some("") {
+                    invalid_fields.push("business_name");
+                }
+            }
+            ExecuteMsg::AddToDenylist { address } | ExecuteMsg::RemoveFromDenylist { address } => {
+                if address.is_empty() {
+                    invalid_fields.push("address");
+                }
+            }
+            ExecuteMsg::UpdateRequiredAttributes { attributes } => {
+                if attributes.iter().any(|name| name.is_empty()) {
+                    invalid_fields.push("attributes");
+                }
+            }
+            ExecuteMsg::CancelInvoice { id } => {
+                if Uuid::parse_str(id).is_err() {
+                    invalid_fields.push("id");
+                }
+            }
+            ExecuteMsg::AmendInvoice {
+                id,
+                amount,
+                description,
+                ..
             } => {
                 if Uuid::parse_str(id).is_err() {
                     invalid_fields.push("id");
@@ -94,25 +361,86 @@ impl Validate for ExecuteMsg {
                 }
 
                 match description {
-                    Some(d) => {
-                        if d.is_empty() || d.len() > 64 {
-                            invalid_fields.push("description");
-                        }
+                    Some(d) if d.is_empty() || d.len() > 64 => {
+                        invalid_fields.push("description");
                     }
-                    None => {
+                    _ => {
                         // noop
                     }
                 }
             }
-            ExecuteMsg::PayInvoice { id } => {
+            ExecuteMsg::IssueCreditNote {
+                invoice_id,
+                amount,
+                reason,
+            } => {
+                if Uuid::parse_str(invoice_id).is_err() {
+                    invalid_fields.push("invoice_id");
+                }
+
+                if amount.is_zero() {
+                    invalid_fields.push("amount");
+                }
+
+                if reason.is_empty() || reason.len() > 64 {
+                    invalid_fields.push("reason");
+                }
+            }
+            ExecuteMsg::AcceptInvoice { id } => {
                 if Uuid::parse_str(id).is_err() {
                     invalid_fields.push("id");
                 }
             }
-            ExecuteMsg::CancelInvoice { id } => {
+            ExecuteMsg::Reject { id, reason } => {
                 if Uuid::parse_str(id).is_err() {
                     invalid_fields.push("id");
                 }
+
+                if reason.is_empty() || reason.len() > 64 {
+                    invalid_fields.push("reason");
+                }
+            }
+            ExecuteMsg::PruneExpired { limit } => {
+                if limit == &Some(0) {
+                    invalid_fields.push("limit");
+                }
+            }
+            ExecuteMsg::WithdrawCredit {} => {}
+            ExecuteMsg::Deposit {} => {}
+            ExecuteMsg::Withdraw { amount } => {
+                if amount.denom.is_empty() || amount.amount.is_zero() {
+                    invalid_fields.push("amount");
+                }
+            }
+            ExecuteMsg::SettleFromDeposit { id } => {
+                if Uuid::parse_str(id).is_err() {
+                    invalid_fields.push("id");
+                }
+            }
+            ExecuteMsg::FundInvoice { id } => {
+                if Uuid::parse_str(id).is_err() {
+                    invalid_fields.push("id");
+                }
+            }
+            ExecuteMsg::UpdateFinancing { terms, financiers } => {
+                if let Some(terms) = terms {
+                    if !terms.is_valid() {
+                        invalid_fields.push("terms");
+                    }
+                }
+
+                if financiers.iter().any(|financier| financier.is_empty()) {
+                    invalid_fields.push("financiers");
+                }
+            }
+            ExecuteMsg::TransferReceivable { id, new_payee } => {
+                if Uuid::parse_str(id).is_err() {
+                    invalid_fields.push("id");
+                }
+
+                if new_payee.is_empty() {
+                    invalid_fields.push("new_payee");
+                }
             }
         }
 
@@ -128,9 +456,52 @@ impl Validate for ExecuteMsg {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    GetInvoice { id: String },
+    GetInvoice {
+        id: String,
+    },
     GetContractInfo {},
+    GetContractName {},
     GetVersionInfo {},
+    GetStats {},
+    GetPayerStatement {
+        payer: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetCredit {
+        address: String,
+    },
+    GetDeposit {
+        address: String,
+    },
+    GetReceivable {
+        id: String,
+    },
+    GetInvoiceHistory {
+        id: String,
+    },
+    GetPayment {
+        invoice_id: String,
+        payment_ref: String,
+    },
+    GetReceipt {
+        invoice_id: String,
+        sequence: u64,
+    },
+    GetDenylist {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    ListReceipts {
+        invoice_id: Option<String>,
+        payer: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    ListInvoices {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 impl Validate for QueryMsg {
@@ -149,13 +520,92 @@ impl Validate for QueryMsg {
         let mut invalid_fields: Vec<&str> = vec![];
 
         match self {
-            QueryMsg::GetInvoice { id } => {
+            QueryMsg::GetInvoice { id }
+            | QueryMsg::GetReceivable { id }
+            | QueryMsg::GetInvoiceHistory { id } => {
                 if Uuid::parse_str(id).is_err() {
                     invalid_fields.push("id");
                 }
             }
+            QueryMsg::GetPayment {
+                invoice_id,
+                payment_ref,
+            } => {
+                if Uuid::parse_str(invoice_id).is_err() {
+                    invalid_fields.push("invoice_id");
+                }
+
+                if payment_ref.is_empty() {
+                    invalid_fields.push("payment_ref");
+                }
+            }
+            QueryMsg::GetReceipt { invoice_id, .. } => {
+                if Uuid::parse_str(invoice_id).is_err() {
+                    invalid_fields.push("invoice_id");
+                }
+            }
+            QueryMsg::ListReceipts {
+                invoice_id,
+                payer,
+                limit,
+                ..
+            } => {
+                // receipts are listed by exactly one of invoice or payer
+                match (invoice_id, payer) {
+                    (Some(invoice_id), None) => {
+                        if Uuid::parse_str(invoice_id).is_err() {
+                            invalid_fields.push("invoice_id");
+                        }
+                    }
+                    (None, Some(payer)) => {
+                        if payer.is_empty() {
+                            invalid_fields.push("payer");
+                        }
+                    }
+                    _ => {
+                        invalid_fields.push("invoice_id");
+                        invalid_fields.push("payer");
+                    }
+                }
+
+                if limit == &Some(0) {
+                    invalid_fields.push("limit");
+                }
+            }
+            QueryMsg::GetDenylist { limit, .. } => {
+                if limit == &Some(0) {
+                    invalid_fields.push("limit");
+                }
+            }
+            QueryMsg::ListInvoices { start_after, limit } => {
+                if let Some(start_after) = start_after {
+                    if Uuid::parse_str(start_after).is_err() {
+                        invalid_fields.push("start_after");
+                    }
+                }
+
+                if limit == &Some(0) {
+                    invalid_fields.push("limit");
+                }
+            }
             QueryMsg::GetContractInfo {} => {}
+            QueryMsg::GetContractName {} => {}
             QueryMsg::GetVersionInfo {} => {}
+            QueryMsg::GetStats {} => {}
+            QueryMsg::GetCredit { address } | QueryMsg::GetDeposit { address } => {
+                if address.is_empty() {
+                    invalid_fields.push("address");
+                }
+            }
+            QueryMsg::GetPayerStatement { payer, limit, .. } => {
+                if payer.is_empty() {
+                    invalid_fields.push("payer");
+                }
+
+                if limit == &Some(0) {
+                    invalid_fields.push("limit");
+                }
+            }
         }
 
         match invalid_fields.len() {
@@ -167,6 +617,218 @@ impl Validate for QueryMsg {
     }
 }
 
+/// A page of a payer's ledger along with their current outstanding balance.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PayerStatement {
+    pub payer: Addr,
+    pub entries: Vec<LedgerEntry>,
+    pub balance: Uint128,
+}
+
+/// Changes the Provenance name bound to the contract address.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractNameUpdate {
+    /// Bind `name`, replacing any name bound before
+    Bind { name: String },
+    /// Unbind the current name
+    Unbind {},
+}
+
+/// The Provenance name bound to the contract, resolved through the name module.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractName {
+    pub name: Option<Name>,
+}
+
+/// A page of denylisted addresses, in address order.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Denylist {
+    pub entries: Vec<DenylistEntry>,
+}
+
+/// A page of open invoices, in id order.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InvoiceList {
+    pub invoices: Vec<Invoice>,
+}
+
+/// A page of payment receipts, in the order they were issued.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReceiptList {
+    pub receipts: Vec<PaymentReceipt>,
+}
+
+/// The current holder of an invoice receivable and every transfer that led to them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReceivableOwnership {
+    pub id: String,
+    pub payee: Addr,
+    pub transfers: Vec<ReceivableTransfer>,
+    // Repaid ahead of the payee, when the invoice was financed
+    pub financing: Option<Financing>,
+}
+
+/// An invoice the merchant signs off-chain so the payer can settle it without a prior
+/// AddInvoice.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignedInvoice {
+    pub id: String,
+    pub amount: Uint128,
+    pub denom: String,
+    pub expires_at: Timestamp,
+    pub payer: String,
+}
+
+/// Domain separator at the start of every signed invoice message.
+pub const SIGNED_INVOICE_PREFIX: &[u8] = b"invoice.signed_invoice.v1";
+
+impl SignedInvoice {
+    /// The message signed by the merchant, bound to one contract on one chain:
+    ///
+    /// ```text
+    /// "invoice.signed_invoice.v1"
+    /// u32 big-endian length of chain_id  || chain_id
+    /// u32 big-endian length of contract  || contract (bech32 address)
+    /// JSON encoding of the invoice
+    /// ```
+    pub fn signing_bytes(&self, chain_id: &str, contract: &Addr) -> StdResult<Vec<u8>> {
+        let mut message = SIGNED_INVOICE_PREFIX.to_vec();
+        for part in &[chain_id, contract.as_str()] {
+            message.extend_from_slice(&(part.len() as u32).to_be_bytes());
+            message.extend_from_slice(part.as_bytes());
+        }
+        message.extend(to_vec(self)?);
+        Ok(message)
+    }
+
+    /// The digest the signature is checked against: sha256 of [`SignedInvoice::signing_bytes`].
+    pub fn message_hash(&self, chain_id: &str, contract: &Addr) -> StdResult<Vec<u8>> {
+        Ok(Sha256::digest(&self.signing_bytes(chain_id, contract)?).to_vec())
+    }
+}
+
+/// The current terms of an invoice, whether open, paid or expired, and its prior terms, oldest
+/// first.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InvoiceHistory {
+    pub id: String,
+    // Unset for invoices paid before paid invoices were kept
+    pub current: Option<Invoice>,
+    pub revisions: Vec<InvoiceRevision>,
+}
+
+/// Response to `GetInvoice`, with the amounts also shown in the denom's display unit when its
+/// metadata is known.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InvoiceResponse {
+    pub invoice: Invoice,
+    pub display: Option<DisplayAmounts>,
+}
+
+/// Invoice amounts in a denom's display unit, as decimal strings.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DisplayAmounts {
+    pub denom: String,
+    pub amount: String,
+    pub amount_due: String,
+}
+
+/// Response to `GetContractInfo`: the contract configuration.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractInfoResponse {
+    pub admin: Addr,
+    pub recipient: Addr,
+    pub denom: String,
+    pub business_name: String,
+    pub overpayment: OverpaymentMode,
+    pub financing_terms: Option<FinancingTerms>,
+    pub financiers: Vec<Addr>,
+    pub require_acceptance: bool,
+    pub signing_key: Option<Binary>,
+    pub attribute_name: Option<String>,
+    pub payment_attributes: bool,
+    pub required_attributes: Vec<String>,
+    pub contract_name: Option<String>,
+    pub scope_specification_id: Option<String>,
+    pub denom_metadata: Option<DenomMetadata>,
+}
+
+impl From<State> for ContractInfoResponse {
+    fn from(state: State) -> Self {
+        ContractInfoResponse {
+            admin: state.admin,
+            recipient: state.recipient,
+            denom: state.denom,
+            business_name: state.business_name,
+            overpayment: state.overpayment,
+            financing_terms: state.financing_terms,
+            financiers: state.financiers,
+            require_acceptance: state.require_acceptance,
+            signing_key: state.signing_key,
+            attribute_name: state.attribute_name,
+            payment_attributes: state.payment_attributes,
+            required_attributes: state.required_attributes,
+            contract_name: state.contract_name,
+            scope_specification_id: state.scope_specification_id,
+            denom_metadata: state.denom_metadata,
+        }
+    }
+}
+
+/// Response to `GetVersionInfo`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VersionInfoResponse {
+    pub contract: String,
+    pub version: String,
+}
+
+impl From<ContractVersion> for VersionInfoResponse {
+    fn from(version: ContractVersion) -> Self {
+        VersionInfoResponse {
+            contract: version.contract,
+            version: version.version,
+        }
+    }
+}
+
+/// Response to `GetStats`, one entry per denom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatsResponse {
+    pub stats: Vec<InvoiceStats>,
+}
+
+/// Response to `GetCredit`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CreditResponse {
+    pub address: Addr,
+    pub credit: Coin,
+}
+
+/// Response to `GetDeposit`, one coin per denom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DepositResponse {
+    pub address: Addr,
+    pub deposits: Vec<Coin>,
+}
+
+/// Response to `GetPayment`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PaymentResponse {
+    pub payment: Payment,
+}
+
+/// Response to `GetReceipt`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReceiptResponse {
+    pub receipt: PaymentReceipt,
+}
+
+// compressed or uncompressed secp256k1 public key
+fn is_public_key(key: &Binary) -> bool {
+    key.len() == 33 || key.len() == 65
+}
+
 pub trait Validate {
     fn validate(&self) -> Result<(), ContractError>;
 }
@@ -174,7 +836,7 @@ pub trait Validate {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::msg::ExecuteMsg::{AddInvoice, CancelInvoice, PayInvoice};
+    use crate::msg::ExecuteMsg::{AddInvoice, AmendInvoice, CancelInvoice, PayInvoice};
 
     #[test]
     fn validate_add_invoice() {
@@ -182,6 +844,10 @@ mod tests {
             id: "fake-id".to_string(),
             amount: Uint128::new(0),
             description: Option::Some("".to_string()),
+            due_date: None,
+            expires_at: None,
+            payer: None,
+            display_amount: None,
         };
 
         let validate_response = invalid_add_msg.validate();
@@ -200,10 +866,62 @@ mod tests {
         }
     }
 
+    #[test]
+    fn validate_add_invoice_display_amount() {
+        let add_msg = |amount: u128, display_amount: &str| AddInvoice {
+            id: "63069195-bc51-41bd-80d7-0ab84b98e283".to_string(),
+            amount: Uint128::new(amount),
+            description: None,
+            due_date: None,
+            expires_at: None,
+            payer: None,
+            display_amount: Some(display_amount.to_string()),
+        };
+
+        assert!(add_msg(0, "1.5").validate().is_ok());
+
+        for (msg, field) in [
+            (add_msg(100, "1.5"), "amount"),
+            (add_msg(0, "0.00"), "display_amount"),
+            (add_msg(0, "1.5 usd"), "display_amount"),
+        ] {
+            match msg.validate() {
+                Err(ContractError::InvalidFields { fields }) => assert_eq!(vec![field], fields),
+                result => panic!("unexpected result: {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn validate_amend_invoice() {
+        let invalid_amend_msg = AmendInvoice {
+            id: "fake-id".to_string(),
+            amount: Uint128::new(0),
+            description: Option::Some("".to_string()),
+            due_date: None,
+        };
+
+        let validate_response = invalid_amend_msg.validate();
+
+        match validate_response {
+            Ok(..) => panic!("expected error but was ok"),
+            Err(error) => match error {
+                ContractError::InvalidFields { fields } => {
+                    assert_eq!(3, fields.len());
+                    assert!(fields.contains(&"id".into()));
+                    assert!(fields.contains(&"amount".into()));
+                    assert!(fields.contains(&"description".into()));
+                }
+                error => panic!("unexpected error: {:?}", error),
+            },
+        }
+    }
+
     #[test]
     fn validate_pay_invoice() {
         let invalid_pay_msg = PayInvoice {
             id: "not-a-real-uuid".to_string(),
+            payment_ref: None,
         };
 
         let validate_response = invalid_pay_msg.validate();
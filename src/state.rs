@@ -1,7 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Storage, Uint128};
+use cosmwasm_std::{Addr, Binary, BlockInfo, Env, StdResult, Storage, Timestamp, Uint128};
 use cosmwasm_storage::{
     bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton,
     Singleton,
@@ -11,6 +11,38 @@ pub static CONFIG_KEY: &[u8] = b"config";
 
 pub static INVOICE_KEY: &[u8] = b"invoice";
 
+pub static STATS_KEY: &[u8] = b"stats";
+
+pub static LEDGER_KEY: &[u8] = b"ledger";
+
+pub static LEDGER_ACCOUNT_KEY: &[u8] = b"ledger_account";
+
+pub static CREDIT_KEY: &[u8] = b"credit";
+
+pub static DEPOSIT_KEY: &[u8] = b"deposit";
+
+pub static RECEIVABLE_KEY: &[u8] = b"receivable";
+
+pub static REVISION_KEY: &[u8] = b"revision";
+
+pub static EXPIRY_KEY: &[u8] = b"expiry";
+
+pub static EXPIRED_INVOICE_KEY: &[u8] = b"expired_invoice";
+
+pub static PAID_INVOICE_KEY: &[u8] = b"paid_invoice";
+
+pub static SIGNED_INVOICE_KEY: &[u8] = b"signed_invoice";
+
+pub static PAYMENT_KEY: &[u8] = b"payment";
+
+pub static RECEIPT_KEY: &[u8] = b"receipt";
+
+pub static PAYER_RECEIPT_KEY: &[u8] = b"payer_receipt";
+
+pub static RECEIPT_SEQUENCE_KEY: &[u8] = b"receipt_sequence";
+
+pub static DENYLIST_KEY: &[u8] = b"denylist";
+
 /// Configuration state for the restricted marker transfer contract.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -23,30 +55,598 @@ pub struct State {
     pub denom: String,
     // The human-readable name
     pub business_name: String,
+    // How funds sent in excess of an invoice are handled
+    #[serde(default)]
+    pub overpayment: OverpaymentMode,
+    // Terms offered to financiers, financing is disabled when unset
+    #[serde(default)]
+    pub financing_terms: Option<FinancingTerms>,
+    // Addresses allowed to finance invoices
+    #[serde(default)]
+    pub financiers: Vec<Addr>,
+    // Whether payers must accept invoices before they are paid or financed
+    #[serde(default)]
+    pub require_acceptance: bool,
+    // Public key signing off-chain invoices, which cannot be paid when unset
+    #[serde(default)]
+    pub signing_key: Option<Binary>,
+    // Name bound to the contract for writing attributes to payer accounts
+    #[serde(default)]
+    pub attribute_name: Option<String>,
+    // Whether a receipt attribute is written to the payer's account on payment
+    #[serde(default)]
+    pub payment_attributes: bool,
+    // Provenance attributes a payer's account must hold to pay or accept invoices
+    #[serde(default)]
+    pub required_attributes: Vec<String>,
+    // Provenance name bound to the contract address so customers can find it
+    #[serde(default)]
+    pub contract_name: Option<String>,
+    // Metadata scope specification invoices are recorded under, when set
+    #[serde(default)]
+    pub scope_specification_id: Option<String>,
+    // Display unit of the denom from the chain's bank metadata, when it has any
+    #[serde(default)]
+    pub denom_metadata: Option<DenomMetadata>,
 }
 
+/// The unit amounts of a denom are shown in, such as `hash` for `nhash`.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenomMetadata {
+    // Name of the display unit
+    pub display: String,
+    // Power of ten base units in one display unit
+    pub decimals: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OverpaymentMode {
+    // Payments must match the amount due exactly
+    Reject,
+    // Excess funds are held as credit for the sender
+    Credit,
+}
+
+// `#[default]` on enum variants needs a newer toolchain than CI pins
+#[allow(clippy::derivable_impls)]
+impl Default for OverpaymentMode {
+    fn default() -> Self {
+        OverpaymentMode::Reject
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
 pub struct Invoice {
     // Unique identifier
     pub id: String,
-    // Amount of payment expected
+    // Amount of payment expected when issued
     pub amount: Uint128,
+    // Amount still owed once credit notes are applied
+    #[serde(default)]
+    pub amount_due: Uint128,
     // The human-readable description of what it's for
     pub description: Option<String>,
+    // When payment is expected
+    #[serde(default)]
+    pub due_date: Option<Timestamp>,
+    // When the invoice closes if still unpaid
+    #[serde(default)]
+    pub expires_at: Option<Timestamp>,
+    // The customer billed, if known when the invoice is added
+    pub payer: Option<Addr>,
+    // The holder of the receivable once transferred away from the recipient
+    pub payee: Option<Addr>,
+    // The advance taken against this invoice, repaid first on payment
+    pub financing: Option<Financing>,
+    // Reductions issued against the amount, oldest first
+    #[serde(default)]
+    pub credit_notes: Vec<CreditNote>,
+    // The payer's answer to the current terms
+    #[serde(default)]
+    pub acceptance: Option<Acceptance>,
+    // Provenance metadata scope recording the invoice
+    #[serde(default)]
+    pub scope_id: Option<String>,
+}
+
+impl Invoice {
+    /// The address entitled to payment of this invoice.
+    pub fn payee<'a>(&'a self, state: &'a State) -> &'a Addr {
+        self.payee.as_ref().unwrap_or(&state.recipient)
+    }
+
+    /// Whether the payer has agreed to the current terms.
+    pub fn is_accepted(&self) -> bool {
+        matches!(self.acceptance, Some(Acceptance::Accepted { .. }))
+    }
+
+    /// Whether the invoice can no longer be paid at `block`.
+    pub fn is_expired(&self, block: &BlockInfo) -> bool {
+        matches!(self.expires_at, Some(expires_at) if block.time >= expires_at)
+    }
+}
+
+/// The payer's acknowledgement of an invoice.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Acceptance {
+    Accepted {
+        block_time: Timestamp,
+    },
+    Rejected {
+        block_time: Timestamp,
+        reason: String,
+    },
 }
 
-pub fn config(storage: &mut dyn Storage) -> Singleton<State> {
+/// The terms of an invoice before it was amended.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InvoiceRevision {
+    pub amount: Uint128,
+    pub description: Option<String>,
+    pub due_date: Option<Timestamp>,
+    // The sender of the amendment that replaced these terms
+    pub amended_by: Addr,
+    pub block_height: u64,
+    pub block_time: Timestamp,
+}
+
+/// A reduction of the amount due on an invoice.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CreditNote {
+    pub amount: Uint128,
+    // Why the invoice was reduced
+    pub reason: String,
+    pub block_height: u64,
+    pub block_time: Timestamp,
+}
+
+/// Advance and fee, in basis points of the amount due, offered to financiers.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FinancingTerms {
+    pub advance_rate_bps: u64,
+    pub fee_bps: u64,
+}
+
+impl FinancingTerms {
+    pub const BPS_DENOMINATOR: u64 = 10_000;
+
+    /// Terms are valid when the advance and fee together do not exceed the invoice amount.
+    pub fn is_valid(&self) -> bool {
+        self.advance_rate_bps > 0
+            && self.advance_rate_bps + self.fee_bps <= FinancingTerms::BPS_DENOMINATOR
+    }
+
+    /// The financing a financier receives for funding `invoice` under these terms.
+    pub fn finance(&self, invoice: &Invoice, financier: &Addr, block: &BlockInfo) -> Financing {
+        Financing {
+            financier: financier.to_owned(),
+            advance: invoice
+                .amount_due
+                .multiply_ratio(self.advance_rate_bps, FinancingTerms::BPS_DENOMINATOR),
+            fee: invoice
+                .amount_due
+                .multiply_ratio(self.fee_bps, FinancingTerms::BPS_DENOMINATOR),
+            funded_at: block.time,
+        }
+    }
+}
+
+/// An advance paid to the merchant against an open invoice.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Financing {
+    pub financier: Addr,
+    // Paid to the merchant when funded
+    pub advance: Uint128,
+    // Owed to the financier on top of the advance
+    pub fee: Uint128,
+    pub funded_at: Timestamp,
+}
+
+/// A payment processed under a client supplied reference.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Payment {
+    pub invoice_id: String,
+    pub payment_ref: String,
+    pub sender: Addr,
+    pub amount: Uint128,
+    pub denom: String,
+    pub block_height: u64,
+    pub block_time: Timestamp,
+}
+
+/// Proof that an invoice was paid, kept after the invoice is closed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PaymentReceipt {
+    // Position among all receipts issued by the contract, starting at 1
+    pub sequence: u64,
+    pub invoice_id: String,
+    pub payer: Addr,
+    pub amount: Uint128,
+    pub denom: String,
+    pub block_height: u64,
+    pub block_time: Timestamp,
+    // Position of the paying transaction in its block
+    pub tx_index: Option<u32>,
+}
+
+/// An address compliance has blocked from paying or being paid.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenylistEntry {
+    pub address: Addr,
+    pub block_height: u64,
+    pub block_time: Timestamp,
+}
+
+/// A change of ownership of an invoice receivable.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReceivableTransfer {
+    pub from: Addr,
+    pub to: Addr,
+    pub block_height: u64,
+    pub block_time: Timestamp,
+}
+
+/// Running totals of invoices billed in a single denom.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct InvoiceStats {
+    // The marker the invoices were billed in
+    pub denom: String,
+    // Invoices awaiting payment
+    pub open: Tally,
+    // Invoices settled by pay_invoice
+    pub paid: Tally,
+    // Invoices removed by cancel_invoice
+    pub cancelled: Tally,
+    // Invoices closed by prune_expired
+    #[serde(default)]
+    pub expired: Tally,
+    // Expired invoices that were transferred or financed, a loss to their holder or financier
+    #[serde(default)]
+    pub written_off: Tally,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Tally {
+    pub count: u64,
+    pub amount: Uint128,
+}
+
+impl Tally {
+    pub fn add(&mut self, amount: Uint128) -> StdResult<()> {
+        self.count += 1;
+        self.amount = self.amount.checked_add(amount)?;
+        Ok(())
+    }
+
+    pub fn sub(&mut self, amount: Uint128) -> StdResult<()> {
+        self.count = self.count.saturating_sub(1);
+        self.amount = self.amount.checked_sub(amount)?;
+        Ok(())
+    }
+
+    /// Lower the total without changing the number of invoices counted.
+    pub fn reduce(&mut self, amount: Uint128) -> StdResult<()> {
+        self.amount = self.amount.checked_sub(amount)?;
+        Ok(())
+    }
+}
+
+/// A single movement on a payer's account.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LedgerEntry {
+    // Position in the payer's ledger, starting at 1
+    pub sequence: u64,
+    // The invoice the entry relates to
+    pub invoice_id: String,
+    pub kind: LedgerEntryKind,
+    pub amount: Uint128,
+    pub denom: String,
+    // Outstanding balance after this entry was applied
+    pub balance: Uint128,
+    pub block_height: u64,
+    pub block_time: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerEntryKind {
+    // An invoice billed to the payer, increases the balance
+    Invoice,
+    // A payment made by the payer, decreases the balance
+    Payment,
+    // An amount forgiven by the merchant, decreases the balance
+    Credit,
+    // Funds returned to the payer, increases the balance
+    Refund,
+}
+
+impl LedgerEntry {
+    pub fn new(
+        invoice_id: &str,
+        kind: LedgerEntryKind,
+        amount: Uint128,
+        denom: &str,
+        block: &BlockInfo,
+    ) -> Self {
+        LedgerEntry {
+            sequence: 0,
+            invoice_id: invoice_id.into(),
+            kind,
+            amount,
+            denom: denom.into(),
+            balance: Uint128::zero(),
+            block_height: block.height,
+            block_time: block.time,
+        }
+    }
+}
+
+/// Summary of a payer's ledger.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct LedgerAccount {
+    // Number of entries recorded
+    pub entries: u64,
+    // Current outstanding balance
+    pub balance: Uint128,
+}
+
+pub fn config(storage: &mut dyn Storage) -> Singleton<'_, State> {
     singleton(storage, CONFIG_KEY)
 }
 
-pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<State> {
+pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, State> {
     singleton_read(storage, CONFIG_KEY)
 }
 
-pub fn get_invoice_storage(storage: &mut dyn Storage) -> Bucket<Invoice> {
+pub fn get_invoice_storage(storage: &mut dyn Storage) -> Bucket<'_, Invoice> {
     bucket(storage, INVOICE_KEY)
 }
 
-pub fn get_invoice_storage_read(storage: &dyn Storage) -> ReadonlyBucket<Invoice> {
+pub fn get_invoice_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, Invoice> {
     bucket_read(storage, INVOICE_KEY)
 }
+
+pub fn get_stats_storage(storage: &mut dyn Storage) -> Bucket<'_, InvoiceStats> {
+    bucket(storage, STATS_KEY)
+}
+
+pub fn get_stats_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, InvoiceStats> {
+    bucket_read(storage, STATS_KEY)
+}
+
+/// Apply `action` to the running totals kept for `denom`.
+pub fn update_stats<A>(storage: &mut dyn Storage, denom: &str, action: A) -> StdResult<()>
+where
+    A: FnOnce(&mut InvoiceStats) -> StdResult<()>,
+{
+    get_stats_storage(storage).update(denom.as_bytes(), |stats| -> StdResult<_> {
+        let mut stats = stats.unwrap_or_else(|| InvoiceStats {
+            denom: denom.into(),
+            ..InvoiceStats::default()
+        });
+        action(&mut stats)?;
+        Ok(stats)
+    })?;
+    Ok(())
+}
+
+pub fn get_ledger_storage<'a>(
+    storage: &'a mut dyn Storage,
+    payer: &Addr,
+) -> Bucket<'a, LedgerEntry> {
+    Bucket::multilevel(storage, &[LEDGER_KEY, payer.as_bytes()])
+}
+
+pub fn get_ledger_storage_read<'a>(
+    storage: &'a dyn Storage,
+    payer: &Addr,
+) -> ReadonlyBucket<'a, LedgerEntry> {
+    ReadonlyBucket::multilevel(storage, &[LEDGER_KEY, payer.as_bytes()])
+}
+
+pub fn get_ledger_account_storage(storage: &mut dyn Storage) -> Bucket<'_, LedgerAccount> {
+    bucket(storage, LEDGER_ACCOUNT_KEY)
+}
+
+pub fn get_ledger_account_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, LedgerAccount> {
+    bucket_read(storage, LEDGER_ACCOUNT_KEY)
+}
+
+/// Append `entry` to the payer's ledger, assigning its sequence and running balance.
+pub fn append_ledger_entry(
+    storage: &mut dyn Storage,
+    payer: &Addr,
+    mut entry: LedgerEntry,
+) -> StdResult<LedgerEntry> {
+    let mut account = get_ledger_account_storage_read(storage)
+        .may_load(payer.as_bytes())?
+        .unwrap_or_default();
+
+    account.entries += 1;
+    account.balance = match entry.kind {
+        LedgerEntryKind::Invoice | LedgerEntryKind::Refund => {
+            account.balance.checked_add(entry.amount)?
+        }
+        LedgerEntryKind::Payment | LedgerEntryKind::Credit => {
+            account.balance.checked_sub(entry.amount)?
+        }
+    };
+
+    entry.sequence = account.entries;
+    entry.balance = account.balance;
+
+    get_ledger_storage(storage, payer).save(&entry.sequence.to_be_bytes(), &entry)?;
+    get_ledger_account_storage(storage).save(payer.as_bytes(), &account)?;
+
+    Ok(entry)
+}
+
+pub fn get_credit_storage(storage: &mut dyn Storage) -> Bucket<'_, Uint128> {
+    bucket(storage, CREDIT_KEY)
+}
+
+pub fn get_credit_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, Uint128> {
+    bucket_read(storage, CREDIT_KEY)
+}
+
+pub fn get_deposit_storage<'a>(storage: &'a mut dyn Storage, payer: &Addr) -> Bucket<'a, Uint128> {
+    Bucket::multilevel(storage, &[DEPOSIT_KEY, payer.as_bytes()])
+}
+
+pub fn get_deposit_storage_read<'a>(
+    storage: &'a dyn Storage,
+    payer: &Addr,
+) -> ReadonlyBucket<'a, Uint128> {
+    ReadonlyBucket::multilevel(storage, &[DEPOSIT_KEY, payer.as_bytes()])
+}
+
+pub fn get_receivable_storage(storage: &mut dyn Storage) -> Bucket<'_, Vec<ReceivableTransfer>> {
+    bucket(storage, RECEIVABLE_KEY)
+}
+
+pub fn get_receivable_storage_read(
+    storage: &dyn Storage,
+) -> ReadonlyBucket<'_, Vec<ReceivableTransfer>> {
+    bucket_read(storage, RECEIVABLE_KEY)
+}
+
+pub fn get_revision_storage(storage: &mut dyn Storage) -> Bucket<'_, Vec<InvoiceRevision>> {
+    bucket(storage, REVISION_KEY)
+}
+
+pub fn get_revision_storage_read(
+    storage: &dyn Storage,
+) -> ReadonlyBucket<'_, Vec<InvoiceRevision>> {
+    bucket_read(storage, REVISION_KEY)
+}
+
+/// Invoice ids indexed by expiry time, so expired invoices can be found oldest first.
+pub fn get_expiry_storage(storage: &mut dyn Storage) -> Bucket<'_, String> {
+    bucket(storage, EXPIRY_KEY)
+}
+
+pub fn get_expiry_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, String> {
+    bucket_read(storage, EXPIRY_KEY)
+}
+
+/// Invoices closed by prune_expired, kept so they can still be looked up and their ids stay used.
+pub fn get_expired_invoice_storage(storage: &mut dyn Storage) -> Bucket<'_, Invoice> {
+    bucket(storage, EXPIRED_INVOICE_KEY)
+}
+
+pub fn get_expired_invoice_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, Invoice> {
+    bucket_read(storage, EXPIRED_INVOICE_KEY)
+}
+
+/// Invoices as they stood when paid, kept so their payee and terms can still be looked up.
+pub fn get_paid_invoice_storage(storage: &mut dyn Storage) -> Bucket<'_, Invoice> {
+    bucket(storage, PAID_INVOICE_KEY)
+}
+
+pub fn get_paid_invoice_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, Invoice> {
+    bucket_read(storage, PAID_INVOICE_KEY)
+}
+
+pub fn expiry_key(expires_at: Timestamp, id: &str) -> Vec<u8> {
+    [&expires_at.nanos().to_be_bytes(), id.as_bytes()].concat()
+}
+
+/// Ids of paid signed invoices, by the block height they were paid at.
+pub fn get_signed_invoice_storage(storage: &mut dyn Storage) -> Bucket<'_, u64> {
+    bucket(storage, SIGNED_INVOICE_KEY)
+}
+
+pub fn get_signed_invoice_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, u64> {
+    bucket_read(storage, SIGNED_INVOICE_KEY)
+}
+
+/// Payments processed against an invoice, keyed by payment reference.
+pub fn get_payment_storage<'a>(
+    storage: &'a mut dyn Storage,
+    invoice_id: &str,
+) -> Bucket<'a, Payment> {
+    Bucket::multilevel(storage, &[PAYMENT_KEY, invoice_id.as_bytes()])
+}
+
+pub fn get_payment_storage_read<'a>(
+    storage: &'a dyn Storage,
+    invoice_id: &str,
+) -> ReadonlyBucket<'a, Payment> {
+    ReadonlyBucket::multilevel(storage, &[PAYMENT_KEY, invoice_id.as_bytes()])
+}
+
+/// Receipts for an invoice, keyed by sequence.
+pub fn get_receipt_storage<'a>(
+    storage: &'a mut dyn Storage,
+    invoice_id: &str,
+) -> Bucket<'a, PaymentReceipt> {
+    Bucket::multilevel(storage, &[RECEIPT_KEY, invoice_id.as_bytes()])
+}
+
+pub fn get_receipt_storage_read<'a>(
+    storage: &'a dyn Storage,
+    invoice_id: &str,
+) -> ReadonlyBucket<'a, PaymentReceipt> {
+    ReadonlyBucket::multilevel(storage, &[RECEIPT_KEY, invoice_id.as_bytes()])
+}
+
+/// Receipts for a payer, keyed by sequence.
+pub fn get_payer_receipt_storage<'a>(
+    storage: &'a mut dyn Storage,
+    payer: &Addr,
+) -> Bucket<'a, PaymentReceipt> {
+    Bucket::multilevel(storage, &[PAYER_RECEIPT_KEY, payer.as_bytes()])
+}
+
+pub fn get_payer_receipt_storage_read<'a>(
+    storage: &'a dyn Storage,
+    payer: &Addr,
+) -> ReadonlyBucket<'a, PaymentReceipt> {
+    ReadonlyBucket::multilevel(storage, &[PAYER_RECEIPT_KEY, payer.as_bytes()])
+}
+
+/// Issue the next receipt for a payment of `amount` on `invoice_id`, indexed by both the
+/// invoice and the payer.
+pub fn issue_receipt(
+    storage: &mut dyn Storage,
+    env: &Env,
+    invoice_id: &str,
+    payer: &Addr,
+    amount: Uint128,
+    denom: &str,
+) -> StdResult<PaymentReceipt> {
+    let sequence = singleton_read::<u64>(storage, RECEIPT_SEQUENCE_KEY)
+        .may_load()?
+        .unwrap_or_default()
+        + 1;
+    singleton(storage, RECEIPT_SEQUENCE_KEY).save(&sequence)?;
+
+    let receipt = PaymentReceipt {
+        sequence,
+        invoice_id: invoice_id.into(),
+        payer: payer.to_owned(),
+        amount,
+        denom: denom.into(),
+        block_height: env.block.height,
+        block_time: env.block.time,
+        tx_index: env
+            .transaction
+            .as_ref()
+            .map(|transaction| transaction.index),
+    };
+
+    get_receipt_storage(storage, invoice_id).save(&sequence.to_be_bytes(), &receipt)?;
+    get_payer_receipt_storage(storage, payer).save(&sequence.to_be_bytes(), &receipt)?;
+
+    Ok(receipt)
+}
+
+pub fn get_denylist_storage(storage: &mut dyn Storage) -> Bucket<'_, DenylistEntry> {
+    bucket(storage, DENYLIST_KEY)
+}
+
+pub fn get_denylist_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, DenylistEntry> {
+    bucket_read(storage, DENYLIST_KEY)
+}